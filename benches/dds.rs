@@ -0,0 +1,107 @@
+//! Decoding and encoding of block compressed DDS surfaces.
+//!
+//! Run with and without `--features simd` to compare the batched BC1 and BC4 decoders with the
+//! scalar ones. The 4096x4096 case is the size of a large texture. The encoding benchmarks compare
+//! the qualities of the BC1 encoder on 256x256 pixels of smooth gradients with noise.
+
+use std::io::Cursor;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use image::codecs::dds::{blocks, DdsDecoder, EncodeQuality};
+use image::ImageDecoder;
+
+/// Builds a file of a format with 8-byte blocks, like BC1 and BC4, with pseudo-random blocks, so
+/// both modes of the format and all indexes occur.
+fn block_file(four_cc: &[u8; 4], width: u32, height: u32) -> Vec<u8> {
+    let mut file = b"DDS ".to_vec();
+    // DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT
+    for value in [124, 0x1007, height, width, 0, 0, 0] {
+        file.extend(u32::to_le_bytes(value));
+    }
+    file.extend([0; 44]);
+    // DDPF_FOURCC
+    for value in [32, 0x4, u32::from_le_bytes(*four_cc), 0, 0, 0, 0, 0] {
+        file.extend(u32::to_le_bytes(value));
+    }
+    // DDSCAPS_TEXTURE
+    for value in [0x1000, 0, 0, 0, 0] {
+        file.extend(u32::to_le_bytes(value));
+    }
+
+    let blocks = width.div_ceil(4) * height.div_ceil(4);
+    let mut state = 0x2545_F491_4F6C_DD1D_u64;
+    for _ in 0..blocks {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        file.extend(state.to_le_bytes());
+    }
+    file
+}
+
+fn decode_bc1(c: &mut Criterion) {
+    decode(c, "dds-bc1", b"DXT1", 4);
+}
+
+fn decode_bc4(c: &mut Criterion) {
+    decode(c, "dds-bc4", b"BC4U", 1);
+}
+
+fn decode(c: &mut Criterion, name: &str, four_cc: &[u8; 4], channels: u32) {
+    let mut group = c.benchmark_group(name);
+    for size in [256, 1024, 4096] {
+        let file = block_file(four_cc, size, size);
+        let mut buf = vec![0; (size * size * channels) as usize];
+        group.throughput(Throughput::Elements(u64::from(size * size)));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &file, |b, file| {
+            b.iter(|| {
+                let decoder = DdsDecoder::new(Cursor::new(file)).unwrap();
+                decoder.read_image(&mut buf).unwrap();
+            });
+        });
+    }
+    group.finish();
+}
+
+fn encode_bc1(c: &mut Criterion) {
+    let mut state = 0x2545_F491_4F6C_DD1D_u64;
+    let blocks: Vec<[[u8; 4]; 16]> = (0..64 * 64)
+        .map(|i| {
+            std::array::from_fn(|p| {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let noise = (state % 16) as u8;
+                let (x, y) = ((i % 64 * 4 + p % 4) as u8, (i / 64 * 4 + p / 4) as u8);
+                [x.wrapping_add(noise), y, 255 - x / 2 - y / 2, 255]
+            })
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("dds-encode-bc1");
+    group.throughput(Throughput::Elements(256 * 256));
+    for quality in [
+        EncodeQuality::Fast,
+        EncodeQuality::Balanced,
+        EncodeQuality::Best,
+    ] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{quality:?}")),
+            &blocks,
+            |b, blocks| {
+                b.iter(|| {
+                    blocks
+                        .iter()
+                        .map(|&pixels| blocks::encode_bc1_with_quality(pixels, quality))
+                        .collect::<Vec<_>>()
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode_bc1, decode_bc4, encode_bc1);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate image;
+
+use image::codecs::dds::DdsDecoder;
+use image::{ImageDecoder, Limits};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode(data);
+    let _ = decode_mipmaps(data);
+});
+
+fn decode(data: &[u8]) -> Result<(), image::ImageError> {
+    let mut decoder = DdsDecoder::new(std::io::Cursor::new(data))?;
+    decoder.set_limits(Limits::default())?;
+    if decoder.total_bytes() > 4_000_000 {
+        return Ok(());
+    }
+    let mut buffer = vec![0; decoder.total_bytes() as usize];
+    decoder.read_image(&mut buffer)?;
+    Ok(())
+}
+
+fn decode_mipmaps(data: &[u8]) -> Result<(), image::ImageError> {
+    let mut decoder = DdsDecoder::new(std::io::Cursor::new(data))?;
+    decoder.set_limits(Limits::default())?;
+    // The main surface is the largest face and mip level.
+    if decoder.total_bytes() > 4_000_000 {
+        return Ok(());
+    }
+    for face in decoder.cubemap_faces().collect::<Vec<_>>() {
+        decoder.read_cubemap_face(face).transpose()?;
+    }
+    for mipmap in decoder.into_mipmaps() {
+        mipmap?;
+    }
+    Ok(())
+}
@@ -0,0 +1,1920 @@
+//! Decoders for individual 4x4 blocks of the block compressed (BC) formats.
+//!
+//! All decoders return the 16 pixels of a block in row-major order, i.e. the pixel at `(x, y)`
+//! within the block is at index `y * 4 + x`.
+
+use super::bc7_tables::{ANCHOR_2, ANCHOR_3_1, ANCHOR_3_2, PARTITIONS_2, PARTITIONS_3};
+use super::convert::{bc4_interp_4, bc4_interp_6, half_to_f32, snorm8_to_unorm8, x4_to_x8, B5G6R5};
+
+/// Reads bits from a 128-bit block, least significant bit first.
+struct BitReader {
+    bits: u128,
+}
+
+impl BitReader {
+    fn new(block_bytes: [u8; 16]) -> Self {
+        Self {
+            bits: u128::from_le_bytes(block_bytes),
+        }
+    }
+
+    fn skip(&mut self, count: u8) {
+        self.bits >>= count;
+    }
+
+    fn read(&mut self, count: u8) -> u8 {
+        debug_assert!(count <= 8);
+        self.read_u16(count) as u8
+    }
+
+    fn read_u16(&mut self, count: u8) -> u16 {
+        debug_assert!(count <= 16);
+        let value = (self.bits & ((1 << count) - 1)) as u16;
+        self.bits >>= count;
+        value
+    }
+}
+
+/// Returns `(w0 * c0 + w1 * c1) / (w0 + w1)` expanded to 8 bits per channel with full alpha.
+///
+/// The components are interpolated before they are expanded, so the result is exact up to a
+/// single rounding to the nearest integer.
+fn interpolate_b5g6r5(c0: B5G6R5, c1: B5G6R5, w0: u32, w1: u32) -> [u8; 4] {
+    let interpolate = |x0: u8, x1: u8, max: u32| {
+        let numerator = (w0 * x0 as u32 + w1 * x1 as u32) * 255;
+        let denominator = (w0 + w1) * max;
+        ((2 * numerator + denominator) / (2 * denominator)) as u8
+    };
+    [
+        interpolate(c0.r5, c1.r5, 31),
+        interpolate(c0.g6, c1.g6, 63),
+        interpolate(c0.b5, c1.b5, 31),
+        255,
+    ]
+}
+
+/// Returns the 4 colors a BC1 block with the given endpoints can use.
+///
+/// If `punch_through` is set and `color0 <= color1`, the block uses the 3-color mode and index 3
+/// is transparent black. Otherwise, the block always uses the opaque 4-color mode.
+pub(crate) fn bc1_palette(color0: u16, color1: u16, punch_through: bool) -> [[u8; 4]; 4] {
+    let c0 = B5G6R5::from_u16(color0);
+    let c1 = B5G6R5::from_u16(color1);
+    if color0 > color1 || !punch_through {
+        [
+            c0.to_rgba8(),
+            c1.to_rgba8(),
+            interpolate_b5g6r5(c0, c1, 2, 1),
+            interpolate_b5g6r5(c0, c1, 1, 2),
+        ]
+    } else {
+        [
+            c0.to_rgba8(),
+            c1.to_rgba8(),
+            interpolate_b5g6r5(c0, c1, 1, 1),
+            [0, 0, 0, 0],
+        ]
+    }
+}
+
+/// Decodes a BC1 block.
+///
+/// If `color0 <= color1`, the block uses the 3-color mode and index 3 is transparent black.
+pub(crate) fn decode_bc1_block(block_bytes: [u8; 8]) -> [[u8; 4]; 16] {
+    decode_bc1_color_block(block_bytes, true)
+}
+
+/// Decodes a BC1 block like [`decode_bc1_block`], but index 3 of the 3-color mode is opaque black.
+pub(crate) fn decode_bc1_block_opaque(block_bytes: [u8; 8]) -> [[u8; 4]; 16] {
+    // All other colors of the palette are opaque.
+    decode_bc1_block(block_bytes).map(|[r, g, b, _]| [r, g, b, 255])
+}
+
+/// Decodes the color of a BC1 block, using the 3-color mode only if `punch_through` is set.
+///
+/// BC2 and BC3 store alpha separately, so their color blocks always use the 4-color mode,
+/// regardless of the order of the endpoints.
+fn decode_bc1_color_block(block_bytes: [u8; 8], punch_through: bool) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block_bytes[0], block_bytes[1]]);
+    let color1 = u16::from_le_bytes([block_bytes[2], block_bytes[3]]);
+    let indexes = u32::from_le_bytes([
+        block_bytes[4],
+        block_bytes[5],
+        block_bytes[6],
+        block_bytes[7],
+    ]);
+
+    let palette = bc1_palette(color0, color1, punch_through);
+    std::array::from_fn(|i| palette[(indexes >> (i * 2)) as usize & 0b11])
+}
+
+/// Splits a 16-byte block into the two 8-byte blocks it consists of.
+fn split_block(block_bytes: [u8; 16]) -> [[u8; 8]; 2] {
+    bytemuck::cast(block_bytes)
+}
+
+/// Decodes a BC2 block. The first 8 bytes hold 4-bit alpha values, the last 8 bytes a BC1 color
+/// block in the 4-color mode.
+pub(crate) fn decode_bc2_block(block_bytes: [u8; 16]) -> [[u8; 4]; 16] {
+    let [alpha, color] = split_block(block_bytes);
+    let alpha = u64::from_le_bytes(alpha);
+    let mut pixels = decode_bc1_color_block(color, false);
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        pixel[3] = x4_to_x8((alpha >> (i * 4)) as u8 & 0xF);
+    }
+    pixels
+}
+
+/// Decodes a BC3 block. The first 8 bytes hold a BC4 block for alpha, the last 8 bytes a BC1
+/// color block in the 4-color mode.
+pub(crate) fn decode_bc3_block(block_bytes: [u8; 16]) -> [[u8; 4]; 16] {
+    let [alpha, color] = split_block(block_bytes);
+    let alpha = decode_bc4_unsigned_block(alpha);
+    let mut pixels = decode_bc1_color_block(color, false);
+    for (pixel, [a]) in pixels.iter_mut().zip(alpha) {
+        pixel[3] = a;
+    }
+    pixels
+}
+
+/// Returns the 3-bit indexes of a BC4 block.
+fn bc4_indexes(block_bytes: [u8; 8]) -> [u8; 16] {
+    let bits = u64::from_le_bytes(block_bytes) >> 16;
+    std::array::from_fn(|i| (bits >> (i * 3)) as u8 & 0b111)
+}
+
+/// Returns the 8 values a BC4 block of the unsigned format with the given endpoints can use.
+///
+/// If `red0 <= red1`, only 4 values are interpolated and the last two values are 0 and 255.
+pub(crate) fn bc4_unsigned_palette(red0: u8, red1: u8) -> [u8; 8] {
+    if red0 > red1 {
+        [
+            red0,
+            red1,
+            bc4_interp_6(red0, red1, 1),
+            bc4_interp_6(red0, red1, 2),
+            bc4_interp_6(red0, red1, 3),
+            bc4_interp_6(red0, red1, 4),
+            bc4_interp_6(red0, red1, 5),
+            bc4_interp_6(red0, red1, 6),
+        ]
+    } else {
+        [
+            red0,
+            red1,
+            bc4_interp_4(red0, red1, 1),
+            bc4_interp_4(red0, red1, 2),
+            bc4_interp_4(red0, red1, 3),
+            bc4_interp_4(red0, red1, 4),
+            0,
+            255,
+        ]
+    }
+}
+
+/// Decodes a BC4 block of the unsigned format (`BC4_UNORM`).
+pub(crate) fn decode_bc4_unsigned_block(block_bytes: [u8; 8]) -> [[u8; 1]; 16] {
+    let palette = bc4_unsigned_palette(block_bytes[0], block_bytes[1]);
+    bc4_indexes(block_bytes).map(|index| [palette[index as usize]])
+}
+
+/// Returns the endpoints of a BC4 block of the signed format mapped to `0..=254`, and whether
+/// the block uses 6 interpolated values.
+///
+/// -128 and -127 both represent -1.0, so both are mapped to 0. This allows interpolating on
+/// unsigned values.
+fn bc4_signed_endpoints(block_bytes: [u8; 8]) -> (u8, u8, bool) {
+    fn unbias(x: i8) -> u8 {
+        (x.max(-127) as i16 + 127) as u8
+    }
+
+    // The mode is selected by the raw endpoints, like in the D3D reference decoder. Comparing the
+    // mapped endpoints would pick the wrong mode for -127 and -128.
+    let [red0, red1] = [block_bytes[0] as i8, block_bytes[1] as i8];
+    (unbias(red0), unbias(red1), red0 > red1)
+}
+
+/// Returns the 8 values a BC4 block of the signed format with the given endpoints can use, all
+/// mapped to `0..=254` like the endpoints.
+///
+/// If not `six_interpolants`, only 4 values are interpolated and the last two values are -1.0 and
+/// 1.0.
+pub(crate) fn bc4_signed_palette(red0: u8, red1: u8, six_interpolants: bool) -> [u8; 8] {
+    if six_interpolants {
+        [
+            red0,
+            red1,
+            bc4_interp_6(red0, red1, 1),
+            bc4_interp_6(red0, red1, 2),
+            bc4_interp_6(red0, red1, 3),
+            bc4_interp_6(red0, red1, 4),
+            bc4_interp_6(red0, red1, 5),
+            bc4_interp_6(red0, red1, 6),
+        ]
+    } else {
+        [
+            red0,
+            red1,
+            bc4_interp_4(red0, red1, 1),
+            bc4_interp_4(red0, red1, 2),
+            bc4_interp_4(red0, red1, 3),
+            bc4_interp_4(red0, red1, 4),
+            0,
+            254,
+        ]
+    }
+}
+
+/// Decodes a BC4 block of the signed format (`BC4_SNORM`) and maps the result to unorm.
+pub(crate) fn decode_bc4_signed_block(block_bytes: [u8; 8]) -> [[u8; 1]; 16] {
+    let (red0, red1, six_interpolants) = bc4_signed_endpoints(block_bytes);
+    let palette = bc4_signed_palette(red0, red1, six_interpolants);
+    bc4_indexes(block_bytes).map(|index| [snorm8_to_unorm8(palette[index as usize])])
+}
+
+/// Decodes a BC4 block of the signed format (`BC4_SNORM`) into signed values in `-127..=127`.
+///
+/// Unlike [`decode_bc4_signed_block`], the values aren't mapped to unorm, so 0.0 stays 0. The
+/// values are interpolated as floats and rounded to the nearest integer. -128 is -1.0 like -127,
+/// so it's decoded as -127.
+pub(crate) fn decode_bc4_signed_block_snorm(block_bytes: [u8; 8]) -> [[i8; 1]; 16] {
+    let (red0, red1, six_interpolants) = bc4_signed_endpoints(block_bytes);
+    let [red0, red1] = [red0, red1].map(|x| x as f32 - 127.0);
+    let interpolate =
+        |factor: f32, count: f32| ((red0 * (count - factor) + red1 * factor) / count).round() as i8;
+    let palette = if six_interpolants {
+        [0.0, 7.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0].map(|factor| interpolate(factor, 7.0))
+    } else {
+        let [a, b, c, d, e, f] =
+            [0.0, 5.0, 1.0, 2.0, 3.0, 4.0].map(|factor| interpolate(factor, 5.0));
+        [a, b, c, d, e, f, -127, 127]
+    };
+    bc4_indexes(block_bytes).map(|index| [palette[index as usize]])
+}
+
+/// Decodes a BC5 block of the unsigned format (`BC5_UNORM`). Blue is always 0.
+pub(crate) fn decode_bc5_unsigned_block(block_bytes: [u8; 16]) -> [[u8; 3]; 16] {
+    decode_bc5_unsigned_block_rg(block_bytes).map(|[r, g]| [r, g, 0])
+}
+
+/// Decodes a BC5 block of the unsigned format (`BC5_UNORM`) into its two channels.
+pub(crate) fn decode_bc5_unsigned_block_rg(block_bytes: [u8; 16]) -> [[u8; 2]; 16] {
+    let [red, green] = split_block(block_bytes);
+    let red = decode_bc4_unsigned_block(red);
+    let green = decode_bc4_unsigned_block(green);
+    std::array::from_fn(|i| [red[i][0], green[i][0]])
+}
+
+/// Decodes a BC5 block of the signed format (`BC5_SNORM`) and maps the result to unorm. Blue is
+/// always 128, which is 0.0 in snorm.
+pub(crate) fn decode_bc5_signed_block(block_bytes: [u8; 16]) -> [[u8; 3]; 16] {
+    decode_bc5_signed_block_rg(block_bytes).map(|[r, g]| [r, g, 128])
+}
+
+/// Decodes a BC5 block of the signed format (`BC5_SNORM`) into its two channels and maps them to
+/// unorm.
+pub(crate) fn decode_bc5_signed_block_rg(block_bytes: [u8; 16]) -> [[u8; 2]; 16] {
+    let [red, green] = split_block(block_bytes);
+    let red = decode_bc4_signed_block(red);
+    let green = decode_bc4_signed_block(green);
+    std::array::from_fn(|i| [red[i][0], green[i][0]])
+}
+
+/// Computes the blue channel of a unit normal from its red and green channels.
+///
+/// All channels map 0..=255 to -1.0..=1.0. Z is assumed to be positive, so blue is at least 128.
+fn reconstruct_normal_z(red: u8, green: u8) -> u8 {
+    let x = red as f32 / 127.5 - 1.0;
+    let y = green as f32 / 127.5 - 1.0;
+    // Quantization can push x² + y² slightly above 1.
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    ((z + 1.0) * 127.5).round() as u8
+}
+
+/// Decodes a BC5 block of the unsigned format (`BC5_UNORM`) as a normal map. Blue is
+/// reconstructed from red and green, assuming a unit normal.
+pub(crate) fn decode_bc5_unsigned_block_normal(block_bytes: [u8; 16]) -> [[u8; 3]; 16] {
+    decode_bc5_unsigned_block(block_bytes).map(|[r, g, _]| [r, g, reconstruct_normal_z(r, g)])
+}
+
+/// Decodes a BC5 block of the signed format (`BC5_SNORM`) as a normal map and maps the result to
+/// unorm. Blue is reconstructed from red and green, assuming a unit normal.
+pub(crate) fn decode_bc5_signed_block_normal(block_bytes: [u8; 16]) -> [[u8; 3]; 16] {
+    decode_bc5_signed_block(block_bytes).map(|[r, g, _]| [r, g, reconstruct_normal_z(r, g)])
+}
+
+/// Returns the 8 values a BC4 block with endpoints in `0..=max` can use, scaled to 16 bits.
+///
+/// The values are interpolated and scaled before rounding, so they are only rounded once.
+fn bc4_palette_u16(red0: u8, red1: u8, six_interpolants: bool, max: u32) -> [u16; 8] {
+    // Returns `(c0 + (c1 - c0) * factor / count) * 65535 / max`, rounded to the nearest integer.
+    let interpolate = |factor: u32, count: u32| {
+        let sum = (count - factor) * red0 as u32 + factor * red1 as u32;
+        let divisor = count * max;
+        ((sum * 65535 + divisor / 2) / divisor) as u16
+    };
+
+    if six_interpolants {
+        [0, 7, 1, 2, 3, 4, 5, 6].map(|factor| interpolate(factor, 7))
+    } else {
+        let [a, b, c, d, e, f] = [0, 5, 1, 2, 3, 4].map(|factor| interpolate(factor, 5));
+        [a, b, c, d, e, f, 0, u16::MAX]
+    }
+}
+
+/// Decodes a BC4 block of the unsigned format (`BC4_UNORM`) to 16 bits.
+pub(crate) fn decode_bc4_unsigned_block_u16(block_bytes: [u8; 8]) -> [[u16; 1]; 16] {
+    let [red0, red1] = [block_bytes[0], block_bytes[1]];
+    let palette = bc4_palette_u16(red0, red1, red0 > red1, 255);
+    bc4_indexes(block_bytes).map(|index| [palette[index as usize]])
+}
+
+/// Decodes a BC4 block of the signed format (`BC4_SNORM`) to 16 bits and maps the result to
+/// unorm.
+pub(crate) fn decode_bc4_signed_block_u16(block_bytes: [u8; 8]) -> [[u16; 1]; 16] {
+    let (red0, red1, six_interpolants) = bc4_signed_endpoints(block_bytes);
+    let palette = bc4_palette_u16(red0, red1, six_interpolants, 254);
+    bc4_indexes(block_bytes).map(|index| [palette[index as usize]])
+}
+
+/// Decodes a BC5 block of the unsigned format (`BC5_UNORM`) to 16 bits. Blue is always 0.
+pub(crate) fn decode_bc5_unsigned_block_u16(block_bytes: [u8; 16]) -> [[u16; 3]; 16] {
+    let [red, green] = split_block(block_bytes);
+    let red = decode_bc4_unsigned_block_u16(red);
+    let green = decode_bc4_unsigned_block_u16(green);
+    std::array::from_fn(|i| [red[i][0], green[i][0], 0])
+}
+
+/// Decodes a BC5 block of the signed format (`BC5_SNORM`) to 16 bits and maps the result to
+/// unorm. Blue is always 32768, which is 0.0 in snorm.
+pub(crate) fn decode_bc5_signed_block_u16(block_bytes: [u8; 16]) -> [[u16; 3]; 16] {
+    let [red, green] = split_block(block_bytes);
+    let red = decode_bc4_signed_block_u16(red);
+    let green = decode_bc4_signed_block_u16(green);
+    std::array::from_fn(|i| [red[i][0], green[i][0], 32768])
+}
+
+/// Like [`reconstruct_normal_z`], but all channels map 0..=65535 to -1.0..=1.0.
+fn reconstruct_normal_z_u16(red: u16, green: u16) -> u16 {
+    let x = red as f32 / 32767.5 - 1.0;
+    let y = green as f32 / 32767.5 - 1.0;
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    ((z + 1.0) * 32767.5).round() as u16
+}
+
+/// Decodes a BC5 block of the unsigned format (`BC5_UNORM`) as a normal map with 16 bits. Blue
+/// is reconstructed from red and green, assuming a unit normal.
+pub(crate) fn decode_bc5_unsigned_block_normal_u16(block_bytes: [u8; 16]) -> [[u16; 3]; 16] {
+    decode_bc5_unsigned_block_u16(block_bytes)
+        .map(|[r, g, _]| [r, g, reconstruct_normal_z_u16(r, g)])
+}
+
+/// Decodes a BC5 block of the signed format (`BC5_SNORM`) as a normal map with 16 bits and maps
+/// the result to unorm. Blue is reconstructed from red and green, assuming a unit normal.
+pub(crate) fn decode_bc5_signed_block_normal_u16(block_bytes: [u8; 16]) -> [[u16; 3]; 16] {
+    decode_bc5_signed_block_u16(block_bytes).map(|[r, g, _]| [r, g, reconstruct_normal_z_u16(r, g)])
+}
+
+/// The parameters of a BC7 mode.
+///
+/// See <https://learn.microsoft.com/en-us/windows/win32/direct3d11/bc7-format-mode-reference>.
+struct Bc7Mode {
+    subsets: u8,
+    partition_bits: u8,
+    rotation_bits: u8,
+    index_selection_bits: u8,
+    color_bits: u8,
+    alpha_bits: u8,
+    /// One P-bit per endpoint.
+    endpoint_p_bits: bool,
+    /// One P-bit per subset, shared by both endpoints of the subset.
+    shared_p_bits: bool,
+    index_bits: u8,
+    secondary_index_bits: u8,
+}
+
+#[rustfmt::skip]
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_p_bits: true,  shared_p_bits: false, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_p_bits: false, shared_p_bits: true,  index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_p_bits: true,  shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_p_bits: true,  shared_p_bits: false, index_bits: 4, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_p_bits: true,  shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+];
+
+const BC7_WEIGHTS_2: [u8; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS_3: [u8; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS_4: [u8; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Returns the interpolation weight (out of 64) of an index with the given number of bits. BC6H
+/// and BC7 share these weights.
+fn bc7_weight(index: u8, index_bits: u8) -> u8 {
+    match index_bits {
+        2 => BC7_WEIGHTS_2[index as usize],
+        3 => BC7_WEIGHTS_3[index as usize],
+        _ => BC7_WEIGHTS_4[index as usize],
+    }
+}
+
+/// Interpolates between two endpoint components with the weight of an index with the given
+/// number of bits.
+pub(crate) fn bc7_interpolate(e0: u8, e1: u8, index: u8, index_bits: u8) -> u8 {
+    let weight = bc7_weight(index, index_bits) as u16;
+    (((64 - weight) * e0 as u16 + weight * e1 as u16 + 32) >> 6) as u8
+}
+
+/// Expands an endpoint component with `bits` bits (including the P-bit) to 8 bits by replicating
+/// its high bits.
+pub(crate) fn bc7_unquantize(value: u8, bits: u8) -> u8 {
+    debug_assert!((5..=8).contains(&bits));
+    let value = value as u16;
+    ((value << (8 - bits)) | (value >> (2 * bits - 8))) as u8
+}
+
+/// Decodes a BC7 block.
+///
+/// Blocks with a reserved mode decode to transparent black, as required by the D3D spec.
+pub(crate) fn decode_bc7_block(block_bytes: [u8; 16]) -> [[u8; 4]; 16] {
+    let mode_index = block_bytes[0].trailing_zeros() as u8;
+    let Some(mode) = BC7_MODES.get(mode_index as usize) else {
+        return [[0; 4]; 16];
+    };
+
+    let mut bits = BitReader::new(block_bytes);
+    bits.skip(mode_index + 1);
+
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    // Endpoints are stored as all reds, then all greens, then all blues, then all alphas.
+    let endpoint_count = mode.subsets as usize * 2;
+    let mut endpoints = [[0_u8; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[channel] = bits.read(mode.color_bits);
+        }
+    }
+    if mode.alpha_bits > 0 {
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[3] = bits.read(mode.alpha_bits);
+        }
+    }
+
+    // P-bits are appended as the new least significant bit of every component.
+    let mut color_bits = mode.color_bits;
+    let mut alpha_bits = mode.alpha_bits;
+    if mode.endpoint_p_bits || mode.shared_p_bits {
+        let mut p_bit = 0;
+        for (i, endpoint) in endpoints[..endpoint_count].iter_mut().enumerate() {
+            if mode.endpoint_p_bits || i % 2 == 0 {
+                p_bit = bits.read(1);
+            }
+            for component in endpoint.iter_mut() {
+                *component = (*component << 1) | p_bit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+
+    for endpoint in &mut endpoints[..endpoint_count] {
+        for component in &mut endpoint[..3] {
+            *component = bc7_unquantize(*component, color_bits);
+        }
+        endpoint[3] = if alpha_bits > 0 {
+            bc7_unquantize(endpoint[3], alpha_bits)
+        } else {
+            255
+        };
+    }
+
+    let (subset_of, anchors) = match mode.subsets {
+        2 => (PARTITIONS_2[partition], [0, ANCHOR_2[partition], 0]),
+        3 => (
+            PARTITIONS_3[partition],
+            [0, ANCHOR_3_1[partition], ANCHOR_3_2[partition]],
+        ),
+        _ => ([0; 16], [0; 3]),
+    };
+
+    // The anchor index of each subset is stored with one bit less, since its most significant
+    // bit is implicitly zero.
+    let mut indexes = [0_u8; 16];
+    for (i, index) in indexes.iter_mut().enumerate() {
+        let is_anchor = anchors[..mode.subsets as usize].contains(&(i as u8));
+        *index = bits.read(mode.index_bits - u8::from(is_anchor));
+    }
+    let mut secondary_indexes = [0_u8; 16];
+    if mode.secondary_index_bits > 0 {
+        for (i, index) in secondary_indexes.iter_mut().enumerate() {
+            *index = bits.read(mode.secondary_index_bits - u8::from(i == 0));
+        }
+    }
+
+    let mut pixels = [[0_u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let subset = subset_of[i] as usize;
+        let e0 = endpoints[subset * 2];
+        let e1 = endpoints[subset * 2 + 1];
+
+        let ((color_index, color_index_bits), (alpha_index, alpha_index_bits)) =
+            if mode.secondary_index_bits == 0 {
+                ((indexes[i], mode.index_bits), (indexes[i], mode.index_bits))
+            } else if index_selection == 0 {
+                (
+                    (indexes[i], mode.index_bits),
+                    (secondary_indexes[i], mode.secondary_index_bits),
+                )
+            } else {
+                (
+                    (secondary_indexes[i], mode.secondary_index_bits),
+                    (indexes[i], mode.index_bits),
+                )
+            };
+
+        for c in 0..3 {
+            pixel[c] = bc7_interpolate(e0[c], e1[c], color_index, color_index_bits);
+        }
+        pixel[3] = bc7_interpolate(e0[3], e1[3], alpha_index, alpha_index_bits);
+
+        match rotation {
+            1 => pixel.swap(0, 3),
+            2 => pixel.swap(1, 3),
+            3 => pixel.swap(2, 3),
+            _ => {}
+        }
+    }
+
+    pixels
+}
+
+/// The parameters of a BC6H mode.
+///
+/// See <https://learn.microsoft.com/en-us/windows/win32/direct3d11/bc6h-format>.
+struct Bc6hMode {
+    /// Whether the endpoints other than the first are stored as deltas to the first endpoint.
+    transformed: bool,
+    /// Whether the block has two subsets instead of one.
+    two_subsets: bool,
+    /// The precision of all endpoints after the deltas have been applied.
+    endpoint_bits: u8,
+    /// The precision of the red, green, and blue components of the other endpoints.
+    delta_bits: [u8; 3],
+    /// Where the endpoint bits are stored in the block, in the order they appear.
+    layout: &'static [Bc6hBits],
+}
+
+/// A run of consecutive endpoint bits: `(endpoint, channel, lowest bit, bit count)`.
+///
+/// Endpoints are numbered like in the D3D docs: w and x are the endpoints of the first subset,
+/// y and z the endpoints of the second subset.
+type Bc6hBits = (u8, u8, u8, u8);
+
+const W: u8 = 0;
+const X: u8 = 1;
+const Y: u8 = 2;
+const Z: u8 = 3;
+const R: u8 = 0;
+const G: u8 = 1;
+const B: u8 = 2;
+
+#[rustfmt::skip]
+const BC6H_MODES: [Bc6hMode; 14] = [
+    // mode 1
+    Bc6hMode { transformed: true, two_subsets: true, endpoint_bits: 10, delta_bits: [5, 5, 5], layout: &[
+        (Y, G, 4, 1), (Y, B, 4, 1), (Z, B, 4, 1), (W, R, 0, 10), (W, G, 0, 10), (W, B, 0, 10),
+        (X, R, 0, 5), (Z, G, 4, 1), (Y, G, 0, 4), (X, G, 0, 5), (Z, B, 0, 1), (Z, G, 0, 4),
+        (X, B, 0, 5), (Z, B, 1, 1), (Y, B, 0, 4), (Y, R, 0, 5), (Z, B, 2, 1), (Z, R, 0, 5),
+        (Z, B, 3, 1),
+    ] },
+    // mode 2
+    Bc6hMode { transformed: true, two_subsets: true, endpoint_bits: 7, delta_bits: [6, 6, 6], layout: &[
+        (Y, G, 5, 1), (Z, G, 4, 1), (Z, G, 5, 1), (W, R, 0, 7), (Z, B, 0, 1), (Z, B, 1, 1),
+        (Y, B, 4, 1), (W, G, 0, 7), (Y, B, 5, 1), (Z, B, 2, 1), (Y, G, 4, 1), (W, B, 0, 7),
+        (Z, B, 3, 1), (Z, B, 5, 1), (Z, B, 4, 1), (X, R, 0, 6), (Y, G, 0, 4), (X, G, 0, 6),
+        (Z, G, 0, 4), (X, B, 0, 6), (Y, B, 0, 4), (Y, R, 0, 6), (Z, R, 0, 6),
+    ] },
+    // mode 3
+    Bc6hMode { transformed: true, two_subsets: true, endpoint_bits: 11, delta_bits: [5, 4, 4], layout: &[
+        (W, R, 0, 10), (W, G, 0, 10), (W, B, 0, 10), (X, R, 0, 5), (W, R, 10, 1), (Y, G, 0, 4),
+        (X, G, 0, 4), (W, G, 10, 1), (Z, B, 0, 1), (Z, G, 0, 4), (X, B, 0, 4), (W, B, 10, 1),
+        (Z, B, 1, 1), (Y, B, 0, 4), (Y, R, 0, 5), (Z, B, 2, 1), (Z, R, 0, 5), (Z, B, 3, 1),
+    ] },
+    // mode 4
+    Bc6hMode { transformed: true, two_subsets: true, endpoint_bits: 11, delta_bits: [4, 5, 4], layout: &[
+        (W, R, 0, 10), (W, G, 0, 10), (W, B, 0, 10), (X, R, 0, 4), (W, R, 10, 1), (Z, G, 4, 1),
+        (Y, G, 0, 4), (X, G, 0, 5), (W, G, 10, 1), (Z, G, 0, 4), (X, B, 0, 4), (W, B, 10, 1),
+        (Z, B, 1, 1), (Y, B, 0, 4), (Y, R, 0, 4), (Z, B, 0, 1), (Z, B, 2, 1), (Z, R, 0, 4),
+        (Y, G, 4, 1), (Z, B, 3, 1),
+    ] },
+    // mode 5
+    Bc6hMode { transformed: true, two_subsets: true, endpoint_bits: 11, delta_bits: [4, 4, 5], layout: &[
+        (W, R, 0, 10), (W, G, 0, 10), (W, B, 0, 10), (X, R, 0, 4), (W, R, 10, 1), (Y, B, 4, 1),
+        (Y, G, 0, 4), (X, G, 0, 4), (W, G, 10, 1), (Z, B, 0, 1), (Z, G, 0, 4), (X, B, 0, 5),
+        (W, B, 10, 1), (Y, B, 0, 4), (Y, R, 0, 4), (Z, B, 1, 1), (Z, B, 2, 1), (Z, R, 0, 4),
+        (Z, B, 4, 1), (Z, B, 3, 1),
+    ] },
+    // mode 6
+    Bc6hMode { transformed: true, two_subsets: true, endpoint_bits: 9, delta_bits: [5, 5, 5], layout: &[
+        (W, R, 0, 9), (Y, B, 4, 1), (W, G, 0, 9), (Y, G, 4, 1), (W, B, 0, 9), (Z, B, 4, 1),
+        (X, R, 0, 5), (Z, G, 4, 1), (Y, G, 0, 4), (X, G, 0, 5), (Z, B, 0, 1), (Z, G, 0, 4),
+        (X, B, 0, 5), (Z, B, 1, 1), (Y, B, 0, 4), (Y, R, 0, 5), (Z, B, 2, 1), (Z, R, 0, 5),
+        (Z, B, 3, 1),
+    ] },
+    // mode 7
+    Bc6hMode { transformed: true, two_subsets: true, endpoint_bits: 8, delta_bits: [6, 5, 5], layout: &[
+        (W, R, 0, 8), (Z, G, 4, 1), (Y, B, 4, 1), (W, G, 0, 8), (Z, B, 2, 1), (Y, G, 4, 1),
+        (W, B, 0, 8), (Z, B, 3, 1), (Z, B, 4, 1), (X, R, 0, 6), (Y, G, 0, 4), (X, G, 0, 5),
+        (Z, B, 0, 1), (Z, G, 0, 4), (X, B, 0, 5), (Z, B, 1, 1), (Y, B, 0, 4), (Y, R, 0, 6),
+        (Z, R, 0, 6),
+    ] },
+    // mode 8
+    Bc6hMode { transformed: true, two_subsets: true, endpoint_bits: 8, delta_bits: [5, 6, 5], layout: &[
+        (W, R, 0, 8), (Z, B, 0, 1), (Y, B, 4, 1), (W, G, 0, 8), (Y, G, 5, 1), (Y, G, 4, 1),
+        (W, B, 0, 8), (Z, G, 5, 1), (Z, B, 4, 1), (X, R, 0, 5), (Z, G, 4, 1), (Y, G, 0, 4),
+        (X, G, 0, 6), (Z, G, 0, 4), (X, B, 0, 5), (Z, B, 1, 1), (Y, B, 0, 4), (Y, R, 0, 5),
+        (Z, B, 2, 1), (Z, R, 0, 5), (Z, B, 3, 1),
+    ] },
+    // mode 9
+    Bc6hMode { transformed: true, two_subsets: true, endpoint_bits: 8, delta_bits: [5, 5, 6], layout: &[
+        (W, R, 0, 8), (Z, B, 1, 1), (Y, B, 4, 1), (W, G, 0, 8), (Y, B, 5, 1), (Y, G, 4, 1),
+        (W, B, 0, 8), (Z, B, 5, 1), (Z, B, 4, 1), (X, R, 0, 5), (Z, G, 4, 1), (Y, G, 0, 4),
+        (X, G, 0, 5), (Z, B, 0, 1), (Z, G, 0, 4), (X, B, 0, 6), (Y, B, 0, 4), (Y, R, 0, 5),
+        (Z, B, 2, 1), (Z, R, 0, 5), (Z, B, 3, 1),
+    ] },
+    // mode 10
+    Bc6hMode { transformed: false, two_subsets: true, endpoint_bits: 6, delta_bits: [6, 6, 6], layout: &[
+        (W, R, 0, 6), (Z, G, 4, 1), (Z, B, 0, 1), (Z, B, 1, 1), (Y, B, 4, 1), (W, G, 0, 6),
+        (Y, G, 5, 1), (Y, B, 5, 1), (Z, B, 2, 1), (Y, G, 4, 1), (W, B, 0, 6), (Z, G, 5, 1),
+        (Z, B, 3, 1), (Z, B, 5, 1), (Z, B, 4, 1), (X, R, 0, 6), (Y, G, 0, 4), (X, G, 0, 6),
+        (Z, G, 0, 4), (X, B, 0, 6), (Y, B, 0, 4), (Y, R, 0, 6), (Z, R, 0, 6),
+    ] },
+    // mode 11
+    Bc6hMode { transformed: false, two_subsets: false, endpoint_bits: 10, delta_bits: [10, 10, 10], layout: &[
+        (W, R, 0, 10), (W, G, 0, 10), (W, B, 0, 10), (X, R, 0, 10), (X, G, 0, 10), (X, B, 0, 10),
+    ] },
+    // mode 12
+    Bc6hMode { transformed: true, two_subsets: false, endpoint_bits: 11, delta_bits: [9, 9, 9], layout: &[
+        (W, R, 0, 10), (W, G, 0, 10), (W, B, 0, 10), (X, R, 0, 9), (W, R, 10, 1), (X, G, 0, 9),
+        (W, G, 10, 1), (X, B, 0, 9), (W, B, 10, 1),
+    ] },
+    // mode 13, the high bits of w are stored in reverse order
+    Bc6hMode { transformed: true, two_subsets: false, endpoint_bits: 12, delta_bits: [8, 8, 8], layout: &[
+        (W, R, 0, 10), (W, G, 0, 10), (W, B, 0, 10), (X, R, 0, 8), (W, R, 11, 1), (W, R, 10, 1),
+        (X, G, 0, 8), (W, G, 11, 1), (W, G, 10, 1), (X, B, 0, 8), (W, B, 11, 1), (W, B, 10, 1),
+    ] },
+    // mode 14, the high bits of w are stored in reverse order
+    Bc6hMode { transformed: true, two_subsets: false, endpoint_bits: 16, delta_bits: [4, 4, 4], layout: &[
+        (W, R, 0, 10), (W, G, 0, 10), (W, B, 0, 10),
+        (X, R, 0, 4), (W, R, 15, 1), (W, R, 14, 1), (W, R, 13, 1), (W, R, 12, 1), (W, R, 11, 1), (W, R, 10, 1),
+        (X, G, 0, 4), (W, G, 15, 1), (W, G, 14, 1), (W, G, 13, 1), (W, G, 12, 1), (W, G, 11, 1), (W, G, 10, 1),
+        (X, B, 0, 4), (W, B, 15, 1), (W, B, 14, 1), (W, B, 13, 1), (W, B, 12, 1), (W, B, 11, 1), (W, B, 10, 1),
+    ] },
+];
+
+fn sign_extend(value: i32, bits: u8) -> i32 {
+    let shift = 32 - bits as u32;
+    (value << shift) >> shift
+}
+
+/// Maps an unsigned endpoint with `bits` bits to the full 16-bit range.
+fn bc6h_unquantize_unsigned(value: i32, bits: u8) -> i32 {
+    if bits >= 15 || value == 0 {
+        value
+    } else if value == (1 << bits) - 1 {
+        0xFFFF
+    } else {
+        ((value << 16) + 0x8000) >> bits
+    }
+}
+
+/// Scales an interpolated unsigned value by 31/64 to get the bits of a non-negative half float.
+fn bc6h_finish_unsigned(value: i32) -> u16 {
+    ((value * 31) >> 6) as u16
+}
+
+/// Maps a signed endpoint with `bits` bits (including the sign) to the range
+/// `-0x7FFF..=0x7FFF`. The magnitude is unquantized and the sign is kept as is.
+fn bc6h_unquantize_signed(value: i32, bits: u8) -> i32 {
+    if bits >= 16 {
+        return value;
+    }
+
+    let magnitude = value.abs();
+    let unquantized = if magnitude == 0 {
+        0
+    } else if magnitude >= (1 << (bits - 1)) - 1 {
+        0x7FFF
+    } else {
+        ((magnitude << 15) + 0x4000) >> (bits - 1)
+    };
+    if value < 0 {
+        -unquantized
+    } else {
+        unquantized
+    }
+}
+
+/// Scales the magnitude of an interpolated signed value by 31/32 to get the bits of a half float.
+fn bc6h_finish_signed(value: i32) -> u16 {
+    let magnitude = ((value.abs() * 31) >> 5) as u16;
+    if value < 0 {
+        0x8000 | magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Decodes a BC6H block into the bits of half floats.
+///
+/// `signed` selects between the `BC6H_SF16` and `BC6H_UF16` formats. The two formats share the
+/// same modes and bit layouts, but differ in how endpoints are sign-extended and unquantized.
+fn decode_bc6h_block(block_bytes: [u8; 16], signed: bool) -> [[u16; 3]; 16] {
+    let mut bits = BitReader::new(block_bytes);
+
+    let mode = match bits.read(2) {
+        low @ (0 | 1) => low,
+        low => match (bits.read(3) << 2) | low {
+            2 => 2,
+            6 => 3,
+            10 => 4,
+            14 => 5,
+            18 => 6,
+            22 => 7,
+            26 => 8,
+            30 => 9,
+            3 => 10,
+            7 => 11,
+            11 => 12,
+            15 => 13,
+            // reserved modes decode to black
+            _ => return [[0; 3]; 16],
+        },
+    };
+    let mode = &BC6H_MODES[mode as usize];
+
+    let mut endpoints = [[0_i32; 3]; 4];
+    for &(endpoint, channel, shift, count) in mode.layout {
+        endpoints[endpoint as usize][channel as usize] |= i32::from(bits.read_u16(count)) << shift;
+    }
+
+    let endpoint_count = if mode.two_subsets { 4 } else { 2 };
+    if signed {
+        for component in &mut endpoints[0] {
+            *component = sign_extend(*component, mode.endpoint_bits);
+        }
+    }
+    if mode.transformed {
+        // Deltas are always signed. The sum wraps around and is then reinterpreted as a signed
+        // value for signed formats.
+        let mask = (1 << mode.endpoint_bits) - 1;
+        let base = endpoints[0];
+        for endpoint in &mut endpoints[1..endpoint_count] {
+            for (channel, component) in endpoint.iter_mut().enumerate() {
+                let delta = sign_extend(*component, mode.delta_bits[channel]);
+                *component = (base[channel] + delta) & mask;
+                if signed {
+                    *component = sign_extend(*component, mode.endpoint_bits);
+                }
+            }
+        }
+    } else if signed {
+        for endpoint in &mut endpoints[1..endpoint_count] {
+            for component in endpoint.iter_mut() {
+                *component = sign_extend(*component, mode.endpoint_bits);
+            }
+        }
+    }
+    for endpoint in &mut endpoints[..endpoint_count] {
+        for component in endpoint.iter_mut() {
+            *component = if signed {
+                bc6h_unquantize_signed(*component, mode.endpoint_bits)
+            } else {
+                bc6h_unquantize_unsigned(*component, mode.endpoint_bits)
+            };
+        }
+    }
+
+    let (subset_of, anchor, index_bits) = if mode.two_subsets {
+        let partition = bits.read(5) as usize;
+        (PARTITIONS_2[partition], ANCHOR_2[partition], 3)
+    } else {
+        ([0; 16], 0, 4)
+    };
+
+    let mut pixels = [[0_u16; 3]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        // The anchor index of each subset is stored with one bit less, since its most
+        // significant bit is implicitly zero.
+        let is_anchor = i == 0 || i == anchor as usize;
+        let index = bits.read(index_bits - u8::from(is_anchor));
+        let weight = i32::from(bc7_weight(index, index_bits));
+
+        let subset = subset_of[i] as usize;
+        let e0 = endpoints[subset * 2];
+        let e1 = endpoints[subset * 2 + 1];
+        for channel in 0..3 {
+            let value = ((64 - weight) * e0[channel] + weight * e1[channel] + 32) >> 6;
+            pixel[channel] = if signed {
+                bc6h_finish_signed(value)
+            } else {
+                bc6h_finish_unsigned(value)
+            };
+        }
+    }
+
+    pixels
+}
+
+/// Decodes a BC6H block of the unsigned format (`BC6H_UF16`) into the bits of half floats.
+pub(crate) fn decode_bc6h_unsigned_block(block_bytes: [u8; 16]) -> [[u16; 3]; 16] {
+    decode_bc6h_block(block_bytes, false)
+}
+
+/// Decodes a BC6H block of the signed format (`BC6H_SF16`) into the bits of half floats.
+///
+/// Negative values are preserved.
+pub(crate) fn decode_bc6h_signed_block(block_bytes: [u8; 16]) -> [[u16; 3]; 16] {
+    decode_bc6h_block(block_bytes, true)
+}
+
+/// Decodes a BC6H block of the unsigned format (`BC6H_UF16`) into floats.
+///
+/// BC6H stores HDR data, so values can be greater than 1.0.
+pub(crate) fn decode_bc6h_unsigned_block_f32(block_bytes: [u8; 16]) -> [[f32; 3]; 16] {
+    decode_bc6h_unsigned_block(block_bytes).map(|pixel| pixel.map(half_to_f32))
+}
+
+/// Decodes a BC6H block of the signed format (`BC6H_SF16`) into floats.
+///
+/// BC6H stores HDR data, so values can be greater than 1.0 or less than -1.0.
+pub(crate) fn decode_bc6h_signed_block_f32(block_bytes: [u8; 16]) -> [[f32; 3]; 16] {
+    decode_bc6h_signed_block(block_bytes).map(|pixel| pixel.map(half_to_f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes bits into a 128-bit block, least significant bit first.
+    struct BitWriter {
+        bits: u128,
+        len: u32,
+    }
+
+    impl BitWriter {
+        fn new() -> Self {
+            Self { bits: 0, len: 0 }
+        }
+
+        fn write(&mut self, value: u32, count: u32) -> &mut Self {
+            assert!(u64::from(value) >> count == 0);
+            self.bits |= u128::from(value) << self.len;
+            self.len += count;
+            self
+        }
+
+        fn write_all(&mut self, values: &[u32], count: u32) -> &mut Self {
+            for &value in values {
+                self.write(value, count);
+            }
+            self
+        }
+
+        fn bc7_mode(mode: u32) -> Self {
+            let mut writer = Self::new();
+            writer.write(1 << mode, mode + 1);
+            writer
+        }
+
+        /// Writes the endpoints of a BC6H mode in the order of its layout.
+        fn bc6h_endpoints(&mut self, mode: usize, endpoints: [[u32; 3]; 4]) -> &mut Self {
+            for &(endpoint, channel, shift, count) in BC6H_MODES[mode].layout {
+                let value = endpoints[endpoint as usize][channel as usize] >> shift;
+                self.write(value & ((1 << count) - 1), count as u32);
+            }
+            self
+        }
+
+        fn finish(&self) -> [u8; 16] {
+            assert_eq!(self.len, 128);
+            self.bits.to_le_bytes()
+        }
+    }
+
+    /// Builds a BC1 block from two endpoints and 16 indexes.
+    fn bc1_block(color0: u16, color1: u16, indexes: [u8; 16]) -> [u8; 8] {
+        let indexes = (0..16).fold(0_u32, |acc, i| acc | (indexes[i] as u32) << (i * 2));
+        let mut block = [0; 8];
+        block[..2].copy_from_slice(&color0.to_le_bytes());
+        block[2..4].copy_from_slice(&color1.to_le_bytes());
+        block[4..].copy_from_slice(&indexes.to_le_bytes());
+        block
+    }
+
+    /// Builds a BC4 block from two endpoints and 16 indexes.
+    fn bc4_block(red0: u8, red1: u8, indexes: [u8; 16]) -> [u8; 8] {
+        let indexes = (0..16).fold(0_u64, |acc, i| acc | (indexes[i] as u64) << (i * 3));
+        let mut block = (indexes << 16).to_le_bytes();
+        block[0] = red0;
+        block[1] = red1;
+        block
+    }
+
+    const BC1_INDEXES: [u8; 16] = [0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3];
+    const BC4_INDEXES: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 0, 1, 2, 3, 4, 5, 6, 7];
+
+    #[test]
+    fn bc1_four_color_mode() {
+        // red to blue
+        let block = bc1_block(0xF800, 0x001F, BC1_INDEXES);
+        let palette = [
+            [255, 0, 0, 255],
+            [0, 0, 255, 255],
+            [170, 0, 85, 255],
+            [85, 0, 170, 255],
+        ];
+        assert_eq!(
+            decode_bc1_block(block),
+            BC1_INDEXES.map(|i| palette[i as usize])
+        );
+    }
+
+    #[test]
+    fn bc1_three_color_mode() {
+        // blue to red, index 3 is transparent
+        let block = bc1_block(0x001F, 0xF800, BC1_INDEXES);
+        let palette = [
+            [0, 0, 255, 255],
+            [255, 0, 0, 255],
+            [128, 0, 128, 255],
+            [0, 0, 0, 0],
+        ];
+        assert_eq!(
+            decode_bc1_block(block),
+            BC1_INDEXES.map(|i| palette[i as usize])
+        );
+    }
+
+    #[test]
+    fn bc1_color_block_of_bc3_is_opaque() {
+        // blue to red, which is the 3-color mode in a standalone BC1 block
+        let color = bc1_block(0x001F, 0xF800, BC1_INDEXES);
+        let mut block = [0; 16];
+        // BC4 alpha block with all values 255
+        block[..2].copy_from_slice(&[255, 255]);
+        block[8..].copy_from_slice(&color);
+
+        let bc1 = decode_bc1_block(color);
+        let bc3 = decode_bc3_block(block);
+        let palette = [
+            [0, 0, 255, 255],
+            [255, 0, 0, 255],
+            [85, 0, 170, 255],
+            [170, 0, 85, 255],
+        ];
+        assert_eq!(bc3, BC1_INDEXES.map(|i| palette[i as usize]));
+        for ((bc1, bc3), i) in bc1.iter().zip(bc3).zip(BC1_INDEXES) {
+            match i {
+                0 | 1 => assert_eq!(*bc1, bc3),
+                2 => assert_eq!(*bc1, [128, 0, 128, 255]),
+                _ => assert_eq!(*bc1, [0, 0, 0, 0]),
+            }
+        }
+
+        // BC2 with all alpha values 15
+        block[..8].fill(0xFF);
+        assert_eq!(decode_bc2_block(block), bc3);
+    }
+
+    #[test]
+    fn bc1_palette_is_exact() {
+        // round((w0 * x0 + w1 * x1) / (w0 + w1) * 255 / max)
+        let interpolate = |x0: u8, x1: u8, w0: f64, w1: f64, max: f64| {
+            ((w0 * x0 as f64 + w1 * x1 as f64) / (w0 + w1) * 255.0 / max).round() as u8
+        };
+        let expected = |c0: B5G6R5, c1: B5G6R5, w0: f64, w1: f64| {
+            [
+                interpolate(c0.r5, c1.r5, w0, w1, 31.0),
+                interpolate(c0.g6, c1.g6, w0, w1, 63.0),
+                interpolate(c0.b5, c1.b5, w0, w1, 31.0),
+                255,
+            ]
+        };
+
+        for color0 in 0..=u16::MAX {
+            for color1 in [0, 0x0001, 0x0020, 0x0800, 0x1234, 0x7BEF, 0x8410, 0xFFFF] {
+                let c0 = B5G6R5::from_u16(color0);
+                let c1 = B5G6R5::from_u16(color1);
+                let palette = bc1_palette(color0, color1, true);
+                assert_eq!(palette[0], c0.to_rgba8());
+                assert_eq!(palette[1], c1.to_rgba8());
+                if color0 > color1 {
+                    assert_eq!(palette[2], expected(c0, c1, 2.0, 1.0));
+                    assert_eq!(palette[3], expected(c0, c1, 1.0, 2.0));
+                } else {
+                    assert_eq!(palette[2], expected(c0, c1, 1.0, 1.0));
+                    assert_eq!(palette[3], [0, 0, 0, 0]);
+                }
+
+                let palette = bc1_palette(color0, color1, false);
+                assert_eq!(palette[2], expected(c0, c1, 2.0, 1.0));
+                assert_eq!(palette[3], expected(c0, c1, 1.0, 2.0));
+            }
+        }
+    }
+
+    #[test]
+    fn bc2_alpha() {
+        let mut block = [0; 16];
+        // pixel i has an alpha of i
+        for (i, byte) in block[..8].iter_mut().enumerate() {
+            *byte = (i as u8 * 2) | ((i as u8 * 2 + 1) << 4);
+        }
+        block[8..].copy_from_slice(&bc1_block(0xFFFF, 0xFFFF, [0; 16]));
+
+        let expected: [[u8; 4]; 16] = std::array::from_fn(|i| [255, 255, 255, i as u8 * 17]);
+        assert_eq!(decode_bc2_block(block), expected);
+    }
+
+    #[test]
+    fn bc3_alpha() {
+        let mut block = [0; 16];
+        block[..8].copy_from_slice(&bc4_block(255, 0, BC4_INDEXES));
+        block[8..].copy_from_slice(&bc1_block(0xFFFF, 0xFFFF, [0; 16]));
+
+        let palette = [255, 0, 219, 182, 146, 109, 73, 36];
+        let expected = BC4_INDEXES.map(|i| [255, 255, 255, palette[i as usize]]);
+        assert_eq!(decode_bc3_block(block), expected);
+    }
+
+    #[test]
+    fn bc4_unsigned() {
+        // 6 interpolated values: round(255 * (7 - i) / 7)
+        let block = bc4_block(255, 0, BC4_INDEXES);
+        let palette = [255, 0, 219, 182, 146, 109, 73, 36];
+        assert_eq!(
+            decode_bc4_unsigned_block(block),
+            BC4_INDEXES.map(|i| [palette[i as usize]])
+        );
+
+        // 4 interpolated values: round((51 * (5 - i) + 200 * i) / 5), then 0 and 255
+        let block = bc4_block(51, 200, BC4_INDEXES);
+        let palette = [51, 200, 81, 111, 140, 170, 0, 255];
+        assert_eq!(
+            decode_bc4_unsigned_block(block),
+            BC4_INDEXES.map(|i| [palette[i as usize]])
+        );
+    }
+
+    #[test]
+    fn bc4_signed() {
+        // 1.0 to -1.0 with 6 interpolated values
+        let block = bc4_block(0x7F, 0x81, BC4_INDEXES);
+        let palette = [255, 0, 219, 182, 146, 109, 73, 36];
+        assert_eq!(
+            decode_bc4_signed_block(block),
+            BC4_INDEXES.map(|i| [palette[i as usize]])
+        );
+
+        // -1.0 to 0.0 with 4 interpolated values, then -1.0 and 1.0
+        let block = bc4_block(0x80, 0x00, BC4_INDEXES);
+        let palette = [0, 128, 25, 51, 76, 102, 0, 255];
+        assert_eq!(
+            decode_bc4_signed_block(block),
+            BC4_INDEXES.map(|i| [palette[i as usize]])
+        );
+    }
+
+    #[test]
+    fn bc4_signed_extremes() {
+        // -128 and -127 are both -1.0
+        for red0 in [0x80, 0x81] {
+            let block = bc4_block(red0, 0x7F, BC4_INDEXES);
+            let palette = [0, 255, 51, 102, 153, 204, 0, 255];
+            assert_eq!(
+                decode_bc4_signed_block(block),
+                BC4_INDEXES.map(|i| [palette[i as usize]])
+            );
+
+            let block = bc4_block(0x7F, red0, BC4_INDEXES);
+            let palette = [255, 0, 219, 182, 146, 109, 73, 36];
+            assert_eq!(
+                decode_bc4_signed_block(block),
+                BC4_INDEXES.map(|i| [palette[i as usize]])
+            );
+        }
+
+        // -127 > -128 selects the 6 interpolant mode, even though both are -1.0.
+        let block = bc4_block(0x81, 0x80, BC4_INDEXES);
+        assert_eq!(decode_bc4_signed_block(block), [[0]; 16]);
+        let block = bc4_block(0x80, 0x81, BC4_INDEXES);
+        let palette = [0, 0, 0, 0, 0, 0, 0, 255];
+        assert_eq!(
+            decode_bc4_signed_block(block),
+            BC4_INDEXES.map(|i| [palette[i as usize]])
+        );
+    }
+
+    #[test]
+    fn bc4_signed_snorm() {
+        // 1.0 to -1.0 with 6 interpolated values
+        let block = bc4_block(0x7F, 0x81, BC4_INDEXES);
+        let palette = [127, -127, 91, 54, 18, -18, -54, -91];
+        assert_eq!(
+            decode_bc4_signed_block_snorm(block),
+            BC4_INDEXES.map(|i| [palette[i as usize]])
+        );
+
+        // -1.0 (as -128) to 0.0 with 4 interpolated values, then -1.0 and 1.0
+        let block = bc4_block(0x80, 0x00, BC4_INDEXES);
+        let palette = [-127, 0, -102, -76, -51, -25, -127, 127];
+        assert_eq!(
+            decode_bc4_signed_block_snorm(block),
+            BC4_INDEXES.map(|i| [palette[i as usize]])
+        );
+    }
+
+    #[test]
+    fn bc4_signed_snorm_matches_unorm() {
+        // The interpolated values never end in .5, so mapping the signed values to unorm gives
+        // exactly the values of the unorm decoder. In particular, both are ordered the same.
+        for red0 in 0..=255 {
+            for red1 in 0..=255 {
+                let block = bc4_block(red0, red1, BC4_INDEXES);
+                let snorm = decode_bc4_signed_block_snorm(block);
+                let unorm = decode_bc4_signed_block(block);
+                for ([snorm], [unorm]) in snorm.into_iter().zip(unorm) {
+                    let mapped = snorm8_to_unorm8((snorm as i16 + 127) as u8);
+                    assert_eq!(mapped, unorm, "{red0} {red1}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bc5() {
+        let mut block = [0; 16];
+        block[..8].copy_from_slice(&bc4_block(100, 100, [0; 16]));
+        block[8..].copy_from_slice(&bc4_block(200, 200, [0; 16]));
+        assert_eq!(decode_bc5_unsigned_block(block), [[100, 200, 0]; 16]);
+
+        block[..8].copy_from_slice(&bc4_block(0x00, 0x00, [0; 16]));
+        block[8..].copy_from_slice(&bc4_block(0x7F, 0x7F, [0; 16]));
+        assert_eq!(decode_bc5_signed_block(block), [[128, 255, 128]; 16]);
+    }
+
+    #[test]
+    fn bc4_u16_matches_u8() {
+        // Rounding the 16-bit values to 8 bits gives the 8-bit values. The 8-bit signed decoder
+        // rounds twice, so it can be off by one.
+        let to_u8 = |x: u16| ((x as u32 * 255 + 32767) / 65535) as u8;
+        for red0 in 0..=255 {
+            for red1 in 0..=255 {
+                let block = bc4_block(red0, red1, BC4_INDEXES);
+                let high = decode_bc4_unsigned_block_u16(block).map(|[x]| [to_u8(x)]);
+                assert_eq!(high, decode_bc4_unsigned_block(block), "{red0} {red1}");
+
+                let high = decode_bc4_signed_block_u16(block);
+                let low = decode_bc4_signed_block(block);
+                for ([high], [low]) in high.into_iter().zip(low) {
+                    assert!(to_u8(high).abs_diff(low) <= 1, "{red0} {red1}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bc4_u16_ramp() {
+        // A smooth ramp from 0 to 1/255 only has 2 distinct 8-bit values, but 8 with 16 bits.
+        let block = bc4_block(1, 0, BC4_INDEXES);
+        let low = decode_bc4_unsigned_block(block);
+        let high = decode_bc4_unsigned_block_u16(block);
+        assert_eq!(low[..8], [[1], [0], [1], [1], [1], [0], [0], [0]]);
+        assert_eq!(
+            high[..8],
+            [[257], [0], [220], [184], [147], [110], [73], [37]]
+        );
+
+        // Signed blocks use the full range too.
+        let block = bc4_block(0x7F, 0x81, BC4_INDEXES);
+        let high = decode_bc4_signed_block_u16(block);
+        assert_eq!(high[..2], [[65535], [0]]);
+        let block = bc4_block(0x00, 0x00, [0; 16]);
+        assert_eq!(decode_bc4_signed_block_u16(block), [[32768]; 16]);
+    }
+
+    #[test]
+    fn bc5_u16() {
+        let mut block = [0; 16];
+        block[..8].copy_from_slice(&bc4_block(100, 100, [0; 16]));
+        block[8..].copy_from_slice(&bc4_block(200, 200, [0; 16]));
+        assert_eq!(
+            decode_bc5_unsigned_block_u16(block),
+            [[25700, 51400, 0]; 16]
+        );
+
+        // x = y = 0.0
+        block[..8].copy_from_slice(&bc4_block(0x00, 0x00, [0; 16]));
+        block[8..].copy_from_slice(&bc4_block(0x00, 0x00, [0; 16]));
+        assert_eq!(
+            decode_bc5_signed_block_u16(block),
+            [[32768, 32768, 32768]; 16]
+        );
+        assert_eq!(
+            decode_bc5_signed_block_normal_u16(block),
+            [[32768, 32768, 65535]; 16]
+        );
+    }
+
+    #[test]
+    fn bc5_normal() {
+        // (red, green, blue)
+        let cases = [
+            (128, 128, 255),
+            (255, 128, 128),
+            (128, 0, 128),
+            (219, 128, 216),
+            (64, 191, 218),
+            // x² + y² > 1
+            (255, 255, 128),
+            (0, 0, 128),
+        ];
+        for (red, green, blue) in cases {
+            let mut block = [0; 16];
+            block[..8].copy_from_slice(&bc4_block(red, red, [0; 16]));
+            block[8..].copy_from_slice(&bc4_block(green, green, [0; 16]));
+            assert_eq!(
+                decode_bc5_unsigned_block_normal(block),
+                [[red, green, blue]; 16],
+                "{red} {green}"
+            );
+        }
+
+        // The reconstructed normals have unit length within the precision of 8 bits.
+        for red in (0..=255).step_by(5) {
+            for green in (0..=255).step_by(5) {
+                let [x, y, z] =
+                    [red, green, reconstruct_normal_z(red, green)].map(|c| c as f32 / 127.5 - 1.0);
+                if x * x + y * y <= 1.0 {
+                    let length = (x * x + y * y + z * z).sqrt();
+                    assert!((length - 1.0).abs() < 0.02, "{red} {green} {length}");
+                }
+            }
+        }
+
+        // 0.0 and 1.0 in snorm
+        let mut block = [0; 16];
+        block[..8].copy_from_slice(&bc4_block(0x00, 0x00, [0; 16]));
+        block[8..].copy_from_slice(&bc4_block(0x7F, 0x7F, [0; 16]));
+        assert_eq!(decode_bc5_signed_block_normal(block), [[128, 255, 128]; 16]);
+        block[8..].copy_from_slice(&bc4_block(0x00, 0x00, [0; 16]));
+        assert_eq!(decode_bc5_signed_block_normal(block), [[128, 128, 255]; 16]);
+    }
+
+    #[test]
+    fn bc7_reserved_mode() {
+        assert_eq!(decode_bc7_block([0; 16]), [[0; 4]; 16]);
+        assert_eq!(
+            decode_bc7_block([
+                0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+                0xFF, 0xFF
+            ]),
+            [[0; 4]; 16]
+        );
+    }
+
+    #[test]
+    fn bc7_mode_0() {
+        // Partition 0 with 3 subsets:
+        //   0 0 1 1
+        //   0 0 1 1
+        //   0 2 2 1
+        //   2 2 2 2
+        let block = BitWriter::bc7_mode(0)
+            .write(0, 4) // partition
+            .write_all(&[15, 0, 0, 0, 0, 0], 4) // red
+            .write_all(&[0, 0, 15, 0, 0, 0], 4) // green
+            .write_all(&[0, 0, 0, 0, 15, 8], 4) // blue
+            .write_all(&[1, 0, 1, 0, 1, 0], 1) // P-bits
+            // indexes, the anchors at 0, 3, and 15 have 2 bits
+            .write(0, 2)
+            .write_all(&[7, 3], 3)
+            .write(0, 2)
+            .write_all(&[0; 11], 3)
+            .write(3, 2)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        // endpoint 0 of subset 0 is [0b11111, 0b00001, 0b00001] -> [255, 8, 8]
+        assert_eq!(pixels[0], [255, 8, 8, 255]);
+        assert_eq!(pixels[1], [0, 0, 0, 255]);
+        // (37 * 255 + 27 * 0 + 32) >> 6 = 147
+        assert_eq!(pixels[2], [5, 147, 5, 255]);
+        assert_eq!(pixels[3], [8, 255, 8, 255]);
+        assert_eq!(pixels[4], [255, 8, 8, 255]);
+        assert_eq!(pixels[12], [8, 8, 255, 255]);
+        // blue of endpoint 1 of subset 2 is 0b10000 -> 132
+        // (37 * 255 + 27 * 132 + 32) >> 6 = 203
+        assert_eq!(pixels[15], [5, 5, 203, 255]);
+    }
+
+    #[test]
+    fn bc7_mode_1() {
+        // Partition 0: columns 0-1 are subset 0, columns 2-3 are subset 1
+        let block = BitWriter::bc7_mode(1)
+            .write(0, 6) // partition
+            .write_all(&[63, 0, 0, 0], 6) // red
+            .write_all(&[0, 0, 63, 32], 6) // green
+            .write_all(&[0, 0, 0, 0], 6) // blue
+            .write_all(&[1, 0], 1) // shared P-bits
+            // indexes, the anchors at 0 and 15 have 2 bits
+            .write(0, 2)
+            .write_all(&[4, 7, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 3)
+            .write(3, 2)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        // subset 0 has a P-bit of 1: [0b1111111, 1, 1] -> [255, 2, 2] and [1, 1, 1] -> [2, 2, 2]
+        assert_eq!(pixels[0], [255, 2, 2, 255]);
+        // (27 * 255 + 37 * 2 + 32) >> 6 = 109
+        assert_eq!(pixels[1], [109, 2, 2, 255]);
+        // subset 1 has a P-bit of 0: green is 0b1111110 -> 253 and 0b1000000 -> 129
+        assert_eq!(pixels[2], [0, 129, 0, 255]);
+        assert_eq!(pixels[3], [0, 253, 0, 255]);
+        // (55 * 255 + 9 * 2 + 32) >> 6 = 219
+        assert_eq!(pixels[4], [219, 2, 2, 255]);
+        assert_eq!(pixels[7], [0, 253, 0, 255]);
+        // (37 * 253 + 27 * 129 + 32) >> 6 = 201
+        assert_eq!(pixels[15], [0, 201, 0, 255]);
+    }
+
+    #[test]
+    fn bc7_mode_2() {
+        // Partition 0 with 3 subsets
+        let block = BitWriter::bc7_mode(2)
+            .write(0, 6) // partition
+            .write_all(&[31, 0, 0, 0, 0, 0], 5) // red
+            .write_all(&[0, 0, 31, 0, 0, 0], 5) // green
+            .write_all(&[0, 0, 0, 0, 31, 16], 5) // blue
+            // indexes, the anchors at 0, 3, and 15 have 1 bit
+            .write(1, 1)
+            .write_all(&[2, 1], 2)
+            .write(1, 1)
+            .write_all(&[0; 11], 2)
+            .write(1, 1)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        // (43 * 255 + 21 * 0 + 32) >> 6 = 171
+        assert_eq!(pixels[0], [171, 0, 0, 255]);
+        // (21 * 255 + 43 * 0 + 32) >> 6 = 84
+        assert_eq!(pixels[1], [84, 0, 0, 255]);
+        assert_eq!(pixels[2], [0, 171, 0, 255]);
+        assert_eq!(pixels[3], [0, 171, 0, 255]);
+        assert_eq!(pixels[4], [255, 0, 0, 255]);
+        // blue of endpoint 1 of subset 2 is 0b10000 -> 132
+        // (43 * 255 + 21 * 132 + 32) >> 6 = 215
+        assert_eq!(pixels[15], [0, 0, 215, 255]);
+    }
+
+    #[test]
+    fn bc7_mode_2_anchors() {
+        // Partition 38 with 3 subsets, the anchors of subsets 1 and 2 are pixels 8 and 9:
+        //   0 1 2 0
+        //   2 0 1 2
+        //   1 2 0 1
+        //   0 1 2 0
+        let subsets = [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0];
+        let anchors = [0, 8, 9];
+
+        // each subset goes from black to full red, green, or blue respectively
+        let mut writer = BitWriter::bc7_mode(2);
+        writer
+            .write(38, 6) // partition
+            .write_all(&[0, 31, 0, 0, 0, 0], 5) // red
+            .write_all(&[0, 0, 0, 31, 0, 0], 5) // green
+            .write_all(&[0, 0, 0, 0, 0, 31], 5); // blue
+        let indexes: [u8; 16] =
+            std::array::from_fn(|i| if anchors.contains(&i) { 1 } else { i as u8 % 4 });
+        for (i, &index) in indexes.iter().enumerate() {
+            let bits = if anchors.contains(&i) { 1 } else { 2 };
+            writer.write(index as u32, bits);
+        }
+        let block = writer.finish();
+
+        // A decoder that reads every anchor with 2 bits would shift all following indexes.
+        let values = [0, 84, 171, 255];
+        let expected: [[u8; 4]; 16] = std::array::from_fn(|i| {
+            let mut pixel = [0, 0, 0, 255];
+            pixel[subsets[i]] = values[indexes[i] as usize];
+            pixel
+        });
+        assert_eq!(decode_bc7_block(block), expected);
+    }
+
+    #[test]
+    fn bc7_mode_3() {
+        let block = BitWriter::bc7_mode(3)
+            .write(13, 6) // partition: the bottom two rows are subset 1
+            .write_all(&[127, 0, 0, 64], 7) // red
+            .write_all(&[0, 127, 0, 0], 7) // green
+            .write_all(&[0, 0, 127, 0], 7) // blue
+            .write_all(&[1, 0, 0, 1], 1) // P-bits
+            // indexes, the anchors at 0 and 15 have 1 bit
+            .write(0, 1)
+            .write_all(&[3; 14], 2)
+            .write(1, 1)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        assert_eq!(pixels[0], [255, 1, 1, 255]);
+        assert_eq!(pixels[1], [0, 254, 0, 255]);
+        assert_eq!(pixels[7], [0, 254, 0, 255]);
+        // subset 1 is [0, 0, 254] and [0b10000001, 1, 1] = [129, 1, 1]
+        assert_eq!(pixels[8], [129, 1, 1, 255]);
+        // (43 * 0 + 21 * 129 + 32) >> 6 = 42 and (43 * 254 + 21 * 1 + 32) >> 6 = 171
+        assert_eq!(pixels[15], [42, 0, 171, 255]);
+    }
+
+    quickcheck! {
+        fn bc7_opaque_modes_have_opaque_alpha(bits: u128, mode: u8) -> bool {
+            // Modes 0 to 3 have no alpha channel
+            let mode = mode % 4;
+            let block = ((bits << (mode + 1)) | (1 << mode)).to_le_bytes();
+            decode_bc7_block(block).iter().all(|pixel| pixel[3] == 255)
+        }
+    }
+
+    quickcheck! {
+        fn every_block_decodes(bits: u128) -> bool {
+            // Every bit pattern is a valid block, because reserved modes decode to black.
+            let block = bits.to_le_bytes();
+            let [half, _] = split_block(block);
+            decode_bc1_block(half);
+            decode_bc2_block(block);
+            decode_bc3_block(block);
+            decode_bc5_signed_block(block);
+            decode_bc5_unsigned_block_normal(block);
+            decode_bc5_signed_block_normal_u16(block);
+            decode_bc6h_unsigned_block(block);
+            decode_bc6h_signed_block_f32(block);
+            decode_bc7_block(block);
+            true
+        }
+    }
+
+    #[test]
+    fn bc7_mode_4() {
+        let block = BitWriter::bc7_mode(4)
+            .write(0, 2) // rotation
+            .write(0, 1) // index selection
+            .write_all(&[31, 0], 5) // red
+            .write_all(&[0, 31], 5) // green
+            .write_all(&[0, 0], 5) // blue
+            .write_all(&[63, 0], 6) // alpha
+            // 2-bit color indexes
+            .write(1, 1)
+            .write_all(&[2; 15], 2)
+            // 3-bit alpha indexes
+            .write(0, 2)
+            .write_all(&[7; 15], 3)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        assert_eq!(pixels[0], [171, 84, 0, 255]);
+        assert_eq!(pixels[1], [84, 171, 0, 0]);
+
+        // with the index selection bit set, the 3-bit indexes are used for color and the
+        // 2-bit indexes for alpha
+        let block = BitWriter::bc7_mode(4)
+            .write(0, 2) // rotation
+            .write(1, 1) // index selection
+            .write_all(&[31, 0], 5) // red
+            .write_all(&[0, 31], 5) // green
+            .write_all(&[0, 0], 5) // blue
+            .write_all(&[63, 0], 6) // alpha
+            .write(1, 1)
+            .write_all(&[2; 15], 2)
+            .write(3, 2)
+            .write_all(&[4; 15], 3)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        // (37 * 255 + 27 * 0 + 32) >> 6 = 147 and (43 * 255 + 32) >> 6 = 171
+        assert_eq!(pixels[0], [147, 108, 0, 171]);
+        // 3-bit index 4: (27 * 255 + 37 * 0 + 32) >> 6 = 108
+        assert_eq!(pixels[1], [108, 147, 0, 84]);
+    }
+
+    #[test]
+    fn bc7_mode_5() {
+        let block = BitWriter::bc7_mode(5)
+            .write(0, 2) // rotation
+            .write_all(&[127, 0], 7) // red
+            .write_all(&[0, 0], 7) // green
+            .write_all(&[0, 127], 7) // blue
+            .write_all(&[255, 0], 8) // alpha
+            // color indexes
+            .write(0, 1)
+            .write_all(&[3; 15], 2)
+            // alpha indexes
+            .write(1, 1)
+            .write_all(&[2; 15], 2)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        assert_eq!(pixels[0], [255, 0, 0, 171]);
+        assert_eq!(pixels[1], [0, 0, 255, 84]);
+    }
+
+    #[test]
+    fn bc7_rotation() {
+        // Both endpoints are [129, 64, 32, 200], so every pixel has the same color
+        let expected = [
+            [129, 64, 32, 200],
+            [200, 64, 32, 129],
+            [129, 200, 32, 64],
+            [129, 64, 200, 32],
+        ];
+        for (rotation, expected) in expected.into_iter().enumerate() {
+            let block = BitWriter::bc7_mode(5)
+                .write(rotation as u32, 2)
+                .write_all(&[64, 64], 7) // red
+                .write_all(&[32, 32], 7) // green
+                .write_all(&[16, 16], 7) // blue
+                .write_all(&[200, 200], 8) // alpha
+                .write(0, 31) // color indexes
+                .write(0, 31) // alpha indexes
+                .finish();
+
+            assert_eq!(decode_bc7_block(block), [expected; 16]);
+        }
+
+        // in mode 4, the rotation is applied after the index selection
+        let block = BitWriter::bc7_mode(4)
+            .write(1, 2) // rotation
+            .write(1, 1) // index selection
+            .write_all(&[31, 0], 5) // red
+            .write_all(&[0, 0], 5) // green
+            .write_all(&[0, 0], 5) // blue
+            .write_all(&[0, 63], 6) // alpha
+            .write(0, 31) // 2-bit indexes, used for alpha
+            .write(0, 47) // 3-bit indexes, used for color
+            .finish();
+
+        // red is 255 and alpha is 0 before the rotation
+        assert_eq!(decode_bc7_block(block), [[0, 0, 0, 255]; 16]);
+    }
+
+    #[test]
+    fn bc7_mode_6() {
+        let block = BitWriter::bc7_mode(6)
+            .write_all(&[127, 0], 7) // red
+            .write_all(&[64, 64], 7) // green
+            .write_all(&[0, 127], 7) // blue
+            .write_all(&[127, 63], 7) // alpha
+            .write_all(&[1, 0], 1) // P-bits
+            // indexes
+            .write(0, 3)
+            .write_all(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15], 4)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        // endpoint 0 is [255, 129, 1, 255], endpoint 1 is [0, 128, 254, 126]
+        assert_eq!(pixels[0], [255, 129, 1, 255]);
+        // index 1 has a weight of 4: (60 * 255 + 4 * 0 + 32) >> 6 = 239
+        assert_eq!(pixels[1], [239, 129, 17, 247]);
+        // index 8 has a weight of 34: (30 * 255 + 34 * 0 + 32) >> 6 = 120
+        assert_eq!(pixels[8], [120, 128, 135, 186]);
+        assert_eq!(pixels[15], [0, 128, 254, 126]);
+    }
+
+    #[test]
+    fn bc7_mode_6_weights() {
+        // black to white, each pixel uses its own index
+        let block = BitWriter::bc7_mode(6)
+            .write_all(&[0, 127], 7) // red
+            .write_all(&[0, 127], 7) // green
+            .write_all(&[0, 127], 7) // blue
+            .write_all(&[0, 127], 7) // alpha
+            .write_all(&[0, 1], 1) // P-bits
+            .write(0, 3)
+            .write_all(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15], 4)
+            .finish();
+
+        // (weight * 255 + 32) >> 6 for every 4-bit weight
+        let expected = [
+            0, 16, 36, 52, 68, 84, 104, 120, 135, 151, 171, 187, 203, 219, 239, 255,
+        ];
+        assert_eq!(decode_bc7_block(block), expected.map(|v| [v; 4]));
+    }
+
+    #[test]
+    fn bc7_mode_6_opaque() {
+        // a typical block of an opaque texture: both alpha endpoints are 0xFF
+        let block = BitWriter::bc7_mode(6)
+            .write_all(&[100, 20], 7) // red
+            .write_all(&[50, 70], 7) // green
+            .write_all(&[0, 127], 7) // blue
+            .write_all(&[127, 127], 7) // alpha
+            .write_all(&[1, 1], 1) // P-bits
+            .write(7, 3)
+            .write_all(&[15, 0, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 4)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        // index 7 has a weight of 30: (34 * 201 + 30 * 41 + 32) >> 6 = 126,
+        // (34 * 101 + 30 * 141 + 32) >> 6 = 120, and (34 * 1 + 30 * 255 + 32) >> 6 = 120
+        assert_eq!(pixels[0], [126, 120, 120, 255]);
+        assert_eq!(pixels[1], [41, 141, 255, 255]);
+        assert_eq!(pixels[2], [201, 101, 1, 255]);
+        assert_eq!(pixels[3], [126, 120, 120, 255]);
+    }
+
+    #[test]
+    fn bc7_mode_7_anchor() {
+        // Partition 17, the anchor of subset 1 is pixel 2:
+        //   0 1 1 1
+        //   0 0 0 1
+        //   0 0 0 0
+        //   0 0 0 0
+        let block = BitWriter::bc7_mode(7)
+            .write(17, 6) // partition
+            .write_all(&[0, 31, 0, 31], 5) // red
+            .write_all(&[0, 31, 0, 31], 5) // green
+            .write_all(&[0, 31, 0, 31], 5) // blue
+            .write_all(&[0, 31, 0, 31], 5) // alpha
+            .write_all(&[0, 1, 0, 1], 1) // P-bits
+            // indexes, the anchors at 0 and 2 have 1 bit
+            .write(1, 1)
+            .write(3, 2)
+            .write(0, 1)
+            .write(2, 2)
+            .write_all(&[1; 12], 2)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        assert_eq!(pixels[0], [84; 4]);
+        assert_eq!(pixels[1], [255; 4]);
+        assert_eq!(pixels[2], [0; 4]);
+        assert_eq!(pixels[3], [171; 4]);
+        assert_eq!(&pixels[4..], &[[84; 4]; 12]);
+    }
+
+    #[test]
+    fn bc7_mode_7() {
+        // Partition 0: columns 0-1 are subset 0, columns 2-3 are subset 1
+        let block = BitWriter::bc7_mode(7)
+            .write(0, 6) // partition
+            .write_all(&[31, 0, 0, 0], 5) // red
+            .write_all(&[0, 0, 31, 0], 5) // green
+            .write_all(&[0, 0, 0, 0], 5) // blue
+            .write_all(&[31, 0, 16, 16], 5) // alpha
+            .write_all(&[1, 0, 0, 1], 1) // P-bits
+            // indexes, anchors at 0 and 15 have 1 bit
+            .write(0, 1)
+            .write_all(&[3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 2)
+            .write(1, 1)
+            .finish();
+
+        let pixels = decode_bc7_block(block);
+        // subset 0 is [0b111111, 1, 1, 0b111111] -> [255, 4, 4, 255] and [0, 0, 0, 0]
+        assert_eq!(pixels[0], [255, 4, 4, 255]);
+        assert_eq!(pixels[1], [0, 0, 0, 0]);
+        // subset 1 is [0, 0b111110, 0, 0b100000] -> [0, 251, 0, 130]
+        // and [1, 1, 1, 0b100001] -> [4, 4, 4, 134]
+        assert_eq!(pixels[2], [0, 251, 0, 130]);
+        // (43 * 251 + 21 * 4 + 32) >> 6 = 170 and (43 * 130 + 21 * 134 + 32) >> 6 = 131
+        assert_eq!(pixels[15], [1, 170, 1, 131]);
+    }
+
+    #[test]
+    fn bc6h_layouts_are_consistent() {
+        for mode in &BC6H_MODES {
+            let endpoint_count = if mode.two_subsets { 4 } else { 2 };
+
+            // every bit of every endpoint component is stored exactly once
+            let mut seen = [[0_u32; 3]; 4];
+            for &(endpoint, channel, shift, count) in mode.layout {
+                let field = &mut seen[endpoint as usize][channel as usize];
+                let mask = ((1 << count) - 1) << shift;
+                assert_eq!(*field & mask, 0);
+                *field |= mask;
+            }
+            for (endpoint, seen) in seen.iter().enumerate() {
+                for (channel, &seen) in seen.iter().enumerate() {
+                    let bits = match endpoint {
+                        0 => mode.endpoint_bits,
+                        e if e < endpoint_count => mode.delta_bits[channel],
+                        _ => 0,
+                    };
+                    assert_eq!(seen, (1 << bits) - 1);
+                }
+            }
+
+            // together with the mode, partition, and indexes, the endpoints fill the block
+            let endpoint_bits: u32 = mode.layout.iter().map(|&(.., count)| count as u32).sum();
+            let other_bits = if mode.two_subsets { 5 + 46 } else { 63 };
+            let mode_bits = 128 - endpoint_bits - other_bits;
+            assert!(mode_bits == 2 || mode_bits == 5);
+        }
+    }
+
+    #[test]
+    fn bc6h_reserved_modes() {
+        for mode in [0b10011, 0b10111, 0b11011, 0b11111] {
+            let mut block = [0xFF; 16];
+            block[0] = 0xE0 | mode;
+            assert_eq!(decode_bc6h_block(block, false), [[0; 3]; 16]);
+            assert_eq!(decode_bc6h_block(block, true), [[0; 3]; 16]);
+        }
+    }
+
+    #[test]
+    fn bc6h_untransformed_mode() {
+        // mode 11
+        let block = BitWriter::new()
+            .write(0b00011, 5)
+            .write_all(&[1023, 0, 512], 10) // w
+            .write_all(&[0, 1023, 512], 10) // x
+            // indexes, the anchor at 0 has 3 bits
+            .write(0, 3)
+            .write_all(&[15, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 4)
+            .finish();
+
+        let pixels = decode_bc6h_block(block, false);
+        // 1023 is the maximum and unquantizes to 0xFFFF, which becomes (0xFFFF * 31) >> 6 = 0x7BFF
+        // 512 unquantizes to ((512 << 16) + 0x8000) >> 10 = 32800 -> 0x3E0F
+        assert_eq!(pixels[0], [0x7BFF, 0, 0x3E0F]);
+        assert_eq!(pixels[1], [0, 0x7BFF, 0x3E0F]);
+        // index 8 has a weight of 34: (30 * 0xFFFF + 32) >> 6 = 30720 -> 0x3A20
+        // and (34 * 0xFFFF + 32) >> 6 = 34815 -> 0x41DF
+        assert_eq!(pixels[2], [0x3A20, 0x41DF, 0x3E0F]);
+        assert_eq!(pixels[3], [0x7BFF, 0, 0x3E0F]);
+
+        let pixels = decode_bc6h_unsigned_block_f32(block);
+        assert_eq!(pixels[0], [65504.0, 0.0, 1.5146484]);
+        assert_eq!(pixels[2], [0.765625, 2.9355469, 1.5146484]);
+    }
+
+    #[test]
+    fn bc6h_two_subsets() {
+        // mode 10 with partition 17, the anchor of subset 1 is pixel 2:
+        //   0 1 1 1
+        //   0 0 0 1
+        //   0 0 0 0
+        //   0 0 0 0
+        let block = BitWriter::new()
+            .write(0b11110, 5)
+            .bc6h_endpoints(9, [[63, 0, 0], [0, 63, 0], [0, 0, 32], [0, 0, 63]])
+            .write(17, 5) // partition
+            // indexes, the anchors at 0 and 2 have 2 bits
+            .write(0, 2)
+            .write(7, 3)
+            .write(3, 2)
+            .write_all(&[0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 3)
+            .finish();
+
+        let pixels = decode_bc6h_unsigned_block(block);
+        // 63 is the maximum and becomes 0x7BFF as in mode 11
+        // 32 unquantizes to ((32 << 16) + 0x8000) >> 6 = 33280 -> (33280 * 31) >> 6 = 0x3EF8
+        assert_eq!(pixels[0], [0x7BFF, 0, 0]);
+        assert_eq!(pixels[1], [0, 0, 0x7BFF]);
+        // index 3 has a weight of 27: (37 * 33280 + 27 * 0xFFFF + 32) >> 6 = 46888 -> 0x58B7
+        assert_eq!(pixels[2], [0, 0, 0x58B7]);
+        assert_eq!(pixels[3], [0, 0, 0x3EF8]);
+        // index 4 has a weight of 37: (27 * 0xFFFF + 32) >> 6 = 27648 -> 0x3450
+        // and (37 * 0xFFFF + 32) >> 6 = 37887 -> 0x47AF
+        assert_eq!(pixels[4], [0x3450, 0x47AF, 0]);
+        assert_eq!(pixels[5], [0x7BFF, 0, 0]);
+        assert_eq!(pixels[7], [0, 0, 0x3EF8]);
+        assert_eq!(pixels[15], [0x7BFF, 0, 0]);
+    }
+
+    #[test]
+    fn bc6h_transformed_mode() {
+        // mode 12: w has 11 bits, the deltas have 9 bits
+        let block = BitWriter::new()
+            .write(0b00111, 5)
+            .write_all(&[0, 100, 1023], 10) // w[9:0]
+            .write(488, 9) // delta of red: -24
+            .write(1, 1) // w[10] of red
+            .write(255, 9) // delta of green: 255
+            .write(0, 1) // w[10] of green
+            .write(1, 9) // delta of blue: 1
+            .write(1, 1) // w[10] of blue
+            // indexes, the anchor at 0 has 3 bits
+            .write(0, 3)
+            .write_all(&[0; 14], 4)
+            .write(15, 4)
+            .finish();
+
+        // w is [1024, 100, 2047] and x is [1000, 355, 0], since blue wraps around
+        let pixels = decode_bc6h_block(block, false);
+        // ((1024 << 16) + 0x8000) >> 11 = 32784 -> 0x3E07
+        // ((100 << 16) + 0x8000) >> 11 = 3216 -> 0x0615
+        assert_eq!(pixels[0], [0x3E07, 0x0615, 0x7BFF]);
+        // ((1000 << 16) + 0x8000) >> 11 = 32016 -> 0x3C93
+        // ((355 << 16) + 0x8000) >> 11 = 11376 -> 0x1586
+        assert_eq!(pixels[15], [0x3C93, 0x1586, 0]);
+    }
+
+    #[test]
+    fn bc6h_signed_untransformed_mode() {
+        // mode 11
+        let block = BitWriter::new()
+            .write(0b00011, 5)
+            .write_all(&[512, 0, 100], 10) // w = [-512, 0, 100]
+            .write_all(&[511, 1023, 924], 10) // x = [511, -1, -100]
+            // indexes, the anchor at 0 has 3 bits
+            .write(0, 3)
+            .write_all(&[15, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 4)
+            .finish();
+
+        let pixels = decode_bc6h_block(block, true);
+        // -512 and 511 are the extremes and unquantize to -0x7FFF and 0x7FFF,
+        // which become (0x7FFF * 31) >> 5 = 0x7BFF with the sign bit set for negative values
+        // 100 unquantizes to ((100 << 15) + 0x4000) >> 9 = 6432 -> 0x1857
+        assert_eq!(pixels[0], [0xFBFF, 0, 0x1857]);
+        // -1 unquantizes to -(((1 << 15) + 0x4000) >> 9) = -96 -> 0x805D
+        assert_eq!(pixels[1], [0x7BFF, 0x805D, 0x9857]);
+        // index 8 has a weight of 34: (30 * -0x7FFF + 34 * 0x7FFF + 32) >> 6 = 2048 -> 0x07C0,
+        // (34 * -96 + 32) >> 6 = -51 -> 0x8031, and (-4 * 6432 + 32) >> 6 = -402 -> 0x8185
+        assert_eq!(pixels[2], [0x07C0, 0x8031, 0x8185]);
+
+        let pixels = decode_bc6h_signed_block_f32(block);
+        assert_eq!(pixels[0][0], -65504.0);
+        assert!(pixels[1][1] < 0.0);
+        assert!(pixels[1][2] < 0.0);
+    }
+
+    #[test]
+    fn bc6h_signed_transformed_mode() {
+        // mode 12: w has 11 bits, the deltas have 9 bits
+        let block = BitWriter::new()
+            .write(0b00111, 5)
+            .write_all(&[0, 100, 1023], 10) // w[9:0]
+            .write(488, 9) // delta of red: -24
+            .write(1, 1) // w[10] of red
+            .write(255, 9) // delta of green: 255
+            .write(0, 1) // w[10] of green
+            .write(1, 9) // delta of blue: 1
+            .write(1, 1) // w[10] of blue
+            // indexes, the anchor at 0 has 3 bits
+            .write(0, 3)
+            .write_all(&[0; 14], 4)
+            .write(15, 4)
+            .finish();
+
+        // w is [-1024, 100, -1] and x is [1000, 355, 0], since red wraps around
+        let pixels = decode_bc6h_block(block, true);
+        // ((100 << 15) + 0x4000) >> 10 = 3216 -> 0x0C2B
+        // -(((1 << 15) + 0x4000) >> 10) = -48 -> 0x802E
+        assert_eq!(pixels[0], [0xFBFF, 0x0C2B, 0x802E]);
+        // ((1000 << 15) + 0x4000) >> 10 = 32016 -> 0x7927
+        // ((355 << 15) + 0x4000) >> 10 = 11376 -> 0x2B0C
+        assert_eq!(pixels[15], [0x7927, 0x2B0C, 0]);
+    }
+
+    #[test]
+    fn bc6h_signed_two_subsets() {
+        // mode 1 with partition 0: the left half is subset 0, the right half subset 1
+        let block = BitWriter::new()
+            .write(0b00, 2)
+            .bc6h_endpoints(
+                0,
+                [
+                    [924, 200, 0], // w = [-100, 200, 0]
+                    [27, 3, 0],    // x = w + [-5, 3, 0]
+                    [10, 16, 1],   // y = w + [10, -16, 1]
+                    [0, 0, 31],    // z = w + [0, 0, -1]
+                ],
+            )
+            .write(0, 5) // partition
+            // indexes, the anchors at 0 and 15 have 2 bits
+            .write(0, 2)
+            .write_all(&[7, 0, 7], 3)
+            .write_all(&[0; 11], 3)
+            .write(3, 2)
+            .finish();
+
+        let pixels = decode_bc6h_signed_block(block);
+        // 10-bit magnitudes unquantize to ((m << 15) + 0x4000) >> 9 = 64 * m + 32, e.g.
+        // -100 -> -6432 -> 0x9857 and 200 -> 12832 -> 0x308F
+        assert_eq!(pixels[0], [0x9857, 0x308F, 0]);
+        assert_eq!(pixels[1], [0x998D, 0x3149, 0]);
+        assert_eq!(pixels[2], [0x95EB, 0x2CAF, 0x005D]);
+        assert_eq!(pixels[3], [0x9857, 0x308F, 0x805D]);
+        // index 3 has a weight of 27: (37 * -5792 + 27 * -6432 + 32) >> 6 = -6062 -> 0x96F0,
+        // (37 * 11808 + 27 * 12832 + 32) >> 6 = 12240 -> 0x2E51,
+        // and (37 * 96 + 27 * -96 + 32) >> 6 = 15 -> 0x000E
+        assert_eq!(pixels[15], [0x96F0, 0x2E51, 0x000E]);
+    }
+}
@@ -0,0 +1,206 @@
+//! Decoding several BC1 blocks at once.
+//!
+//! [`bc::decode_bc1_block`](super::bc::decode_bc1_block) computes the palette of one block at a
+//! time, which is mostly scalar integer math. Here, the palettes of [`LANES`] blocks are computed
+//! together, one channel at a time, using fixed-size arrays with one element per block. The loops
+//! over these arrays have no branches, so the compiler turns them into SIMD instructions on every
+//! target without any `unsafe` or platform-specific code.
+//!
+//! On x86-64, the same code is also compiled for AVX2, where a vector holds the values of all
+//! blocks, and that version is used if the CPU supports it. SSE2 is always available on x86-64, so
+//! the default version already uses it. Likewise, NEON is always available on aarch64, and the
+//! palette loops compile to NEON instructions there. Unlike for
+//! [`bc4_batch`](super::bc4_batch), this covers all of the palette computation, so there is no
+//! separate version with intrinsics.
+//!
+//! The results are bit-identical to the scalar decoder.
+
+/// The number of blocks decoded at once.
+pub(crate) const LANES: usize = 8;
+
+/// One value per block.
+type Lanes = [u32; LANES];
+
+/// Returns `round(n / d)` for each lane with ties rounded up, like the scalar decoder rounds.
+///
+/// There is no vectorized integer division, so this multiplies with the reciprocal in `f32`.
+/// The callers divide at most `3 * 63 * 255` by at most `3 * 63`, so quotients that don't round
+/// to the same integer are at least `1 / 378` apart, and the error of `f32` is much smaller than
+/// that. The bias of `1 / 1024` makes exact ties round up despite the error of the reciprocal.
+#[inline(always)]
+fn round_div(n: &Lanes, d: u32) -> Lanes {
+    // Adding 2^23 rounds to the nearest integer and moves it into the mantissa bits. Unlike `as`
+    // casts, which saturate, this is vectorized.
+    const MAGIC: f32 = 8_388_608.0;
+
+    let reciprocal = 1.0 / d as f32;
+    let mut result = [0; LANES];
+    for l in 0..LANES {
+        // Signed conversions are faster than unsigned ones, and all values fit into an i32.
+        let quotient = n[l] as i32 as f32 * reciprocal;
+        result[l] = ((quotient + 1.0 / 1024.0) + MAGIC).to_bits() - MAGIC.to_bits();
+    }
+    result
+}
+
+/// Returns `round((w0 * x0 + w1 * x1) / (w0 + w1))` expanded from `0..=max` to 8 bits for each
+/// lane.
+#[inline(always)]
+fn interpolate(x0: &Lanes, x1: &Lanes, w0: u32, w1: u32, max: u32) -> Lanes {
+    // Multiplying by 255 before dividing expands the channel with a single rounding.
+    let mut sum = [0; LANES];
+    for l in 0..LANES {
+        sum[l] = (w0 * x0[l] + w1 * x1[l]) * 255;
+    }
+    round_div(&sum, (w0 + w1) * max)
+}
+
+/// Adds one channel of the 4 palette colors of each block to `palette`, shifted by `shift`.
+///
+/// The channel of the endpoints is `(color >> shift) & max`. `four_color` has all bits set for
+/// blocks that use the 4-color mode.
+#[inline(always)]
+fn add_palette_channel(
+    palette: &mut [Lanes; 4],
+    colors: [&Lanes; 2],
+    four_color: &Lanes,
+    shift: u32,
+    max: u32,
+) {
+    let mut x0 = [0; LANES];
+    let mut x1 = [0; LANES];
+    for l in 0..LANES {
+        x0[l] = (colors[0][l] >> shift) & max;
+        x1[l] = (colors[1][l] >> shift) & max;
+    }
+
+    let e0 = interpolate(&x0, &x1, 1, 0, max);
+    let e1 = interpolate(&x0, &x1, 0, 1, max);
+    let two_thirds = interpolate(&x0, &x1, 2, 1, max);
+    let one_third = interpolate(&x0, &x1, 1, 2, max);
+    let half = interpolate(&x0, &x1, 1, 1, max);
+    let offset = match shift {
+        11 => 0,
+        5 => 8,
+        _ => 16,
+    };
+    for l in 0..LANES {
+        let mask = four_color[l];
+        palette[0][l] |= e0[l] << offset;
+        palette[1][l] |= e1[l] << offset;
+        palette[2][l] |= ((two_thirds[l] & mask) | (half[l] & !mask)) << offset;
+        // Index 3 of the 3-color mode is transparent black.
+        palette[3][l] |= (one_third[l] & mask) << offset;
+    }
+}
+
+/// Decodes [`LANES`] BC1 blocks.
+pub(crate) fn decode_bc1_blocks(blocks: [[u8; 8]; LANES]) -> [[[u8; 4]; 16]; LANES] {
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") {
+        // SAFETY: The CPU supports AVX2.
+        return unsafe { decode_bc1_blocks_avx2(blocks) };
+    }
+    decode_bc1_blocks_portable(blocks)
+}
+
+/// [`decode_bc1_blocks_portable`] compiled for AVX2.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn decode_bc1_blocks_avx2(blocks: [[u8; 8]; LANES]) -> [[[u8; 4]; 16]; LANES] {
+    decode_bc1_blocks_portable(blocks)
+}
+
+/// Decodes [`LANES`] BC1 blocks with the instructions available on every CPU of the target.
+#[inline(always)]
+fn decode_bc1_blocks_portable(blocks: [[u8; 8]; LANES]) -> [[[u8; 4]; 16]; LANES] {
+    let mut color0 = [0; LANES];
+    let mut color1 = [0; LANES];
+    let mut indexes = [0; LANES];
+    let mut four_color = [0; LANES];
+    for l in 0..LANES {
+        let block = u64::from_le_bytes(blocks[l]);
+        color0[l] = block as u32 & 0xFFFF;
+        color1[l] = (block >> 16) as u32 & 0xFFFF;
+        indexes[l] = (block >> 32) as u32;
+        four_color[l] = 0_u32.wrapping_sub(u32::from(color0[l] > color1[l]));
+    }
+
+    // The palette colors as little-endian RGBA
+    let mut palette = [[0xFF00_0000; LANES]; 4];
+    for l in 0..LANES {
+        palette[3][l] &= four_color[l];
+    }
+    let colors = [&color0, &color1];
+    add_palette_channel(&mut palette, colors, &four_color, 11, 0x1F);
+    add_palette_channel(&mut palette, colors, &four_color, 5, 0x3F);
+    add_palette_channel(&mut palette, colors, &four_color, 0, 0x1F);
+
+    let mut pixels = [[[0; 4]; 16]; LANES];
+    for l in 0..LANES {
+        for (i, pixel) in pixels[l].iter_mut().enumerate() {
+            let index = (indexes[l] >> (i * 2)) & 0b11;
+            *pixel = palette[index as usize][l].to_le_bytes();
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codecs::dds::bc::decode_bc1_block;
+
+    fn check(blocks: [[u8; 8]; LANES]) {
+        let decoded = decode_bc1_blocks(blocks);
+        for (block, decoded) in blocks.into_iter().zip(decoded) {
+            assert_eq!(decoded, decode_bc1_block(block), "{block:?}");
+        }
+        // `decode_bc1_blocks` only uses one of the versions, so also check the portable one.
+        assert_eq!(decode_bc1_blocks_portable(blocks), decoded);
+    }
+
+    #[test]
+    fn all_endpoints() {
+        // Every color0 with a few color1, covering both modes and equal endpoints
+        let indexes = 0b11_10_01_00_u32.wrapping_mul(0x0101_0101).to_le_bytes();
+        for color0 in (0..=u16::MAX).step_by(LANES) {
+            for color1 in [0, 0x0821, 0x7BEF, 0x8410, color0, 0xFFFF] {
+                check(std::array::from_fn(|l| {
+                    let mut block = [0; 8];
+                    block[..2].copy_from_slice(&(color0 + l as u16).to_le_bytes());
+                    block[2..4].copy_from_slice(&color1.to_le_bytes());
+                    block[4..].copy_from_slice(&indexes);
+                    block
+                }));
+            }
+        }
+    }
+
+    #[test]
+    fn all_channel_pairs() {
+        // Every pair of 6-bit greens, and every pair of 5-bit reds and blues, in both modes
+        let color = |x: u16| ((x >> 1) << 11) | (x << 5) | (x >> 1);
+        let indexes = 0b11_10_01_00_u32.wrapping_mul(0x0101_0101).to_le_bytes();
+        for x0 in 0..64 {
+            for x1 in (0..64).step_by(LANES) {
+                check(std::array::from_fn(|l| {
+                    let mut block = [0; 8];
+                    block[..2].copy_from_slice(&color(x0).to_le_bytes());
+                    block[2..4].copy_from_slice(&color(x1 + l as u16).to_le_bytes());
+                    block[4..].copy_from_slice(&indexes);
+                    block
+                }));
+            }
+        }
+    }
+
+    quickcheck! {
+        fn matches_scalar_decoder(blocks: Vec<u64>) -> bool {
+            for chunk in blocks.chunks(LANES) {
+                check(std::array::from_fn(|l| chunk.get(l).copied().unwrap_or(0).to_le_bytes()));
+            }
+            true
+        }
+    }
+}
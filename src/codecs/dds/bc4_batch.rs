@@ -0,0 +1,231 @@
+//! Decoding several BC4 blocks at once.
+//!
+//! Like in [`bc1_batch`](super::bc1_batch), the palettes of [`LANES`] blocks are computed
+//! together with branchless loops over fixed-size arrays, which the compiler vectorizes. Both
+//! palettes of a block are computed, and the one of its mode is selected with a mask. The values
+//! fit into 16 bits, so a 256-bit AVX2 vector holds one value of every block.
+//!
+//! On aarch64, the palettes are computed with NEON intrinsics instead, because the compiler only
+//! vectorizes parts of the portable loops there. Two 128-bit vectors hold one value of every
+//! block, and the divisions by 7 and 5 are done with a widening multiplication.
+//!
+//! The results are bit-identical to the scalar decoder.
+
+/// The number of blocks decoded at once.
+pub(crate) const LANES: usize = 16;
+
+/// One value per block.
+type Lanes = [u16; LANES];
+
+/// Returns `round((w0 * x0 + w1 * x1) / (w0 + w1))` for each lane, like `bc4_interp_6` and
+/// `bc4_interp_4` round.
+#[cfg(any(not(target_arch = "aarch64"), test))]
+#[inline(always)]
+fn interpolate(x0: &Lanes, x1: &Lanes, w0: u16, w1: u16) -> Lanes {
+    // The divisor is a constant, so the division becomes a multiplication.
+    let mut result = [0; LANES];
+    for l in 0..LANES {
+        result[l] = (w0 * x0[l] + w1 * x1[l] + (w0 + w1) / 2) / (w0 + w1);
+    }
+    result
+}
+
+/// Decodes [`LANES`] blocks of the unsigned format (`BC4_UNORM`).
+pub(crate) fn decode_bc4_unsigned_blocks(blocks: [[u8; 8]; LANES]) -> [[[u8; 1]; 16]; LANES] {
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") {
+        // SAFETY: The CPU supports AVX2.
+        return unsafe { decode_bc4_unsigned_blocks_avx2(blocks) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    // SAFETY: NEON is always available on aarch64.
+    return unsafe { decode_bc4_unsigned_blocks_neon(blocks) };
+    #[cfg(not(target_arch = "aarch64"))]
+    decode_bc4_unsigned_blocks_portable(blocks)
+}
+
+/// [`decode_bc4_unsigned_blocks_portable`] compiled for AVX2.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn decode_bc4_unsigned_blocks_avx2(blocks: [[u8; 8]; LANES]) -> [[[u8; 1]; 16]; LANES] {
+    decode_bc4_unsigned_blocks_portable(blocks)
+}
+
+/// Decodes [`LANES`] blocks, computing the palettes with NEON intrinsics.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+fn decode_bc4_unsigned_blocks_neon(blocks: [[u8; 8]; LANES]) -> [[[u8; 1]; 16]; LANES] {
+    let (red0, red1) = endpoints(&blocks);
+    scatter_indexes(&blocks, &palettes_neon(&red0, &red1))
+}
+
+/// Decodes [`LANES`] blocks with the instructions available on every CPU of the target.
+///
+/// On aarch64, this is only used to test the NEON version.
+#[cfg(any(not(target_arch = "aarch64"), test))]
+#[inline(always)]
+fn decode_bc4_unsigned_blocks_portable(blocks: [[u8; 8]; LANES]) -> [[[u8; 1]; 16]; LANES] {
+    let (red0, red1) = endpoints(&blocks);
+    scatter_indexes(&blocks, &palettes_portable(&red0, &red1))
+}
+
+/// Returns the two endpoints of each block.
+#[inline(always)]
+fn endpoints(blocks: &[[u8; 8]; LANES]) -> (Lanes, Lanes) {
+    let mut red0 = [0; LANES];
+    let mut red1 = [0; LANES];
+    for l in 0..LANES {
+        red0[l] = u16::from(blocks[l][0]);
+        red1[l] = u16::from(blocks[l][1]);
+    }
+    (red0, red1)
+}
+
+/// Returns the 8 palette values of each block.
+#[cfg(any(not(target_arch = "aarch64"), test))]
+#[inline(always)]
+fn palettes_portable(red0: &Lanes, red1: &Lanes) -> [Lanes; 8] {
+    let mut six_interpolants = [0; LANES];
+    for l in 0..LANES {
+        six_interpolants[l] = 0_u16.wrapping_sub(u16::from(red0[l] > red1[l]));
+    }
+
+    let mut palette = [[0; LANES]; 8];
+    palette[0] = *red0;
+    palette[1] = *red1;
+    for i in 1..=4 {
+        let six = interpolate(red0, red1, 7 - i, i);
+        let four = interpolate(red0, red1, 5 - i, i);
+        for l in 0..LANES {
+            let mask = six_interpolants[l];
+            palette[i as usize + 1][l] = (six[l] & mask) | (four[l] & !mask);
+        }
+    }
+    // The last two values are 0 and 255 with 4 interpolated values.
+    let five_sevenths = interpolate(red0, red1, 2, 5);
+    let six_sevenths = interpolate(red0, red1, 1, 6);
+    for l in 0..LANES {
+        let mask = six_interpolants[l];
+        palette[6][l] = five_sevenths[l] & mask;
+        palette[7][l] = (six_sevenths[l] & mask) | (0xFF & !mask);
+    }
+    palette
+}
+
+/// Returns the 8 palette values of each block, like [`palettes_portable`].
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+fn palettes_neon(red0: &Lanes, red1: &Lanes) -> [Lanes; 8] {
+    use std::arch::aarch64::*;
+
+    /// Returns `round((w0 * x0 + w1 * x1) / (w0 + w1))`, where `w0 + w1` is 7 or 5.
+    ///
+    /// The sum is at most `7 * 255 + 3`, and for all such values, multiplying by `2^16 / 7` or
+    /// `2^16 / 5` rounded up and taking the upper 16 bits is the same as dividing.
+    #[target_feature(enable = "neon")]
+    #[inline]
+    fn interpolate(x0: uint16x8_t, x1: uint16x8_t, w0: u16, w1: u16) -> uint16x8_t {
+        let divisor = w0 + w1;
+        let reciprocal = match divisor {
+            7 => 9363,
+            5 => 13108,
+            _ => unreachable!(),
+        };
+        let sum = vaddq_u16(
+            vmlaq_n_u16(vmulq_n_u16(x0, w0), x1, w1),
+            vdupq_n_u16(divisor / 2),
+        );
+        let low = vmull_n_u16(vget_low_u16(sum), reciprocal);
+        let high = vmull_high_n_u16(sum, reciprocal);
+        vshrn_high_n_u32::<16>(vshrn_n_u32::<16>(low), high)
+    }
+
+    let mut palette = [[0; LANES]; 8];
+    palette[0] = *red0;
+    palette[1] = *red1;
+    // The lanes are processed in two halves of 8 blocks.
+    for half in 0..2 {
+        let range = half * 8..half * 8 + 8;
+        // SAFETY: The slices hold 8 values each.
+        let (x0, x1) = unsafe {
+            (
+                vld1q_u16(red0[range.clone()].as_ptr()),
+                vld1q_u16(red1[range.clone()].as_ptr()),
+            )
+        };
+        let six_interpolants = vcgtq_u16(x0, x1);
+
+        let mut values = [vdupq_n_u16(0); 6];
+        for i in 1..=4 {
+            let six = interpolate(x0, x1, 7 - i, i);
+            let four = interpolate(x0, x1, 5 - i, i);
+            values[i as usize - 1] = vbslq_u16(six_interpolants, six, four);
+        }
+        // The last two values are 0 and 255 with 4 interpolated values.
+        values[4] = vandq_u16(interpolate(x0, x1, 2, 5), six_interpolants);
+        values[5] = vbslq_u16(
+            six_interpolants,
+            interpolate(x0, x1, 1, 6),
+            vdupq_n_u16(0xFF),
+        );
+
+        for (i, value) in values.into_iter().enumerate() {
+            // SAFETY: The slice holds 8 values.
+            unsafe { vst1q_u16(palette[i + 2][range.clone()].as_mut_ptr(), value) };
+        }
+    }
+    palette
+}
+
+/// Looks up the palette value of each pixel of each block.
+#[inline(always)]
+fn scatter_indexes(blocks: &[[u8; 8]; LANES], palette: &[Lanes; 8]) -> [[[u8; 1]; 16]; LANES] {
+    let mut pixels = [[[0; 1]; 16]; LANES];
+    for l in 0..LANES {
+        let indexes = u64::from_le_bytes(blocks[l]) >> 16;
+        for (i, pixel) in pixels[l].iter_mut().enumerate() {
+            let index = (indexes >> (i * 3)) & 0b111;
+            *pixel = [palette[index as usize][l] as u8];
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codecs::dds::bc::decode_bc4_unsigned_block;
+
+    fn check(blocks: [[u8; 8]; LANES]) {
+        let decoded = decode_bc4_unsigned_blocks(blocks);
+        for (block, decoded) in blocks.into_iter().zip(decoded) {
+            assert_eq!(decoded, decode_bc4_unsigned_block(block), "{block:?}");
+        }
+        // `decode_bc4_unsigned_blocks` only uses one of the versions, so also check the portable
+        // one.
+        assert_eq!(decode_bc4_unsigned_blocks_portable(blocks), decoded);
+    }
+
+    #[test]
+    fn all_endpoints() {
+        // Every pair of endpoints, with all 8 indexes
+        let indexes = 0o7654_3210_u64 | 0o7654_3210 << 24;
+        for red0 in 0..=255 {
+            for red1 in (0..=255).step_by(LANES) {
+                check(std::array::from_fn(|l| {
+                    let block = indexes << 16 | (red1 + l as u64) << 8 | red0;
+                    block.to_le_bytes()
+                }));
+            }
+        }
+    }
+
+    quickcheck! {
+        fn matches_scalar_decoder(blocks: Vec<u64>) -> bool {
+            for chunk in blocks.chunks(LANES) {
+                check(std::array::from_fn(|l| chunk.get(l).copied().unwrap_or(0).to_le_bytes()));
+            }
+            true
+        }
+    }
+}
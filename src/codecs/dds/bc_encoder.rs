@@ -0,0 +1,1215 @@
+//! Encoders for individual 4x4 blocks of the block compressed (BC) formats.
+//!
+//! All encoders take the 16 pixels of a block in row-major order, the same order the decoders in
+//! [`super::bc`] return them in.
+
+use super::bc::{
+    bc1_palette, bc4_signed_palette, bc4_unsigned_palette, bc7_interpolate, bc7_unquantize,
+    decode_bc3_block, decode_bc7_block,
+};
+use super::convert::{unorm8_to_snorm8, x8_to_x4, B5G6R5};
+
+/// BC1 pixels with an alpha below this value are encoded as transparent black.
+pub(crate) const BC1_ALPHA_THRESHOLD: u8 = 128;
+
+/// Returns the squared distance between the RGB components of two colors.
+fn rgb_distance(a: [u8; 4], b: [u8; 4]) -> u32 {
+    a.iter()
+        .zip(b)
+        .take(3)
+        .map(|(&a, b)| (a as i32 - b as i32).pow(2) as u32)
+        .sum()
+}
+
+/// Returns the two ends of the line that best fits the given colors.
+///
+/// The line goes through the mean of the colors along their principal axis. Its ends are the
+/// projections of the outermost colors onto it. Returns `None` if there are no colors.
+fn fit_line<const N: usize>(
+    colors: impl Iterator<Item = [u8; N]> + Clone,
+) -> Option<([u8; N], [u8; N])> {
+    let count = colors.clone().count();
+    if count == 0 {
+        return None;
+    }
+    let mut mean = [0.0_f32; N];
+    for color in colors.clone() {
+        for (m, c) in mean.iter_mut().zip(color) {
+            *m += c as f32;
+        }
+    }
+    let mean = mean.map(|m| m / count as f32);
+    let offset =
+        |color: [u8; N]| -> [f32; N] { std::array::from_fn(|i| color[i] as f32 - mean[i]) };
+
+    let mut covariance = [[0.0_f32; N]; N];
+    for color in colors.clone() {
+        let d = offset(color);
+        for (row, di) in covariance.iter_mut().zip(d) {
+            for (cell, dj) in row.iter_mut().zip(d) {
+                *cell += di * dj;
+            }
+        }
+    }
+
+    // Find the principal axis with power iteration, starting with the covariance of the channel
+    // with the largest variance. The axis stays zero if all colors are the same.
+    let start = (0..N)
+        .max_by(|&a, &b| covariance[a][a].total_cmp(&covariance[b][b]))
+        .unwrap();
+    let mut axis = covariance[start];
+    for _ in 0..8 {
+        let next: [f32; N] = covariance.map(|row| row.iter().zip(axis).map(|(c, a)| c * a).sum());
+        let length = next.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if length == 0.0 {
+            break;
+        }
+        axis = next.map(|x| x / length);
+    }
+
+    let (mut low, mut high) = (f32::MAX, f32::MIN);
+    for color in colors {
+        let t: f32 = offset(color).iter().zip(axis).map(|(d, a)| d * a).sum();
+        low = low.min(t);
+        high = high.max(t);
+    }
+    let end = |t: f32| -> [u8; N] {
+        std::array::from_fn(|i| (mean[i] + axis[i] * t).round().clamp(0.0, 255.0) as u8)
+    };
+    Some((end(high), end(low)))
+}
+
+/// The tradeoff between speed and quality of the block encoders.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EncodeQuality {
+    /// Chooses the endpoints from the bounding box of the colors.
+    Fast,
+    /// Chooses the endpoints along the principal axis of the colors.
+    #[default]
+    Balanced,
+    /// Tries the endpoints of [`Fast`](Self::Fast) and [`Balanced`](Self::Balanced) and the other
+    /// diagonals of the bounding box, and refines the endpoints with the lowest error with least
+    /// squares.
+    Best,
+}
+
+/// Returns the two corners of the bounding box of the given colors, or `None` if there are no
+/// colors.
+///
+/// The corners are the maximum and minimum of each channel, except for the channels set in
+/// `flip`, which are swapped. So the 4 values of `flip` from 0 to 3 give the 4 diagonals.
+fn bounding_box(colors: impl Iterator<Item = [u8; 3]>, flip: u8) -> Option<([u8; 3], [u8; 3])> {
+    let mut colors = colors.peekable();
+    colors.peek()?;
+    let (mut high, mut low) = ([0_u8; 3], [255_u8; 3]);
+    for color in colors {
+        for i in 0..3 {
+            high[i] = high[i].max(color[i]);
+            low[i] = low[i].min(color[i]);
+        }
+    }
+    // Flipping green and blue covers the diagonals. Flipping red as well would only swap the ends.
+    for (i, bit) in [(1, 1), (2, 2)] {
+        if flip & bit != 0 {
+            std::mem::swap(&mut high[i], &mut low[i]);
+        }
+    }
+    Some((high, low))
+}
+
+/// Encodes a BC1 block with the given ends of the color line and returns it with the sum of
+/// squared errors of the opaque pixels.
+fn encode_bc1_endpoints(
+    pixels: &[[u8; 4]; 16],
+    transparent: [bool; 16],
+    (a, b): ([u8; 3], [u8; 3]),
+) -> ([u8; 8], u32) {
+    let three_color = transparent.contains(&true);
+    let mut color0 = B5G6R5::from_rgb8(a).to_u16();
+    let mut color1 = B5G6R5::from_rgb8(b).to_u16();
+    // The order of the endpoints selects the mode.
+    if three_color == (color0 > color1) {
+        std::mem::swap(&mut color0, &mut color1);
+    }
+
+    let palette = bc1_palette(color0, color1, true);
+    // Equal endpoints also select the 3-color mode, so index 3 may be transparent even if no
+    // pixel is.
+    let opaque_colors = if color0 > color1 { 4 } else { 3 };
+    let mut indexes = 0_u32;
+    let mut error = 0;
+    for (i, (&pixel, transparent)) in pixels.iter().zip(transparent).enumerate() {
+        let index = if transparent {
+            3
+        } else {
+            let (index, distance) = (0..opaque_colors)
+                .map(|index| (index, rgb_distance(pixel, palette[index])))
+                .min_by_key(|&(_, distance)| distance)
+                .unwrap();
+            error += distance;
+            index
+        };
+        indexes |= (index as u32) << (i * 2);
+    }
+
+    let mut block = [0; 8];
+    block[..2].copy_from_slice(&color0.to_le_bytes());
+    block[2..4].copy_from_slice(&color1.to_le_bytes());
+    block[4..].copy_from_slice(&indexes.to_le_bytes());
+    (block, error)
+}
+
+/// Returns the endpoints that minimize the squared error of the opaque pixels for the indexes of
+/// the given block, or `None` if they aren't unique.
+fn refine_bc1_endpoints(
+    pixels: &[[u8; 4]; 16],
+    transparent: [bool; 16],
+    block: [u8; 8],
+) -> Option<([u8; 3], [u8; 3])> {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let indexes = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    // The weight of color0 for each index
+    let weights = if color0 > color1 {
+        [1.0, 0.0, 2.0 / 3.0, 1.0 / 3.0]
+    } else {
+        [1.0, 0.0, 0.5, 0.0]
+    };
+
+    // The normal equations of pixel = w * end0 + (1 - w) * end1
+    let (mut aa, mut ab, mut bb) = (0.0_f32, 0.0_f32, 0.0_f32);
+    let (mut ax, mut bx) = ([0.0_f32; 3], [0.0_f32; 3]);
+    for (i, (pixel, transparent)) in pixels.iter().zip(transparent).enumerate() {
+        if transparent {
+            continue;
+        }
+        let w0 = weights[(indexes >> (i * 2)) as usize & 0b11];
+        let w1 = 1.0 - w0;
+        aa += w0 * w0;
+        ab += w0 * w1;
+        bb += w1 * w1;
+        for c in 0..3 {
+            ax[c] += w0 * pixel[c] as f32;
+            bx[c] += w1 * pixel[c] as f32;
+        }
+    }
+    let determinant = aa * bb - ab * ab;
+    if determinant.abs() < 1e-6 {
+        return None;
+    }
+    let end = |x: f32| x.round().clamp(0.0, 255.0) as u8;
+    let end0 = std::array::from_fn(|c| end((bb * ax[c] - ab * bx[c]) / determinant));
+    let end1 = std::array::from_fn(|c| end((aa * bx[c] - ab * ax[c]) / determinant));
+    Some((end0, end1))
+}
+
+/// Encodes a BC1 block.
+///
+/// Pixels with an alpha below [`BC1_ALPHA_THRESHOLD`] are encoded as transparent black, which
+/// requires the 3-color mode. Blocks without such pixels use the 4-color mode.
+pub(crate) fn encode_bc1_block(pixels: [[u8; 4]; 16], quality: EncodeQuality) -> [u8; 8] {
+    let transparent = pixels.map(|[_, _, _, a]| a < BC1_ALPHA_THRESHOLD);
+    encode_color_block(&pixels, transparent, quality)
+}
+
+/// Encodes the colors of a BC1 block, ignoring alpha.
+///
+/// The pixels in `transparent` are encoded as transparent black with the 3-color mode. If there
+/// are none, the block uses the 4-color mode, as BC2 and BC3 require. The endpoints are chosen as
+/// described by [`EncodeQuality`], and each pixel gets the index of the closest color.
+fn encode_color_block(
+    pixels: &[[u8; 4]; 16],
+    transparent: [bool; 16],
+    quality: EncodeQuality,
+) -> [u8; 8] {
+    let opaque = pixels
+        .iter()
+        .zip(transparent)
+        .filter(|(_, transparent)| !transparent)
+        .map(|(&[r, g, b, _], _)| [r, g, b]);
+
+    let encode =
+        |ends: Option<_>| encode_bc1_endpoints(pixels, transparent, ends.unwrap_or_default());
+    match quality {
+        EncodeQuality::Fast => encode(bounding_box(opaque, 0)).0,
+        EncodeQuality::Balanced => encode(fit_line(opaque)).0,
+        EncodeQuality::Best => {
+            let mut best = encode(fit_line(opaque.clone()));
+            for flip in 0..4 {
+                let candidate = encode(bounding_box(opaque.clone(), flip));
+                if candidate.1 < best.1 {
+                    best = candidate;
+                }
+            }
+            // Refining the endpoints of the best candidate changes its indexes, which may allow
+            // further refinement.
+            for _ in 0..2 {
+                let Some(ends) = refine_bc1_endpoints(pixels, transparent, best.0) else {
+                    break;
+                };
+                let candidate = encode(Some(ends));
+                if candidate.1 >= best.1 {
+                    break;
+                }
+                best = candidate;
+            }
+            best.0
+        }
+    }
+}
+
+/// Returns the index of the closest palette value for each pixel and the sum of squared errors.
+fn bc4_closest_indexes(pixels: [[u8; 1]; 16], palette: [u8; 8]) -> ([u8; 16], u32) {
+    let mut error = 0;
+    let indexes = pixels.map(|[value]| {
+        let (index, distance) = palette
+            .iter()
+            .map(|&p| (p as i32 - value as i32).pow(2) as u32)
+            .enumerate()
+            .min_by_key(|&(_, distance)| distance)
+            .unwrap();
+        error += distance;
+        index as u8
+    });
+    (indexes, error)
+}
+
+/// Encodes a BC4 block with values in `0..=max` and returns the endpoints and indexes.
+///
+/// Both modes are tried: 6 values interpolated between the minimum and maximum, and 4 values
+/// interpolated between the minimum and maximum excluding 0 and `max`, which the mode stores
+/// exactly. The mode with the lower error is used.
+fn encode_bc4_values(
+    pixels: [[u8; 1]; 16],
+    max: u8,
+    palette: impl Fn(u8, u8) -> [u8; 8],
+) -> (u8, u8, u64) {
+    let values = pixels.map(|[value]| value);
+    let low = values.iter().copied().min().unwrap();
+    let high = values.iter().copied().max().unwrap();
+    let inner = values.iter().copied().filter(|&v| v != 0 && v != max);
+    let inner_min = inner.clone().min().unwrap_or(0);
+    let inner_max = inner.max().unwrap_or(0);
+
+    // `red0 > red1` selects 6 interpolated values, `red0 <= red1` selects 4.
+    let (red0, red1, indexes) = [(high, low), (inner_min, inner_max)]
+        .into_iter()
+        .map(|(red0, red1)| {
+            let (indexes, error) = bc4_closest_indexes(pixels, palette(red0, red1));
+            (red0, red1, indexes, error)
+        })
+        .min_by_key(|&(.., error)| error)
+        .map(|(red0, red1, indexes, _)| (red0, red1, indexes))
+        .unwrap();
+
+    let indexes = (0..16).fold(0_u64, |acc, i| acc | (indexes[i] as u64) << (i * 3));
+    (red0, red1, indexes)
+}
+
+/// Returns a BC4 block with the given raw endpoints and indexes.
+fn bc4_block(red0: u8, red1: u8, indexes: u64) -> [u8; 8] {
+    let mut block = (indexes << 16).to_le_bytes();
+    block[0] = red0;
+    block[1] = red1;
+    block
+}
+
+/// Encodes a BC4 block of the unsigned format (`BC4_UNORM`).
+///
+/// See [`encode_bc4_values`] for how the mode is chosen.
+pub(crate) fn encode_bc4_unsigned_block(pixels: [[u8; 1]; 16]) -> [u8; 8] {
+    let (red0, red1, indexes) = encode_bc4_values(pixels, 255, bc4_unsigned_palette);
+    bc4_block(red0, red1, indexes)
+}
+
+/// Encodes a BC4 block of the signed format (`BC4_SNORM`) from unorm values, so 0 is encoded as
+/// -1.0 and 255 as 1.0.
+///
+/// The values are rounded to snorm first and then encoded like [`encode_bc4_unsigned_block`]
+/// does. -128 is never used as an endpoint.
+pub(crate) fn encode_bc4_signed_block(pixels: [[u8; 1]; 16]) -> [u8; 8] {
+    let pixels = pixels.map(|[value]| [unorm8_to_snorm8(value)]);
+    // The endpoints are offset by 127, which doesn't change their order, so the mode is the same.
+    let (red0, red1, indexes) = encode_bc4_values(pixels, 254, |red0, red1| {
+        bc4_signed_palette(red0, red1, red0 > red1)
+    });
+    bc4_block(red0.wrapping_sub(127), red1.wrapping_sub(127), indexes)
+}
+
+/// Encodes a BC5 block of the unsigned format (`BC5_UNORM`) from red and green.
+pub(crate) fn encode_bc5_unsigned_block(pixels: [[u8; 2]; 16]) -> [u8; 16] {
+    let red = encode_bc4_unsigned_block(pixels.map(|[r, _]| [r]));
+    let green = encode_bc4_unsigned_block(pixels.map(|[_, g]| [g]));
+    join_blocks(red, green)
+}
+
+/// Encodes a BC5 block of the signed format (`BC5_SNORM`) from unorm red and green, like
+/// [`encode_bc4_signed_block`].
+pub(crate) fn encode_bc5_signed_block(pixels: [[u8; 2]; 16]) -> [u8; 16] {
+    let red = encode_bc4_signed_block(pixels.map(|[r, _]| [r]));
+    let green = encode_bc4_signed_block(pixels.map(|[_, g]| [g]));
+    join_blocks(red, green)
+}
+
+/// Joins two 8-byte blocks into the 16-byte block of BC2, BC3 or BC5.
+fn join_blocks(first: [u8; 8], second: [u8; 8]) -> [u8; 16] {
+    bytemuck::cast([first, second])
+}
+
+/// Encodes the alpha block of BC2, which stores alpha rounded to 4 bits per pixel.
+fn encode_bc2_alpha(pixels: &[[u8; 4]; 16]) -> [u8; 8] {
+    let alpha = pixels
+        .iter()
+        .enumerate()
+        .fold(0_u64, |alpha, (i, &[_, _, _, a])| {
+            alpha | u64::from(x8_to_x4(a)) << (i * 4)
+        });
+    alpha.to_le_bytes()
+}
+
+/// Encodes the alpha block of BC3, which is a BC4 block.
+fn encode_bc3_alpha(pixels: &[[u8; 4]; 16]) -> [u8; 8] {
+    encode_bc4_unsigned_block(pixels.map(|[_, _, _, a]| [a]))
+}
+
+/// Encodes a BC2 block.
+///
+/// Alpha is rounded to 4 bits per pixel and stored in the first 8 bytes. The color is encoded like
+/// an opaque BC1 block and stored in the last 8 bytes. Unlike BC3, every pixel keeps its own
+/// alpha, which suits noisy alpha better than smooth gradients.
+pub(crate) fn encode_bc2_block(pixels: [[u8; 4]; 16], quality: EncodeQuality) -> [u8; 16] {
+    // BC2 has no transparent color, so the color block is encoded as fully opaque.
+    let color = encode_color_block(&pixels, [false; 16], quality);
+    join_blocks(encode_bc2_alpha(&pixels), color)
+}
+
+/// Encodes a BC3 block.
+///
+/// Alpha is encoded like a BC4 block and stored in the first 8 bytes. The color is encoded like
+/// an opaque BC1 block and stored in the last 8 bytes.
+pub(crate) fn encode_bc3_block(pixels: [[u8; 4]; 16], quality: EncodeQuality) -> [u8; 16] {
+    // BC3 has no transparent color, so the color block is encoded as fully opaque.
+    let color = encode_color_block(&pixels, [false; 16], quality);
+    join_blocks(encode_bc3_alpha(&pixels), color)
+}
+
+/// Transcodes a BC3 block into a BC1 block with the same colors by dropping its alpha block.
+///
+/// The color block of BC3 always uses the 4-color mode, but a standalone BC1 block only does if
+/// `color0 > color1`. Otherwise, the endpoints are swapped, which reverses the order of the
+/// palette, or all indexes are set to 0 if the endpoints are equal.
+pub(crate) fn transcode_bc3_to_bc1_block(block: [u8; 16]) -> [u8; 8] {
+    let [_, color]: [[u8; 8]; 2] = bytemuck::cast(block);
+    let color0 = u16::from_le_bytes([color[0], color[1]]);
+    let color1 = u16::from_le_bytes([color[2], color[3]]);
+    let indexes = u32::from_le_bytes([color[4], color[5], color[6], color[7]]);
+    let (color0, color1, indexes) = match color0.cmp(&color1) {
+        std::cmp::Ordering::Greater => return color,
+        // Swapping the endpoints swaps indexes 0 and 1, and 2 and 3.
+        std::cmp::Ordering::Less => (color1, color0, indexes ^ 0x5555_5555),
+        // All colors of the palette are the same.
+        std::cmp::Ordering::Equal => (color0, color1, 0),
+    };
+
+    let mut block = [0; 8];
+    block[..2].copy_from_slice(&color0.to_le_bytes());
+    block[2..4].copy_from_slice(&color1.to_le_bytes());
+    block[4..].copy_from_slice(&indexes.to_le_bytes());
+    block
+}
+
+/// Transcodes a BC3 normal map block with X in alpha and Y in green into a `BC5_UNORM` block.
+///
+/// The alpha block of BC3 has the same layout as a BC4 block, so X is kept exactly. Only Y is
+/// decoded and encoded again.
+pub(crate) fn transcode_bc3_to_bc5_block(block: [u8; 16]) -> [u8; 16] {
+    let [alpha, _]: [[u8; 8]; 2] = bytemuck::cast(block);
+    let green = encode_bc4_unsigned_block(decode_bc3_block(block).map(|[_, g, _, _]| [g]));
+    join_blocks(alpha, green)
+}
+
+/// Writes bits into a 128-bit block, least significant bit first.
+struct BitWriter {
+    bits: u128,
+    len: u8,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self { bits: 0, len: 0 }
+    }
+
+    fn write(&mut self, value: u8, count: u8) {
+        debug_assert!(u32::from(value) >> count == 0);
+        self.bits |= u128::from(value) << self.len;
+        self.len += count;
+    }
+
+    fn finish(self) -> [u8; 16] {
+        debug_assert_eq!(self.len, 128);
+        self.bits.to_le_bytes()
+    }
+}
+
+/// Returns the sum of squared errors of all components between two blocks.
+fn rgba_block_error(a: &[[u8; 4]; 16], b: &[[u8; 4]; 16]) -> u32 {
+    a.as_flattened()
+        .iter()
+        .zip(b.as_flattened())
+        .map(|(&a, &b)| (a as i32 - b as i32).pow(2) as u32)
+        .sum()
+}
+
+/// Returns the `bits`-bit value whose expansion to 8 bits is closest to `value`.
+fn bc7_quantize(value: u8, bits: u8) -> u8 {
+    let max = (1_u16 << bits) - 1;
+    let rounded = ((u16::from(value) * max + 127) / 255) as u8;
+    // The expansion replicates the high bits, so the rounded value may be off by one.
+    [
+        rounded.saturating_sub(1),
+        rounded,
+        rounded.saturating_add(1).min(max as u8),
+    ]
+    .into_iter()
+    .min_by_key(|&q| bc7_unquantize(q, bits).abs_diff(value))
+    .unwrap()
+}
+
+/// Returns the index of the closest interpolated value for each pixel.
+fn bc7_closest_indexes<const N: usize>(
+    pixels: &[[u8; N]; 16],
+    (e0, e1): ([u8; N], [u8; N]),
+    index_bits: u8,
+) -> [u8; 16] {
+    pixels.map(|pixel| {
+        (0..1 << index_bits)
+            .min_by_key(|&index| {
+                (0..N)
+                    .map(|c| {
+                        let value = bc7_interpolate(e0[c], e1[c], index, index_bits);
+                        (value as i32 - pixel[c] as i32).pow(2) as u32
+                    })
+                    .sum::<u32>()
+            })
+            .unwrap()
+    })
+}
+
+/// Makes the index of the first pixel fit into one bit less, as the anchor index of a subset is
+/// stored. Returns whether the indexes were inverted, in which case the endpoints have to be
+/// swapped.
+///
+/// The weights are symmetric, so swapping the endpoints and inverting the indexes gives the same
+/// pixels.
+fn bc7_fix_anchor(indexes: &mut [u8; 16], index_bits: u8) -> bool {
+    let max = (1 << index_bits) - 1;
+    if indexes[0] <= max >> 1 {
+        return false;
+    }
+    for index in indexes {
+        *index = max - *index;
+    }
+    true
+}
+
+/// Quantizes the components of an endpoint to 7 bits with the given P-bit as their least
+/// significant bit, and returns the 7-bit values.
+fn bc7_quantize_with_p_bit(endpoint: [u8; 4], p_bit: u8) -> [u8; 4] {
+    endpoint.map(|c| u16::from(c.saturating_sub(p_bit)).div_ceil(2).min(127) as u8)
+}
+
+/// Returns the P-bit with which an endpoint of mode 6 is closest to the given one.
+fn bc7_best_p_bit(endpoint: [u8; 4]) -> u8 {
+    (0..2)
+        .min_by_key(|&p_bit| {
+            let quantized = bc7_quantize_with_p_bit(endpoint, p_bit);
+            (0..4)
+                .map(|c| u32::from(endpoint[c].abs_diff(quantized[c] << 1 | p_bit)).pow(2))
+                .sum::<u32>()
+        })
+        .unwrap()
+}
+
+/// Encodes a BC7 block with mode 6, which has a single subset with 7-bit RGBA endpoints, a P-bit
+/// per endpoint, and 4-bit indexes.
+fn encode_bc7_mode_6(
+    pixels: &[[u8; 4]; 16],
+    (a, b): ([u8; 4], [u8; 4]),
+    mut p_bits: [u8; 2],
+) -> [u8; 16] {
+    let mut endpoints = [
+        bc7_quantize_with_p_bit(a, p_bits[0]),
+        bc7_quantize_with_p_bit(b, p_bits[1]),
+    ];
+    let expand = |i: usize| endpoints[i].map(|c| c << 1 | p_bits[i]);
+    let mut indexes = bc7_closest_indexes(pixels, (expand(0), expand(1)), 4);
+    if bc7_fix_anchor(&mut indexes, 4) {
+        endpoints.swap(0, 1);
+        p_bits.swap(0, 1);
+    }
+
+    let mut bits = BitWriter::new();
+    bits.write(1 << 6, 7);
+    for c in 0..4 {
+        for endpoint in endpoints {
+            bits.write(endpoint[c], 7);
+        }
+    }
+    for p_bit in p_bits {
+        bits.write(p_bit, 1);
+    }
+    for (i, index) in indexes.into_iter().enumerate() {
+        bits.write(index, 4 - u8::from(i == 0));
+    }
+    bits.finish()
+}
+
+/// Encodes a BC7 block with mode 5, which has a single subset with 7-bit color and 8-bit alpha
+/// endpoints, and separate 2-bit indexes for color and alpha.
+///
+/// The rotation swaps alpha with red, green, or blue for values 1 to 3, so the swapped channel
+/// gets its own indexes.
+fn encode_bc7_mode_5(pixels: &[[u8; 4]; 16], rotation: u8) -> [u8; 16] {
+    let pixels = pixels.map(|mut pixel| {
+        if rotation > 0 {
+            pixel.swap(rotation as usize - 1, 3);
+        }
+        pixel
+    });
+    let colors = pixels.map(|[r, g, b, _]| [r, g, b]);
+    let alphas = pixels.map(|[_, _, _, a]| [a]);
+
+    let (c0, c1) = fit_line(colors.iter().copied()).unwrap();
+    let mut color_endpoints = [c0, c1].map(|endpoint| endpoint.map(|c| bc7_quantize(c, 7)));
+    let expanded = color_endpoints.map(|endpoint| endpoint.map(|c| bc7_unquantize(c, 7)));
+    let mut color_indexes = bc7_closest_indexes(&colors, (expanded[0], expanded[1]), 2);
+    if bc7_fix_anchor(&mut color_indexes, 2) {
+        color_endpoints.swap(0, 1);
+    }
+
+    let alpha_min = alphas.iter().map(|[a]| *a).min().unwrap();
+    let alpha_max = alphas.iter().map(|[a]| *a).max().unwrap();
+    let mut alpha_endpoints = [alpha_max, alpha_min];
+    let mut alpha_indexes = bc7_closest_indexes(&alphas, ([alpha_max], [alpha_min]), 2);
+    if bc7_fix_anchor(&mut alpha_indexes, 2) {
+        alpha_endpoints.swap(0, 1);
+    }
+
+    let mut bits = BitWriter::new();
+    bits.write(1 << 5, 6);
+    bits.write(rotation, 2);
+    for c in 0..3 {
+        for endpoint in color_endpoints {
+            bits.write(endpoint[c], 7);
+        }
+    }
+    for endpoint in alpha_endpoints {
+        bits.write(endpoint, 8);
+    }
+    for indexes in [color_indexes, alpha_indexes] {
+        for (i, index) in indexes.into_iter().enumerate() {
+            bits.write(index, 2 - u8::from(i == 0));
+        }
+    }
+    bits.finish()
+}
+
+/// Encodes a BC7 block.
+///
+/// Only the single-subset modes are used. Mode 6 suits most blocks, since it fits all four
+/// channels along one line with 16 levels. Mode 5 stores alpha, or a channel rotated into alpha,
+/// with its own indexes, which suits blocks whose alpha doesn't follow the colors.
+///
+/// [`EncodeQuality::Fast`] only tries mode 6. [`EncodeQuality::Balanced`] also tries mode 5
+/// without rotation, and [`EncodeQuality::Best`] tries all rotations and all P-bits of mode 6.
+/// The candidate with the lowest squared error is used.
+pub(crate) fn encode_bc7_block(pixels: [[u8; 4]; 16], quality: EncodeQuality) -> [u8; 16] {
+    let ends = fit_line(pixels.iter().copied()).unwrap();
+    let best_p_bits = [[bc7_best_p_bit(ends.0), bc7_best_p_bit(ends.1)]];
+    let (p_bits, rotations): (&[[u8; 2]], &[u8]) = match quality {
+        EncodeQuality::Fast => (&best_p_bits, &[]),
+        EncodeQuality::Balanced => (&best_p_bits, &[0]),
+        EncodeQuality::Best => (&[[0, 0], [0, 1], [1, 0], [1, 1]], &[0, 1, 2, 3]),
+    };
+
+    let mode_6 = p_bits
+        .iter()
+        .map(|&p_bits| encode_bc7_mode_6(&pixels, ends, p_bits));
+    let mode_5 = rotations
+        .iter()
+        .map(|&rotation| encode_bc7_mode_5(&pixels, rotation));
+    mode_6
+        .chain(mode_5)
+        .min_by_key(|&block| rgba_block_error(&pixels, &decode_bc7_block(block)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codecs::dds::bc::{
+        decode_bc1_block, decode_bc2_block, decode_bc4_signed_block, decode_bc4_unsigned_block,
+        decode_bc5_signed_block_rg, decode_bc5_unsigned_block_rg,
+    };
+    use crate::codecs::dds::convert::{snorm8_to_unorm8, x4_to_x8};
+
+    /// Returns the largest difference of any RGB component between the two blocks.
+    fn max_rgb_error(a: &[[u8; 4]; 16], b: &[[u8; 4]; 16]) -> u8 {
+        a.iter()
+            .zip(b)
+            .flat_map(|(a, b)| (0..3).map(|i| a[i].abs_diff(b[i])))
+            .max()
+            .unwrap()
+    }
+
+    /// Quantizing an endpoint to 5 bits can be off by up to 255 / 31 / 2 = 4.1.
+    const QUANTIZATION_ERROR: u8 = 4;
+
+    const QUALITIES: [EncodeQuality; 3] = [
+        EncodeQuality::Fast,
+        EncodeQuality::Balanced,
+        EncodeQuality::Best,
+    ];
+
+    quickcheck! {
+        fn bc1_flat_blocks(r: u8, g: u8, b: u8) -> bool {
+            let pixels = [[r, g, b, 255]; 16];
+            QUALITIES.into_iter().all(|quality| {
+                let decoded = decode_bc1_block(encode_bc1_block(pixels, quality));
+                max_rgb_error(&pixels, &decoded) <= QUANTIZATION_ERROR
+                    && decoded.iter().all(|p| p[3] == 255)
+            })
+        }
+
+        fn bc1_two_color_blocks(
+            a: (u8, u8, u8),
+            b: (u8, u8, u8),
+            mask: u16,
+            transparent: u16
+        ) -> bool {
+            // The endpoints are the two colors, so each pixel is at most the quantization error
+            // plus the rounding of the endpoints away from one of them. The closest palette color
+            // may be a different one, but isn't further away.
+            let pixels: [[u8; 4]; 16] = std::array::from_fn(|i| {
+                let (r, g, b) = if mask >> i & 1 == 0 { a } else { b };
+                let alpha = if transparent >> i & 1 == 0 {
+                    255
+                } else {
+                    BC1_ALPHA_THRESHOLD - 1
+                };
+                [r, g, b, alpha]
+            });
+            let decoded = decode_bc1_block(encode_bc1_block(pixels, EncodeQuality::Balanced));
+            pixels.iter().zip(decoded).all(|(pixel, decoded)| {
+                if pixel[3] < BC1_ALPHA_THRESHOLD {
+                    decoded == [0, 0, 0, 0]
+                } else {
+                    let error: u32 = (0..3)
+                        .map(|i| u32::from(pixel[i].abs_diff(decoded[i])).pow(2))
+                        .sum();
+                    let bound = u32::from(QUANTIZATION_ERROR + 1).pow(2) * 3;
+                    decoded[3] == 255 && error <= bound
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn bc1_gradients() {
+        // A gradient with 4 levels fits the palette exactly.
+        let pixels: [[u8; 4]; 16] = std::array::from_fn(|i| {
+            let x = (i % 4) as u8;
+            [x * 85, 255 - x * 85, 0, 255]
+        });
+        let decoded = decode_bc1_block(encode_bc1_block(pixels, EncodeQuality::Balanced));
+        assert!(max_rgb_error(&pixels, &decoded) <= QUANTIZATION_ERROR);
+        assert!(decoded.iter().all(|p| p[3] == 255));
+
+        // A gradient with 16 levels has to be rounded to the 4 colors of the palette, which are a
+        // third of the range apart. So the error is at most a sixth of the range plus the
+        // quantization error.
+        let pixels: [[u8; 4]; 16] = std::array::from_fn(|i| {
+            let i = i as u8;
+            [20 + i * 4, 100, 200 - i * 8, 255]
+        });
+        let decoded = decode_bc1_block(encode_bc1_block(pixels, EncodeQuality::Balanced));
+        assert!(max_rgb_error(&pixels, &decoded) <= 120 / 6 + QUANTIZATION_ERROR);
+        assert!(decoded.iter().all(|p| p[3] == 255));
+
+        // A diagonal gradient with 7 levels
+        let pixels: [[u8; 4]; 16] = std::array::from_fn(|i| {
+            let v = (i % 4 + i / 4) as u8 * 30;
+            [v, v, v, 255]
+        });
+        let decoded = decode_bc1_block(encode_bc1_block(pixels, EncodeQuality::Balanced));
+        assert!(max_rgb_error(&pixels, &decoded) <= 180 / 6 + QUANTIZATION_ERROR);
+    }
+
+    #[test]
+    fn bc1_transparency() {
+        let pixels: [[u8; 4]; 16] = std::array::from_fn(|i| {
+            let v = i as u8 * 10;
+            let alpha = if i % 3 == 0 { 20 } else { 240 };
+            [v, 255 - v, 50, alpha]
+        });
+        let block = encode_bc1_block(pixels, EncodeQuality::Balanced);
+        let decoded = decode_bc1_block(block);
+
+        // the 3-color mode has colors that are half the range apart
+        let color0 = u16::from_le_bytes([block[0], block[1]]);
+        let color1 = u16::from_le_bytes([block[2], block[3]]);
+        assert!(color0 <= color1);
+        for (pixel, decoded) in pixels.iter().zip(decoded) {
+            if pixel[3] < BC1_ALPHA_THRESHOLD {
+                assert_eq!(decoded, [0, 0, 0, 0]);
+            } else {
+                assert_eq!(decoded[3], 255);
+                let error = (0..3).map(|i| pixel[i].abs_diff(decoded[i])).max().unwrap();
+                assert!(error <= 150 / 4 + QUANTIZATION_ERROR);
+            }
+        }
+
+        assert_eq!(
+            decode_bc1_block(encode_bc1_block(
+                [[255, 0, 0, 0]; 16],
+                EncodeQuality::Balanced
+            )),
+            [[0; 4]; 16]
+        );
+    }
+
+    /// Returns the sum of squared RGB errors of the opaque pixels after encoding with the given
+    /// quality.
+    fn bc1_error(pixels: [[u8; 4]; 16], quality: EncodeQuality) -> u32 {
+        let decoded = decode_bc1_block(encode_bc1_block(pixels, quality));
+        pixels
+            .iter()
+            .zip(decoded)
+            .filter(|(pixel, _)| pixel[3] >= BC1_ALPHA_THRESHOLD)
+            .map(|(&pixel, decoded)| rgb_distance(pixel, decoded))
+            .sum()
+    }
+
+    #[test]
+    fn bc1_best_is_no_worse() {
+        let mut state = 0x2545_F491_4F6C_DD1D_u64;
+        let mut noise = || {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state.to_le_bytes()
+        };
+        let mut blocks: Vec<[[u8; 4]; 16]> = vec![
+            // flat
+            [[30, 140, 250, 255]; 16],
+            // a gradient along the main diagonal of its bounding box
+            std::array::from_fn(|i| {
+                let v = i as u8 * 16;
+                [v, v, v / 2, 255]
+            }),
+            // a gradient along another diagonal of its bounding box
+            std::array::from_fn(|i| {
+                let v = i as u8 * 16;
+                [v, 255 - v, 0, 255]
+            }),
+            // two colors
+            std::array::from_fn(|i| {
+                if i % 3 == 0 {
+                    [200, 20, 90, 255]
+                } else {
+                    [10, 180, 60, 255]
+                }
+            }),
+            // a gradient with transparent pixels
+            std::array::from_fn(|i| {
+                let alpha = if i % 5 == 4 { 0 } else { 255 };
+                [i as u8 * 10, 100, 255 - i as u8 * 10, alpha]
+            }),
+        ];
+        for _ in 0..100 {
+            // noise around a color
+            let base = noise();
+            blocks.push(std::array::from_fn(|_| {
+                let n = noise();
+                let channel = |c: usize| base[c].saturating_add(n[c] % 32);
+                [channel(0), channel(1), channel(2), 255]
+            }));
+            // full noise
+            blocks.push(std::array::from_fn(|_| {
+                let [r, g, b, a, ..] = noise();
+                [r, g, b, a]
+            }));
+        }
+
+        for pixels in blocks {
+            let fast = bc1_error(pixels, EncodeQuality::Fast);
+            let balanced = bc1_error(pixels, EncodeQuality::Balanced);
+            let best = bc1_error(pixels, EncodeQuality::Best);
+            assert!(best <= fast, "{pixels:?}");
+            assert!(best <= balanced, "{pixels:?}");
+        }
+
+        // The bounding box of the second gradient misses the colors entirely.
+        let pixels = std::array::from_fn(|i| {
+            let v = i as u8 * 16;
+            [v, 255 - v, 0, 255]
+        });
+        assert!(
+            bc1_error(pixels, EncodeQuality::Best) < bc1_error(pixels, EncodeQuality::Fast) / 10
+        );
+    }
+
+    /// Returns the largest difference between the values of the two blocks.
+    fn max_error(a: &[[u8; 1]; 16], b: &[[u8; 1]; 16]) -> u8 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| a[0].abs_diff(b[0]))
+            .max()
+            .unwrap()
+    }
+
+    quickcheck! {
+        fn bc4_unsigned_flat_blocks(value: u8) -> bool {
+            let pixels = [[value]; 16];
+            decode_bc4_unsigned_block(encode_bc4_unsigned_block(pixels)) == pixels
+        }
+    }
+
+    #[test]
+    fn bc4_unsigned_ramp() {
+        // With 6 interpolated values, the 8 values of the palette are 255 / 7 apart. So the error
+        // is at most 255 / 14 plus 1 for rounding the palette.
+        let pixels: [[u8; 1]; 16] = std::array::from_fn(|i| [i as u8 * 17]);
+        let decoded = decode_bc4_unsigned_block(encode_bc4_unsigned_block(pixels));
+        assert!(max_error(&pixels, &decoded) <= 255 / 14 + 1);
+    }
+
+    #[test]
+    fn bc4_unsigned_modes() {
+        // 0 and 255 have their own values with 4 interpolated values, so these encode exactly
+        let pixels: [[u8; 1]; 16] = std::array::from_fn(|i| [[0, 100, 255, 140][i % 4]]);
+        let block = encode_bc4_unsigned_block(pixels);
+        assert!(block[0] <= block[1]);
+        assert_eq!(decode_bc4_unsigned_block(block), pixels);
+
+        // equal endpoints
+        let pixels: [[u8; 1]; 16] = std::array::from_fn(|i| [[0, 100, 255][i % 3]]);
+        let block = encode_bc4_unsigned_block(pixels);
+        assert_eq!(block[0], block[1]);
+        assert_eq!(decode_bc4_unsigned_block(block), pixels);
+
+        // values spread out evenly between the endpoints use 6 interpolated values
+        let pixels: [[u8; 1]; 16] = std::array::from_fn(|i| [10 + (i % 8) as u8 * 20]);
+        let block = encode_bc4_unsigned_block(pixels);
+        assert_eq!(block[..2], [150, 10]);
+        assert_eq!(decode_bc4_unsigned_block(block), pixels);
+    }
+
+    quickcheck! {
+        fn bc4_signed_flat_blocks(value: u8) -> bool {
+            let block = encode_bc4_signed_block([[value]; 16]);
+            let expected = snorm8_to_unorm8(unorm8_to_snorm8(value));
+            block[0] != 0x80
+                && block[1] != 0x80
+                && decode_bc4_signed_block(block) == [[expected]; 16]
+        }
+
+        fn bc5_round_trip(red: Vec<u8>, green: Vec<u8>) -> bool {
+            let pixels: [[u8; 2]; 16] = std::array::from_fn(|i| {
+                [red.get(i).copied().unwrap_or(0), green.get(i).copied().unwrap_or(255)]
+            });
+            // Like for BC3 alpha, the values are at most a tenth of the range off. Signed values
+            // are rounded to snorm first, which is off by at most 1 more.
+            let bound = |channel: usize| {
+                let values = pixels.map(|p| p[channel]);
+                let range = values.iter().max().unwrap() - values.iter().min().unwrap();
+                range / 10 + 1
+            };
+            let (red_bound, green_bound) = (bound(0), bound(1));
+
+            let unsigned = decode_bc5_unsigned_block_rg(encode_bc5_unsigned_block(pixels));
+            let signed = decode_bc5_signed_block_rg(encode_bc5_signed_block(pixels));
+            pixels.iter().zip(unsigned).zip(signed).all(|((&[r, g], unsigned), signed)| {
+                r.abs_diff(unsigned[0]) <= red_bound
+                    && g.abs_diff(unsigned[1]) <= green_bound
+                    && r.abs_diff(signed[0]) <= red_bound + 1
+                    && g.abs_diff(signed[1]) <= green_bound + 1
+            })
+        }
+    }
+
+    #[test]
+    fn bc4_signed_modes() {
+        // -1.0 and 1.0 have their own values with 4 interpolated values, and 128 is 0.0
+        let pixels: [[u8; 1]; 16] = std::array::from_fn(|i| [[0, 255, 128, 128][i % 4]]);
+        let block = encode_bc4_signed_block(pixels);
+        assert_eq!(block[..2], [0, 0]);
+        assert_eq!(decode_bc4_signed_block(block), pixels);
+
+        // With 6 interpolated values, the values are 254 / 7 apart after rounding to snorm.
+        let pixels: [[u8; 1]; 16] = std::array::from_fn(|i| [i as u8 * 17]);
+        let block = encode_bc4_signed_block(pixels);
+        assert_eq!(block[..2], [127, 0x81], "1.0 and -1.0");
+        let decoded = decode_bc4_signed_block(block);
+        assert!(max_error(&pixels, &decoded) <= 255 / 14 + 2);
+    }
+
+    #[test]
+    fn bc5_channels_are_bc4_blocks() {
+        let pixels: [[u8; 2]; 16] = std::array::from_fn(|i| [i as u8 * 16, 255 - i as u8 * 3]);
+        let red = pixels.map(|[r, _]| [r]);
+        let green = pixels.map(|[_, g]| [g]);
+
+        let block = encode_bc5_unsigned_block(pixels);
+        assert_eq!(block[..8], encode_bc4_unsigned_block(red));
+        assert_eq!(block[8..], encode_bc4_unsigned_block(green));
+
+        let block = encode_bc5_signed_block(pixels);
+        assert_eq!(block[..8], encode_bc4_signed_block(red));
+        assert_eq!(block[8..], encode_bc4_signed_block(green));
+    }
+
+    quickcheck! {
+        fn bc2_bc3_alpha_round_trip(alpha: Vec<u8>, color: (u8, u8, u8)) -> bool {
+            let (r, g, b) = color;
+            let pixels: [[u8; 4]; 16] =
+                std::array::from_fn(|i| [r, g, b, alpha.get(i).copied().unwrap_or(255)]);
+
+            // BC2 rounds to the nearest multiple of 17. BC3 rounds to the nearest of 8 values,
+            // which are at most a fifth of the range of the alpha values apart if the mode with 4
+            // interpolated values is used.
+            let min = pixels.iter().map(|p| p[3]).min().unwrap();
+            let max = pixels.iter().map(|p| p[3]).max().unwrap();
+            let bc3_error = (max - min) / 10 + 1;
+            let bc2 = decode_bc2_block(encode_bc2_block(pixels, EncodeQuality::Balanced));
+            let bc3 = decode_bc3_block(encode_bc3_block(pixels, EncodeQuality::Balanced));
+            pixels.iter().zip(bc2).zip(bc3).all(|((pixel, bc2), bc3)| {
+                pixel[3].abs_diff(bc2[3]) <= 8
+                    && pixel[3].abs_diff(bc3[3]) <= bc3_error
+                    && max_rgb_error(&[*pixel; 16], &[bc2; 16]) <= QUANTIZATION_ERROR
+                    && bc2[..3] == bc3[..3]
+            })
+        }
+    }
+
+    quickcheck! {
+        fn bc3_to_bc1_keeps_colors(bytes: Vec<u8>) -> bool {
+            let block: [u8; 16] = std::array::from_fn(|i| bytes.get(i).copied().unwrap_or(0));
+            let bc1 = decode_bc1_block(transcode_bc3_to_bc1_block(block));
+            let bc3 = decode_bc3_block(block);
+            bc1.iter()
+                .zip(bc3)
+                .all(|(bc1, bc3)| bc1[..3] == bc3[..3] && bc1[3] == 255)
+        }
+    }
+
+    #[test]
+    fn bc3_to_bc1_endpoint_order() {
+        let block = |color0: u16, color1: u16, indexes: u32| {
+            let mut block = [0xAB; 16];
+            block[8..10].copy_from_slice(&color0.to_le_bytes());
+            block[10..12].copy_from_slice(&color1.to_le_bytes());
+            block[12..].copy_from_slice(&indexes.to_le_bytes());
+            block
+        };
+        let bc1 = |color0: u16, color1: u16, indexes: u32| -> [u8; 8] {
+            block(color0, color1, indexes)[8..].try_into().unwrap()
+        };
+
+        // Blocks in the 4-color mode are kept.
+        let bc3 = block(0xF800, 0x001F, 0x1B1B_E4E4);
+        assert_eq!(
+            transcode_bc3_to_bc1_block(bc3),
+            bc1(0xF800, 0x001F, 0x1B1B_E4E4)
+        );
+        // Otherwise, the endpoints are swapped, or all pixels use the first one.
+        let bc3 = block(0x001F, 0xF800, 0x1B1B_E4E4);
+        assert_eq!(
+            transcode_bc3_to_bc1_block(bc3),
+            bc1(0xF800, 0x001F, 0x4E4E_B1B1)
+        );
+        let bc3 = block(0x07E0, 0x07E0, 0xFFFF_FFFF);
+        assert_eq!(transcode_bc3_to_bc1_block(bc3), bc1(0x07E0, 0x07E0, 0));
+    }
+
+    quickcheck! {
+        fn bc3_to_bc5_keeps_x(bytes: Vec<u8>) -> bool {
+            let block: [u8; 16] = std::array::from_fn(|i| bytes.get(i).copied().unwrap_or(0));
+            let bc5 = decode_bc5_unsigned_block_rg(transcode_bc3_to_bc5_block(block));
+            let bc3 = decode_bc3_block(block);
+            bc5.iter().zip(bc3).all(|(bc5, bc3)| bc5[0] == bc3[3])
+        }
+    }
+
+    #[test]
+    fn bc3_to_bc5_normal_map() {
+        // A 16x16 normal map of a dome, stored as BC3 with X in alpha and Y in green
+        let normal = |x: usize, y: usize| -> [u8; 4] {
+            let unorm = |v: usize| ((v as f32 - 7.5) / 12.0 * 127.5 + 127.5).round() as u8;
+            [0, unorm(y), 0, unorm(x)]
+        };
+        for block_y in 0..4 {
+            for block_x in 0..4 {
+                let pixels: [[u8; 4]; 16] =
+                    std::array::from_fn(|i| normal(block_x * 4 + i % 4, block_y * 4 + i / 4));
+                let bc3 = encode_bc3_block(pixels, EncodeQuality::Balanced);
+                let bc3_pixels = decode_bc3_block(bc3);
+                let bc5_pixels = decode_bc5_unsigned_block_rg(transcode_bc3_to_bc5_block(bc3));
+                for ((bc3, bc5), original) in bc3_pixels.iter().zip(bc5_pixels).zip(pixels) {
+                    assert_eq!(bc5[0], bc3[3]);
+                    assert!(bc5[1].abs_diff(bc3[1]) <= 2, "{bc5:?} {bc3:?}");
+                    assert!(bc5[0].abs_diff(original[3]) <= 2, "{bc5:?} {original:?}");
+                    assert!(bc5[1].abs_diff(original[1]) <= 4, "{bc5:?} {original:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bc2_stepped_alpha() {
+        // Multiples of 17 are stored exactly, and the steps in between round to the nearest one.
+        let pixels: [[u8; 4]; 16] = std::array::from_fn(|i| {
+            let v = (i % 4) as u8 * 85;
+            [v, 255 - v, 0, i as u8 * 17]
+        });
+        let block = encode_bc2_block(pixels, EncodeQuality::Balanced);
+        assert_eq!(
+            block[..8],
+            0xFEDC_BA98_7654_3210_u64.to_le_bytes(),
+            "first pixel in the low nibble"
+        );
+        let decoded = decode_bc2_block(block);
+        assert!(max_rgb_error(&pixels, &decoded) <= QUANTIZATION_ERROR);
+        for (pixel, decoded) in pixels.iter().zip(decoded) {
+            assert_eq!(pixel[3], decoded[3]);
+        }
+
+        for offset in [1, 8, 9, 16] {
+            let pixels: [[u8; 4]; 16] =
+                std::array::from_fn(|i| [0, 0, 0, (i as u8 * 17).saturating_sub(offset)]);
+            let decoded = decode_bc2_block(encode_bc2_block(pixels, EncodeQuality::Balanced));
+            for (pixel, decoded) in pixels.iter().zip(decoded) {
+                // 17 / 2 is the largest error of rounding to a multiple of 17
+                assert!(pixel[3].abs_diff(decoded[3]) <= 8, "{offset}");
+                let best = (0..=15).map(|a| x4_to_x8(a).abs_diff(pixel[3])).min();
+                assert_eq!(Some(pixel[3].abs_diff(decoded[3])), best);
+            }
+        }
+
+        // Noisy alpha doesn't affect the color.
+        let pixels: [[u8; 4]; 16] =
+            std::array::from_fn(|i| [200, 100, 50, [0, 255, 30, 220][i % 4]]);
+        let decoded = decode_bc2_block(encode_bc2_block(pixels, EncodeQuality::Balanced));
+        assert!(max_rgb_error(&pixels, &decoded) <= QUANTIZATION_ERROR);
+        assert_eq!(decoded.map(|p| p[3])[..4], [0, 255, 34, 221]);
+    }
+
+    #[test]
+    fn bc3_soft_alpha() {
+        // an 8x8 image with a diagonal alpha gradient and a gray gradient, encoded as 4 blocks
+        for (bx, by) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let pixels: [[u8; 4]; 16] = std::array::from_fn(|i| {
+                let x = bx * 4 + i % 4;
+                let y = by * 4 + i / 4;
+                let v = (x * 255 / 7) as u8;
+                [v, v, v, ((x + y) * 255 / 14) as u8]
+            });
+            let block = encode_bc3_block(pixels, EncodeQuality::Balanced);
+            assert_eq!(
+                block[..8],
+                encode_bc4_unsigned_block(pixels.map(|p| [p[3]]))
+            );
+
+            let decoded = decode_bc3_block(block);
+            // the gradients are linear, so they only lose what the palettes can't represent
+            assert!(max_rgb_error(&pixels, &decoded) <= QUANTIZATION_ERROR + 1);
+            for (pixel, decoded) in pixels.iter().zip(decoded) {
+                assert!(pixel[3].abs_diff(decoded[3]) <= 255 / 14 / 2 + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn bc3_uses_four_colors() {
+        // transparent pixels still get their color encoded
+        let pixels: [[u8; 4]; 16] = std::array::from_fn(|i| {
+            let x = (i % 4) as u8;
+            [x * 85, 0, 255 - x * 85, x * 10]
+        });
+        let block = encode_bc3_block(pixels, EncodeQuality::Balanced);
+        let color0 = u16::from_le_bytes([block[8], block[9]]);
+        let color1 = u16::from_le_bytes([block[10], block[11]]);
+        assert!(color0 > color1);
+
+        let decoded = decode_bc3_block(block);
+        assert!(max_rgb_error(&pixels, &decoded) <= QUANTIZATION_ERROR);
+    }
+
+    /// Returns the PSNR of all RGBA components of a decoded block in dB.
+    fn psnr(pixels: &[[u8; 4]; 16], decoded: &[[u8; 4]; 16]) -> f64 {
+        let mse = f64::from(rgba_block_error(pixels, decoded)) / 64.0;
+        10.0 * (255.0_f64.powi(2) / mse).log10()
+    }
+
+    quickcheck! {
+        fn bc7_flat_blocks(r: u8, g: u8, b: u8, a: u8) -> bool {
+            let pixels = [[r, g, b, a]; 16];
+            QUALITIES.into_iter().all(|quality| {
+                let decoded = decode_bc7_block(encode_bc7_block(pixels, quality));
+                decoded
+                    .iter()
+                    .all(|p| (0..4).all(|c| p[c].abs_diff(pixels[0][c]) <= 1))
+            })
+        }
+    }
+
+    #[test]
+    fn bc7_psnr() {
+        // color gradients with opaque, constant, and correlated alpha
+        let gradient: [[u8; 4]; 16] = std::array::from_fn(|i| {
+            let i = i as u8;
+            [i * 17, 255 - i * 13, 40 + i * 5, 255]
+        });
+        let translucent = gradient.map(|[r, g, b, _]| [r, g, b, 100]);
+        let fading = gradient.map(|[r, g, b, _]| [r, g, b, r]);
+        let diagonal: [[u8; 4]; 16] = std::array::from_fn(|i| {
+            let v = (i % 4 + i / 4) as u8 * 40;
+            [v, v / 2, 240 - v, 255]
+        });
+        // alpha runs across the color gradient, so the channels don't lie on one line
+        let alpha: [[u8; 4]; 16] = std::array::from_fn(|i| {
+            let (x, y) = ((i % 4) as u8, (i / 4) as u8);
+            [x * 80, 30 + x * 60, 250 - x * 70, y * 85]
+        });
+        // 7 levels don't fit the 16 uneven weights of mode 6 as well.
+        for (pixels, threshold) in [
+            (gradient, 45.0),
+            (translucent, 45.0),
+            (fading, 45.0),
+            (diagonal, 35.0),
+        ] {
+            for quality in QUALITIES {
+                let decoded = decode_bc7_block(encode_bc7_block(pixels, quality));
+                let psnr = psnr(&pixels, &decoded);
+                assert!(psnr > threshold, "{quality:?}: {psnr} dB for {pixels:?}");
+            }
+        }
+
+        // Mode 6 can't represent the alpha block, so only the qualities that try mode 5 do well.
+        let errors = QUALITIES.map(|quality| {
+            let block = encode_bc7_block(alpha, quality);
+            let decoded = decode_bc7_block(block);
+            if quality != EncodeQuality::Fast {
+                assert_eq!(block[0].trailing_zeros(), 5, "{quality:?}");
+                assert!(psnr(&alpha, &decoded) > 45.0, "{quality:?}");
+            }
+            rgba_block_error(&alpha, &decoded)
+        });
+        // Higher qualities try a superset of the candidates.
+        assert!(
+            errors[0] >= errors[1] && errors[1] >= errors[2],
+            "{errors:?}"
+        );
+    }
+}
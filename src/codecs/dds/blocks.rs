@@ -0,0 +1,244 @@
+//! Decoders and encoders for individual blocks of the block compressed (BC) formats.
+//!
+//! These functions decode or encode a single block without going through a DDS container, e.g.
+//! for blocks read from a KTX file or a GPU readback. Decoders take the bytes of exactly one block
+//! and return its 4x4 pixels in row-major order, i.e. the pixel at `(x, y)` within the block is at
+//! index `y * 4 + x`. Encoders take the pixels in the same order. None of them allocate.
+//!
+//! Signed formats other than BC6H are mapped to unorm, so -1.0 is 0 and 1.0 is 255, except for
+//! [`decode_bc4_signed_snorm`].
+//!
+//! # Examples
+//! ```rust
+//! use image::codecs::dds::blocks;
+//!
+//! // A BC1 block with a red and a blue endpoint, where every pixel uses the red endpoint
+//! let block = [0x00, 0xF8, 0x1F, 0x00, 0, 0, 0, 0];
+//! let pixels = blocks::decode_bc1(block);
+//! assert_eq!(pixels, [[255, 0, 0, 255]; 16]);
+//!
+//! // Encoding and decoding a solid color gives the same color back.
+//! assert_eq!(blocks::decode_bc1(blocks::encode_bc1(pixels)), pixels);
+//! ```
+
+use super::{bc, bc_encoder, EncodeQuality};
+
+/// Decodes a BC1 (DXT1) block into RGBA.
+///
+/// Blocks using the 3-color mode decode index 3 to transparent black.
+pub fn decode_bc1(block: [u8; 8]) -> [[u8; 4]; 16] {
+    bc::decode_bc1_block(block)
+}
+
+/// Decodes a BC2 (DXT3) block into RGBA.
+pub fn decode_bc2(block: [u8; 16]) -> [[u8; 4]; 16] {
+    bc::decode_bc2_block(block)
+}
+
+/// Decodes a BC3 (DXT5) block into RGBA.
+pub fn decode_bc3(block: [u8; 16]) -> [[u8; 4]; 16] {
+    bc::decode_bc3_block(block)
+}
+
+/// Decodes a `BC4_UNORM` block into its single channel.
+pub fn decode_bc4_unsigned(block: [u8; 8]) -> [[u8; 1]; 16] {
+    bc::decode_bc4_unsigned_block(block)
+}
+
+/// Decodes a `BC4_SNORM` block into its single channel.
+pub fn decode_bc4_signed(block: [u8; 8]) -> [[u8; 1]; 16] {
+    bc::decode_bc4_signed_block(block)
+}
+
+/// Decodes a `BC4_SNORM` block into its single channel as signed values.
+///
+/// -1.0 is -127, 0.0 is 0, and 1.0 is 127. This keeps values like signed displacements exact,
+/// which the mapping of [`decode_bc4_signed`] to unorm doesn't.
+pub fn decode_bc4_signed_snorm(block: [u8; 8]) -> [[i8; 1]; 16] {
+    bc::decode_bc4_signed_block_snorm(block)
+}
+
+/// Decodes a `BC5_UNORM` block into RGB. Blue is always 0.
+pub fn decode_bc5_unsigned(block: [u8; 16]) -> [[u8; 3]; 16] {
+    bc::decode_bc5_unsigned_block(block)
+}
+
+/// Decodes a `BC5_SNORM` block into RGB. Blue is always 128.
+pub fn decode_bc5_signed(block: [u8; 16]) -> [[u8; 3]; 16] {
+    bc::decode_bc5_signed_block(block)
+}
+
+/// Decodes a `BC5_UNORM` block into its red and green channels.
+pub fn decode_bc5_unsigned_rg(block: [u8; 16]) -> [[u8; 2]; 16] {
+    bc::decode_bc5_unsigned_block_rg(block)
+}
+
+/// Decodes a `BC5_SNORM` block into its red and green channels.
+pub fn decode_bc5_signed_rg(block: [u8; 16]) -> [[u8; 2]; 16] {
+    bc::decode_bc5_signed_block_rg(block)
+}
+
+/// Decodes a `BC5_UNORM` normal map block into RGB.
+///
+/// Blue is reconstructed as `sqrt(1 - x² - y²)`, where x and y are red and green mapped to -1..=1.
+pub fn decode_bc5_unsigned_normal(block: [u8; 16]) -> [[u8; 3]; 16] {
+    bc::decode_bc5_unsigned_block_normal(block)
+}
+
+/// Decodes a `BC5_SNORM` normal map block into RGB.
+///
+/// Blue is reconstructed like in [`decode_bc5_unsigned_normal`].
+pub fn decode_bc5_signed_normal(block: [u8; 16]) -> [[u8; 3]; 16] {
+    bc::decode_bc5_signed_block_normal(block)
+}
+
+/// Decodes a `BC4_UNORM` block into its single channel with 16 bits.
+///
+/// Interpolated values are more precise than with [`decode_bc4_unsigned`], which matters for
+/// smooth gradients like height maps.
+pub fn decode_bc4_unsigned_u16(block: [u8; 8]) -> [[u16; 1]; 16] {
+    bc::decode_bc4_unsigned_block_u16(block)
+}
+
+/// Decodes a `BC4_SNORM` block into its single channel with 16 bits.
+pub fn decode_bc4_signed_u16(block: [u8; 8]) -> [[u16; 1]; 16] {
+    bc::decode_bc4_signed_block_u16(block)
+}
+
+/// Decodes a `BC5_UNORM` block into RGB with 16 bits. Blue is always 0.
+pub fn decode_bc5_unsigned_u16(block: [u8; 16]) -> [[u16; 3]; 16] {
+    bc::decode_bc5_unsigned_block_u16(block)
+}
+
+/// Decodes a `BC5_SNORM` block into RGB with 16 bits. Blue is always 32768.
+pub fn decode_bc5_signed_u16(block: [u8; 16]) -> [[u16; 3]; 16] {
+    bc::decode_bc5_signed_block_u16(block)
+}
+
+/// Decodes a `BC5_UNORM` normal map block into RGB with 16 bits.
+///
+/// Blue is reconstructed like in [`decode_bc5_unsigned_normal`].
+pub fn decode_bc5_unsigned_normal_u16(block: [u8; 16]) -> [[u16; 3]; 16] {
+    bc::decode_bc5_unsigned_block_normal_u16(block)
+}
+
+/// Decodes a `BC5_SNORM` normal map block into RGB with 16 bits.
+///
+/// Blue is reconstructed like in [`decode_bc5_unsigned_normal`].
+pub fn decode_bc5_signed_normal_u16(block: [u8; 16]) -> [[u16; 3]; 16] {
+    bc::decode_bc5_signed_block_normal_u16(block)
+}
+
+/// Decodes a `BC6H_UF16` block into RGB half floats.
+///
+/// Each channel holds the bits of an IEEE 754 half float. Alpha is always opaque in BC6H.
+pub fn decode_bc6h_unsigned(block: [u8; 16]) -> [[u16; 3]; 16] {
+    bc::decode_bc6h_unsigned_block(block)
+}
+
+/// Decodes a `BC6H_SF16` block into RGB half floats.
+///
+/// Each channel holds the bits of an IEEE 754 half float. Unlike the other signed formats,
+/// negative values are preserved.
+pub fn decode_bc6h_signed(block: [u8; 16]) -> [[u16; 3]; 16] {
+    bc::decode_bc6h_signed_block(block)
+}
+
+/// Decodes a `BC6H_UF16` block into RGB floats.
+///
+/// BC6H stores HDR data, so values can be greater than 1.0.
+pub fn decode_bc6h_unsigned_f32(block: [u8; 16]) -> [[f32; 3]; 16] {
+    bc::decode_bc6h_unsigned_block_f32(block)
+}
+
+/// Decodes a `BC6H_SF16` block into RGB floats.
+///
+/// BC6H stores HDR data, so values can be greater than 1.0 or less than -1.0.
+pub fn decode_bc6h_signed_f32(block: [u8; 16]) -> [[f32; 3]; 16] {
+    bc::decode_bc6h_signed_block_f32(block)
+}
+
+/// Decodes a BC7 block into RGBA.
+///
+/// Blocks with a reserved mode decode to transparent black.
+pub fn decode_bc7(block: [u8; 16]) -> [[u8; 4]; 16] {
+    bc::decode_bc7_block(block)
+}
+
+/// Encodes RGBA pixels into a BC1 (DXT1) block.
+///
+/// Pixels with an alpha below 128 are encoded as transparent black. All other pixels are opaque.
+pub fn encode_bc1(pixels: [[u8; 4]; 16]) -> [u8; 8] {
+    encode_bc1_with_quality(pixels, EncodeQuality::default())
+}
+
+/// Encodes RGBA pixels into a BC1 (DXT1) block with the given quality.
+///
+/// Pixels with an alpha below 128 are encoded as transparent black. All other pixels are opaque.
+pub fn encode_bc1_with_quality(pixels: [[u8; 4]; 16], quality: EncodeQuality) -> [u8; 8] {
+    bc_encoder::encode_bc1_block(pixels, quality)
+}
+
+/// Encodes a single channel into a `BC4_UNORM` block.
+pub fn encode_bc4_unsigned(pixels: [[u8; 1]; 16]) -> [u8; 8] {
+    bc_encoder::encode_bc4_unsigned_block(pixels)
+}
+
+/// Encodes a single channel into a `BC4_SNORM` block.
+///
+/// Like the decoders, the values are unorm, so 0 is encoded as -1.0 and 255 as 1.0.
+pub fn encode_bc4_signed(pixels: [[u8; 1]; 16]) -> [u8; 8] {
+    bc_encoder::encode_bc4_signed_block(pixels)
+}
+
+/// Encodes red and green into a `BC5_UNORM` block.
+pub fn encode_bc5_unsigned(pixels: [[u8; 2]; 16]) -> [u8; 16] {
+    bc_encoder::encode_bc5_unsigned_block(pixels)
+}
+
+/// Encodes red and green into a `BC5_SNORM` block.
+///
+/// Like the decoders, the values are unorm, so 0 is encoded as -1.0 and 255 as 1.0.
+pub fn encode_bc5_signed(pixels: [[u8; 2]; 16]) -> [u8; 16] {
+    bc_encoder::encode_bc5_signed_block(pixels)
+}
+
+/// Encodes RGBA pixels into a BC2 (DXT3) block.
+///
+/// Alpha is rounded to the nearest of the 16 values BC2 can store.
+pub fn encode_bc2(pixels: [[u8; 4]; 16]) -> [u8; 16] {
+    bc_encoder::encode_bc2_block(pixels, EncodeQuality::default())
+}
+
+/// Encodes RGBA pixels into a BC3 (DXT5) block.
+pub fn encode_bc3(pixels: [[u8; 4]; 16]) -> [u8; 16] {
+    bc_encoder::encode_bc3_block(pixels, EncodeQuality::default())
+}
+
+/// Encodes RGBA pixels into a BC7 block.
+pub fn encode_bc7(pixels: [[u8; 4]; 16]) -> [u8; 16] {
+    encode_bc7_with_quality(pixels, EncodeQuality::default())
+}
+
+/// Encodes RGBA pixels into a BC7 block with the given quality.
+///
+/// Higher qualities try more of the modes of BC7 and keep the one with the lowest error.
+pub fn encode_bc7_with_quality(pixels: [[u8; 4]; 16], quality: EncodeQuality) -> [u8; 16] {
+    bc_encoder::encode_bc7_block(pixels, quality)
+}
+
+/// Transcodes a BC3 (DXT5) block into a BC1 (DXT1) block by dropping its alpha.
+///
+/// The colors are kept exactly, and all pixels of the BC1 block are opaque. This is much faster
+/// than decoding and encoding the block again.
+pub fn transcode_bc3_to_bc1(block: [u8; 16]) -> [u8; 8] {
+    bc_encoder::transcode_bc3_to_bc1_block(block)
+}
+
+/// Transcodes a BC3 (DXT5) normal map block into a `BC5_UNORM` block.
+///
+/// The BC3 block must store X in alpha and Y in green, which is common for normal maps. These
+/// become red and green of the BC5 block. X is kept exactly, while Y is decoded and encoded again.
+pub fn transcode_bc3_to_bc5(block: [u8; 16]) -> [u8; 16] {
+    bc_encoder::transcode_bc3_to_bc5_block(block)
+}
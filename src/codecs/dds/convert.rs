@@ -0,0 +1,583 @@
+//! Conversions between the pixel representations used by DDS surfaces.
+
+use std::sync::LazyLock;
+
+use crate::Primitive;
+
+/// Converts a `from_bits`-bit unorm to a `to_bits`-bit unorm.
+///
+/// This is equivalent to `round(value * (2^to_bits - 1) / (2^from_bits - 1))`. When widening,
+/// this is the same as replicating the bits of `value`, e.g. 4 bits `abcd` become `abcdabcd`.
+#[inline(always)]
+pub(crate) fn expand_bits(value: u32, from_bits: u32, to_bits: u32) -> u32 {
+    debug_assert!((1..=16).contains(&from_bits) && (1..=16).contains(&to_bits));
+    let max_from = (1 << from_bits) - 1;
+    let max_to = (1 << to_bits) - 1;
+    debug_assert!(value <= max_from);
+    // Both maximums are constants after inlining, so the division becomes a multiplication.
+    (value * max_to + max_from / 2) / max_from
+}
+
+/// Converts a 4-bit unorm to an 8-bit unorm.
+#[inline(always)]
+pub(crate) fn x4_to_x8(x: u8) -> u8 {
+    expand_bits(x as u32, 4, 8) as u8
+}
+
+/// Converts an 8-bit unorm to the nearest 4-bit unorm.
+///
+/// This is equivalent to `round(x * 15 / 255)`, so `x4_to_x8(x8_to_x4(x))` is the closest value
+/// to `x` a 4-bit unorm can represent.
+#[inline(always)]
+pub(crate) fn x8_to_x4(x: u8) -> u8 {
+    ((x as u16 * 15 + 127) / 255) as u8
+}
+
+/// Converts a 5-bit unorm to an 8-bit unorm.
+#[inline(always)]
+pub(crate) fn x5_to_x8(x: u8) -> u8 {
+    expand_bits(x as u32, 5, 8) as u8
+}
+
+/// Converts a 6-bit unorm to an 8-bit unorm.
+#[inline(always)]
+pub(crate) fn x6_to_x8(x: u8) -> u8 {
+    expand_bits(x as u32, 6, 8) as u8
+}
+
+/// Converts an 8-bit snorm, offset to the range `0..=254`, to an 8-bit unorm.
+///
+/// The input represents the signed value `x - 127`, so 0 is -1.0, 127 is 0.0 and 254 is 1.0.
+/// This is equivalent to `round(x * 255 / 254)`.
+#[inline(always)]
+pub(crate) fn snorm8_to_unorm8(x: u8) -> u8 {
+    debug_assert!(x <= 254);
+    ((x as u16 * 255 + 127) / 254) as u8
+}
+
+/// Converts an 8-bit unorm to an 8-bit snorm offset to the range `0..=254`, the inverse of
+/// [`snorm8_to_unorm8`]. This is equivalent to `round(x * 254 / 255)`.
+#[inline(always)]
+pub(crate) fn unorm8_to_snorm8(x: u8) -> u8 {
+    ((x as u16 * 254 + 127) / 255) as u8
+}
+
+/// Returns the BC4 value `factor / 7` of the way from `c0` to `c1`, rounded to the nearest
+/// integer. These are the 6 interpolated values of blocks with `red0 > red1`.
+#[inline(always)]
+pub(crate) fn bc4_interp_6(c0: u8, c1: u8, factor: u16) -> u8 {
+    debug_assert!((1..=6).contains(&factor));
+    (((7 - factor) * c0 as u16 + factor * c1 as u16 + 3) / 7) as u8
+}
+
+/// Returns the BC4 value `factor / 5` of the way from `c0` to `c1`, rounded to the nearest
+/// integer. These are the 4 interpolated values of blocks with `red0 <= red1`.
+#[inline(always)]
+pub(crate) fn bc4_interp_4(c0: u8, c1: u8, factor: u16) -> u8 {
+    debug_assert!((1..=4).contains(&factor));
+    (((5 - factor) * c0 as u16 + factor * c1 as u16 + 2) / 5) as u8
+}
+
+/// Copies the single channel of decoded BC4 pixels to red, green, and blue, with opaque alpha.
+pub(crate) fn bc4_to_rgba<T: Primitive>(pixels: [[T; 1]; 16]) -> [[T; 4]; 16] {
+    pixels.map(|[x]| [x, x, x, T::DEFAULT_MAX_VALUE])
+}
+
+/// Unpacks a `R10G10B10A2_UNORM` pixel to 16 bits per channel.
+///
+/// Red is stored in the least significant bits, alpha in the 2 most significant bits.
+pub(crate) fn rgb10a2_unpack(value: u32) -> [u16; 4] {
+    [
+        expand_bits(value & 0x3FF, 10, 16) as u16,
+        expand_bits((value >> 10) & 0x3FF, 10, 16) as u16,
+        expand_bits((value >> 20) & 0x3FF, 10, 16) as u16,
+        expand_bits(value >> 30, 2, 16) as u16,
+    ]
+}
+
+/// Converts a `R9G9B9E5_SHAREDEXP` pixel to RGB floats.
+///
+/// Each channel has a 9-bit mantissa without an implicit leading one, stored with red in the
+/// least significant bits. The channels share the exponent in the 5 most significant bits, which
+/// has a bias of 15. This is exact.
+pub(crate) fn rgb9e5_to_f32(value: u32) -> [f32; 3] {
+    let exponent = (value >> 27) as i32;
+    let scale = 2.0_f32.powi(exponent - 15 - 9);
+    [0, 9, 18].map(|shift| ((value >> shift) & 0x1FF) as f32 * scale)
+}
+
+/// Converts the bits of an IEEE 754 half float to an `f32`.
+///
+/// This is exact, because every half float can be represented as an `f32`. Subnormals, infinities,
+/// and NaNs, including their sign and payload, are preserved.
+pub(crate) fn half_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exponent = u32::from((bits >> 10) & 0x1F);
+    let mantissa = u32::from(bits & 0x3FF);
+
+    let magnitude = match exponent {
+        // zero and subnormals are exactly `mantissa * 2^-24`
+        0 => (mantissa as f32 / 16_777_216.0).to_bits(),
+        // infinity and NaN
+        31 => 0x7F80_0000 | (mantissa << 13),
+        _ => ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(sign | magnitude)
+}
+
+/// Converts an sRGB-encoded 8-bit unorm to a linear value in the range `0.0..=1.0`.
+///
+/// This uses the piecewise sRGB transfer function, not a 2.2 gamma approximation.
+pub(crate) fn srgb8_to_linear_f32(x: u8) -> f32 {
+    let x = x as f32 / 255.0;
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear value in the range `0.0..=1.0` to an sRGB-encoded 8-bit unorm, rounded to
+/// the nearest value. This is the inverse of [`srgb8_to_linear_f32`]. Values outside the range,
+/// including NaN, are clamped.
+pub(crate) fn linear_f32_to_srgb8(x: f32) -> u8 {
+    let x = x.clamp(0.0, 1.0);
+    let srgb = if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    };
+    // NaN becomes 0
+    (srgb * 255.0).round() as u8
+}
+
+/// Converts an sRGB-encoded 8-bit unorm to a linear 8-bit unorm, rounded to the nearest value.
+pub(crate) fn srgb8_to_linear8(x: u8) -> u8 {
+    (srgb8_to_linear_f32(x) * 255.0).round() as u8
+}
+
+/// [`srgb8_to_linear_f32`] for every 8-bit value, so surfaces can be converted without evaluating
+/// the transfer function for each channel.
+pub(crate) static SRGB_TO_LINEAR_F32: LazyLock<[f32; 256]> =
+    LazyLock::new(|| std::array::from_fn(|x| srgb8_to_linear_f32(x as u8)));
+
+/// [`srgb8_to_linear8`] for every 8-bit value.
+pub(crate) static SRGB_TO_LINEAR8: LazyLock<[u8; 256]> =
+    LazyLock::new(|| std::array::from_fn(|x| srgb8_to_linear8(x as u8)));
+
+/// Multiplies the colors of an RGBA8 pixel by its alpha.
+///
+/// This is equivalent to `round(color * alpha / 255)`.
+pub(crate) fn premultiply_alpha([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    let premultiply = |c: u8| ((c as u16 * a as u16 + 127) / 255) as u8;
+    [premultiply(r), premultiply(g), premultiply(b), a]
+}
+
+/// Divides the colors of a premultiplied RGBA8 pixel by its alpha, the inverse of
+/// [`premultiply_alpha`].
+///
+/// This is equivalent to `min(round(color * 255 / alpha), 255)`. Pixels with an alpha of 0 are
+/// returned as they are.
+pub(crate) fn unpremultiply_alpha([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    if a == 0 {
+        return [r, g, b, a];
+    }
+    let unpremultiply = |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+    [unpremultiply(r), unpremultiply(g), unpremultiply(b), a]
+}
+
+/// A 16-bit color with 5 bits of red, 6 bits of green, and 5 bits of blue.
+///
+/// Blue is stored in the least significant bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct B5G6R5 {
+    pub r5: u8,
+    pub g6: u8,
+    pub b5: u8,
+}
+
+impl B5G6R5 {
+    pub(crate) fn from_u16(value: u16) -> Self {
+        Self {
+            r5: (value >> 11) as u8,
+            g6: ((value >> 5) & 0x3F) as u8,
+            b5: (value & 0x1F) as u8,
+        }
+    }
+
+    /// Quantizes an 8-bit color to the nearest 5/6/5-bit color.
+    pub(crate) fn from_rgb8([r, g, b]: [u8; 3]) -> Self {
+        Self {
+            r5: ((r as u16 * 31 + 127) / 255) as u8,
+            g6: ((g as u16 * 63 + 127) / 255) as u8,
+            b5: ((b as u16 * 31 + 127) / 255) as u8,
+        }
+    }
+
+    pub(crate) fn to_u16(self) -> u16 {
+        ((self.r5 as u16) << 11) | ((self.g6 as u16) << 5) | self.b5 as u16
+    }
+
+    /// Expands the color to 8 bits per channel with full alpha.
+    pub(crate) fn to_rgba8(self) -> [u8; 4] {
+        [x5_to_x8(self.r5), x6_to_x8(self.g6), x5_to_x8(self.b5), 255]
+    }
+}
+
+/// A 16-bit color with 5 bits of red, green, and blue, and 1 bit of alpha.
+///
+/// Blue is stored in the least significant bits, alpha in the most significant bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct B5G5R5A1 {
+    pub r5: u8,
+    pub g5: u8,
+    pub b5: u8,
+    pub a1: bool,
+}
+
+impl B5G5R5A1 {
+    pub(crate) fn from_u16(value: u16) -> Self {
+        Self {
+            r5: ((value >> 10) & 0x1F) as u8,
+            g5: ((value >> 5) & 0x1F) as u8,
+            b5: (value & 0x1F) as u8,
+            a1: value & 0x8000 != 0,
+        }
+    }
+
+    /// Expands the color to 8 bits per channel. Alpha is either 0 or 255.
+    pub(crate) fn to_rgba8(self) -> [u8; 4] {
+        let a = if self.a1 { 255 } else { 0 };
+        [x5_to_x8(self.r5), x5_to_x8(self.g5), x5_to_x8(self.b5), a]
+    }
+}
+
+/// A 16-bit color with 4 bits of red, green, blue, and alpha.
+///
+/// Blue is stored in the least significant bits, alpha in the most significant bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct B4G4R4A4 {
+    pub r4: u8,
+    pub g4: u8,
+    pub b4: u8,
+    pub a4: u8,
+}
+
+impl B4G4R4A4 {
+    pub(crate) fn from_u16(value: u16) -> Self {
+        Self {
+            r4: ((value >> 8) & 0xF) as u8,
+            g4: ((value >> 4) & 0xF) as u8,
+            b4: (value & 0xF) as u8,
+            a4: (value >> 12) as u8,
+        }
+    }
+
+    /// Expands the color to 8 bits per channel.
+    pub(crate) fn to_rgba8(self) -> [u8; 4] {
+        [self.r4, self.g4, self.b4, self.a4].map(x4_to_x8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_unorm(x: u32, max_in: u32, max_out: u32) -> u32 {
+        (x as f64 * max_out as f64 / max_in as f64).round() as u32
+    }
+
+    #[test]
+    fn srgb_to_linear() {
+        // (encoded, linear), computed with the sRGB transfer function
+        let samples = [
+            (0, 0.0),
+            (10, 0.003_035),
+            (11, 0.003_347),
+            (64, 0.051_269),
+            (128, 0.215_861),
+            (188, 0.502_886),
+            (255, 1.0),
+        ];
+        for (encoded, linear) in samples {
+            let actual = srgb8_to_linear_f32(encoded);
+            assert!((actual - linear).abs() < 2e-6, "{encoded}: {actual}");
+        }
+
+        assert_eq!(srgb8_to_linear8(0), 0);
+        assert_eq!(srgb8_to_linear8(64), 13);
+        assert_eq!(srgb8_to_linear8(128), 55);
+        assert_eq!(srgb8_to_linear8(188), 128);
+        assert_eq!(srgb8_to_linear8(255), 255);
+
+        for x in 0..=255 {
+            assert_eq!(SRGB_TO_LINEAR_F32[x as usize], srgb8_to_linear_f32(x));
+            assert_eq!(SRGB_TO_LINEAR8[x as usize], srgb8_to_linear8(x));
+        }
+    }
+
+    #[test]
+    fn linear_to_srgb() {
+        for x in 0..=255 {
+            assert_eq!(linear_f32_to_srgb8(srgb8_to_linear_f32(x)), x);
+        }
+        assert_eq!(linear_f32_to_srgb8(0.5), 188);
+        assert_eq!(linear_f32_to_srgb8(-1.0), 0);
+        assert_eq!(linear_f32_to_srgb8(2.0), 255);
+        assert_eq!(linear_f32_to_srgb8(f32::NAN), 0);
+    }
+
+    #[test]
+    fn unpremultiply_alpha_rounds() {
+        for a in 1..=255 {
+            for c in 0..=255 {
+                let expected = round_unorm(c * 255, a, 1).min(255) as u8;
+                let pixel = unpremultiply_alpha([c as u8, 0, c as u8, a as u8]);
+                assert_eq!(pixel, [expected, 0, expected, a as u8], "{c} {a}");
+
+                // Premultiplying again gives the same colors back.
+                if c <= a {
+                    assert_eq!(premultiply_alpha(pixel)[0], c as u8, "{c} {a}");
+                }
+            }
+        }
+        assert_eq!(unpremultiply_alpha([10, 20, 30, 0]), [10, 20, 30, 0]);
+    }
+
+    #[test]
+    fn premultiply_alpha_rounds() {
+        for a in 0..=255 {
+            for c in 0..=255 {
+                let expected = round_unorm(c * a, 255 * 255, 255) as u8;
+                let [r, g, b, alpha] = premultiply_alpha([c as u8, c as u8, 0, a as u8]);
+                assert_eq!(
+                    [r, g, b, alpha],
+                    [expected, expected, 0, a as u8],
+                    "{c} {a}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn x4_to_x8_rounds() {
+        for x in 0..=15 {
+            assert_eq!(x4_to_x8(x) as u32, round_unorm(x as u32, 15, 255));
+            // The previous implementation
+            assert_eq!(x4_to_x8(x), x * 17);
+        }
+    }
+
+    #[test]
+    fn expand_bits_rounds() {
+        for from_bits in 1..=10 {
+            for to_bits in 1..=16 {
+                let (max_from, max_to) = ((1 << from_bits) - 1, (1 << to_bits) - 1);
+                for x in 0..=max_from {
+                    assert_eq!(
+                        expand_bits(x, from_bits, to_bits),
+                        round_unorm(x, max_from, max_to),
+                        "{x} {from_bits} {to_bits}"
+                    );
+                }
+            }
+        }
+        // Widening replicates the bits.
+        for x in 0..8 {
+            assert_eq!(expand_bits(x, 3, 6), x << 3 | x);
+        }
+        assert_eq!(expand_bits(0b1011, 4, 8), 0b1011_1011);
+    }
+
+    #[test]
+    fn x8_to_x4_rounds() {
+        for x in 0..=255 {
+            let x4 = x8_to_x4(x);
+            assert_eq!(x4 as u32, round_unorm(x as u32, 255, 15));
+            // no other 4-bit value is closer
+            let error = x4_to_x8(x4).abs_diff(x);
+            assert!((0..=15).all(|other| x4_to_x8(other).abs_diff(x) >= error));
+        }
+    }
+
+    #[test]
+    fn bc4_interp_6_factors() {
+        let ramp: Vec<_> = (1..=6).map(|f| bc4_interp_6(0, 255, f)).collect();
+        assert_eq!(ramp, [36, 73, 109, 146, 182, 219]);
+        let ramp: Vec<_> = (1..=6).map(|f| bc4_interp_6(200, 10, f)).collect();
+        assert_eq!(ramp, [173, 146, 119, 91, 64, 37]);
+        for f in 1..=6 {
+            assert_eq!(bc4_interp_6(77, 77, f), 77);
+        }
+    }
+
+    #[test]
+    fn bc4_interp_4_factors() {
+        let ramp: Vec<_> = (1..=4).map(|f| bc4_interp_4(0, 255, f)).collect();
+        assert_eq!(ramp, [51, 102, 153, 204]);
+        let ramp: Vec<_> = (1..=4).map(|f| bc4_interp_4(10, 200, f)).collect();
+        assert_eq!(ramp, [48, 86, 124, 162]);
+        for f in 1..=4 {
+            assert_eq!(bc4_interp_4(77, 77, f), 77);
+        }
+    }
+
+    #[test]
+    fn bc4_to_rgba_broadcasts() {
+        let pixels: [[u8; 1]; 16] = std::array::from_fn(|i| [i as u8 * 17]);
+        let rgba = bc4_to_rgba(pixels);
+        for (i, [r, g, b, a]) in rgba.into_iter().enumerate() {
+            assert_eq!([r, g, b], [i as u8 * 17; 3]);
+            assert_eq!(a, 255);
+        }
+        assert_eq!(
+            bc4_to_rgba([[1000_u16]; 16]),
+            [[1000, 1000, 1000, 65535]; 16]
+        );
+    }
+
+    #[test]
+    fn snorm8_to_unorm8_rounds() {
+        assert_eq!(snorm8_to_unorm8(0), 0);
+        assert_eq!(snorm8_to_unorm8(127), 128);
+        assert_eq!(snorm8_to_unorm8(254), 255);
+        for x in 0..=254 {
+            assert_eq!(snorm8_to_unorm8(x) as u32, round_unorm(x as u32, 254, 255));
+        }
+    }
+
+    #[test]
+    fn unorm8_to_snorm8_rounds() {
+        assert_eq!(unorm8_to_snorm8(0), 0);
+        assert_eq!(unorm8_to_snorm8(128), 127);
+        assert_eq!(unorm8_to_snorm8(255), 254);
+        for x in 0..=255 {
+            assert_eq!(unorm8_to_snorm8(x) as u32, round_unorm(x as u32, 255, 254));
+        }
+        for x in 0..=254 {
+            assert_eq!(unorm8_to_snorm8(snorm8_to_unorm8(x)), x);
+        }
+    }
+
+    #[test]
+    fn rgb10a2_values() {
+        assert_eq!(rgb10a2_unpack(0), [0; 4]);
+        assert_eq!(rgb10a2_unpack(u32::MAX), [65535; 4]);
+        // red 1023, green 512, blue 1, alpha 1
+        let value = 1023 | 512 << 10 | 1 << 20 | 1 << 30;
+        assert_eq!(rgb10a2_unpack(value), [65535, 32800, 64, 21845]);
+    }
+
+    #[test]
+    fn rgb9e5_values() {
+        assert_eq!(rgb9e5_to_f32(0), [0.0; 3]);
+        // A mantissa of 256 with an exponent of 16 is 1.0.
+        let value = 256 | 128 << 9 | 1 << 18 | 16 << 27;
+        assert_eq!(rgb9e5_to_f32(value), [1.0, 0.5, 1.0 / 256.0]);
+        // the largest and smallest non-zero values
+        assert_eq!(rgb9e5_to_f32(u32::MAX), [65408.0; 3]);
+        assert_eq!(rgb9e5_to_f32(1), [2.0_f32.powi(-24), 0.0, 0.0]);
+    }
+
+    #[test]
+    fn half_to_f32_values() {
+        assert_eq!(half_to_f32(0x0000).to_bits(), 0.0_f32.to_bits());
+        assert_eq!(half_to_f32(0x8000).to_bits(), (-0.0_f32).to_bits());
+        assert_eq!(half_to_f32(0x3C00), 1.0);
+        assert_eq!(half_to_f32(0x3E0F), 1.5146484);
+        assert_eq!(half_to_f32(0xC000), -2.0);
+        assert_eq!(half_to_f32(0x7BFF), 65504.0);
+        assert_eq!(half_to_f32(0x0400), 2.0_f32.powi(-14));
+
+        // subnormals
+        assert_eq!(half_to_f32(0x0001), 2.0_f32.powi(-24));
+        assert_eq!(half_to_f32(0x0615), 1557.0 * 2.0_f32.powi(-24));
+        assert_eq!(half_to_f32(0x03FF), 1023.0 * 2.0_f32.powi(-24));
+        assert_eq!(half_to_f32(0x8001), -(2.0_f32.powi(-24)));
+
+        assert_eq!(half_to_f32(0x7C00), f32::INFINITY);
+        assert_eq!(half_to_f32(0xFC00), f32::NEG_INFINITY);
+        assert!(half_to_f32(0x7E00).is_nan());
+        assert!(half_to_f32(0x7C01).is_nan());
+        assert_eq!(half_to_f32(0x7E00).to_bits(), 0x7FC0_0000);
+    }
+
+    #[test]
+    fn half_to_f32_round_trips() {
+        // f32 -> f16 is exact for every value that came from a half float
+        for bits in 0..=u16::MAX {
+            let value = half_to_f32(bits);
+            if value.is_nan() {
+                continue;
+            }
+            let f32_bits = value.to_bits();
+            let sign = ((f32_bits >> 16) & 0x8000) as u16;
+            let magnitude = value.abs();
+            let half = if magnitude.is_infinite() {
+                0x7C00
+            } else if magnitude < 2.0_f32.powi(-14) {
+                (magnitude * 16_777_216.0) as u16
+            } else {
+                let exponent = ((magnitude.to_bits() >> 23) + 15 - 127) as u16;
+                (exponent << 10) | ((magnitude.to_bits() >> 13) & 0x3FF) as u16
+            };
+            assert_eq!(sign | half, bits, "{bits:#06x}");
+        }
+    }
+
+    #[test]
+    fn b5g6r5_to_rgba8() {
+        for value in 0..=u16::MAX {
+            let color = B5G6R5::from_u16(value);
+            assert_eq!(color.to_u16(), value);
+
+            let [r, g, b, a] = color.to_rgba8();
+            assert_eq!(r as u32, round_unorm(color.r5 as u32, 31, 255));
+            assert_eq!(g as u32, round_unorm(color.g6 as u32, 63, 255));
+            assert_eq!(b as u32, round_unorm(color.b5 as u32, 31, 255));
+            assert_eq!(a, 255);
+        }
+    }
+
+    #[test]
+    fn x5_and_x6_to_x8_round() {
+        // The previous implementations used these constants.
+        for x in 0..=31 {
+            assert_eq!(x5_to_x8(x) as u32, round_unorm(x as u32, 31, 255));
+            assert_eq!(x5_to_x8(x), ((x as u16 * 2108 + 92) >> 8) as u8);
+        }
+        for x in 0..=63 {
+            assert_eq!(x6_to_x8(x) as u32, round_unorm(x as u32, 63, 255));
+            assert_eq!(x6_to_x8(x), ((x as u16 * 1036 + 132) >> 8) as u8);
+        }
+    }
+
+    #[test]
+    fn b5g5r5a1_to_rgba8() {
+        assert_eq!(B5G5R5A1::from_u16(0x7C00).to_rgba8(), [255, 0, 0, 0]);
+        assert_eq!(B5G5R5A1::from_u16(0x83E0).to_rgba8(), [0, 255, 0, 255]);
+        assert_eq!(B5G5R5A1::from_u16(0x801F).to_rgba8(), [0, 0, 255, 255]);
+        // 16 / 31 * 255 = 131.6 and 1 / 31 * 255 = 8.2
+        assert_eq!(
+            B5G5R5A1::from_u16((16 << 10) | (1 << 5) | 31).to_rgba8(),
+            [132, 8, 255, 0]
+        );
+    }
+
+    #[test]
+    fn b4g4r4a4_to_rgba8() {
+        assert_eq!(B4G4R4A4::from_u16(0x0F00).to_rgba8(), [255, 0, 0, 0]);
+        assert_eq!(B4G4R4A4::from_u16(0xF0F0).to_rgba8(), [0, 255, 0, 255]);
+        assert_eq!(B4G4R4A4::from_u16(0x123F).to_rgba8(), [34, 51, 255, 17]);
+    }
+
+    #[test]
+    fn b5g6r5_from_rgb8() {
+        for x in 0..=255 {
+            let color = B5G6R5::from_rgb8([x, x, x]);
+            assert_eq!(color.r5 as u32, round_unorm(x as u32, 255, 31));
+            assert_eq!(color.g6 as u32, round_unorm(x as u32, 255, 63));
+            assert_eq!(color.b5, color.r5);
+        }
+    }
+}
@@ -0,0 +1,4720 @@
+use super::convert::{
+    bc4_to_rgba, half_to_f32, premultiply_alpha, rgb10a2_unpack, rgb9e5_to_f32,
+    unpremultiply_alpha, B4G4R4A4, B5G5R5A1, B5G6R5, SRGB_TO_LINEAR8, SRGB_TO_LINEAR_F32,
+};
+use super::dxgi::DxgiFormat;
+use super::format::Format;
+use super::header::{
+    Dx10Header, Header, D3D10_RESOURCE_DIMENSION_TEXTURE3D, D3D10_RESOURCE_MISC_TEXTURECUBE,
+    DDPF_FOURCC, DDSCAPS2_CUBEMAP, DDSCAPS2_VOLUME, DDSD_PITCH,
+};
+use super::random_access::{rgba8_block_decoder, RandomAccessDecoder};
+use super::{bc, surface};
+#[cfg(feature = "simd")]
+use super::{bc1_batch, bc4_batch};
+use crate::error::{
+    LimitError, LimitErrorKind, ParameterError, ParameterErrorKind, UnsupportedError,
+    UnsupportedErrorKind,
+};
+use crate::io::ReadExt;
+use crate::{
+    ColorType, DynamicImage, GrayImage, ImageBuffer, ImageDecoder, ImageError, ImageFormat,
+    ImageResult, Limits, Luma,
+};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::ControlFlow;
+
+/// A face of a cubemap.
+///
+/// The faces are listed in the order they are stored in a DDS file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CubeFace {
+    /// The face facing +X.
+    PositiveX,
+    /// The face facing -X.
+    NegativeX,
+    /// The face facing +Y.
+    PositiveY,
+    /// The face facing -Y.
+    NegativeY,
+    /// The face facing +Z.
+    PositiveZ,
+    /// The face facing -Z.
+    NegativeZ,
+}
+
+impl CubeFace {
+    /// All faces in the order they are stored in a DDS file.
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// The `DDSCAPS2_CUBEMAP_*` flag that marks the face as present.
+    fn caps2_flag(self) -> u32 {
+        0x400 << self as u32
+    }
+}
+
+/// How the decoder handles surfaces with an sRGB format, e.g. `BC7_UNORM_SRGB`.
+///
+/// Alpha is never affected, and surfaces with other formats are always kept as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SrgbHandling {
+    /// Returns the sRGB-encoded colors as they are stored.
+    #[default]
+    KeepEncoded,
+    /// Converts the colors to linear 8-bit values. Dark colors lose precision.
+    ToLinear8,
+    /// Converts the colors to linear `f32` values. The color type becomes
+    /// [`ColorType::Rgba32F`].
+    ToLinearF32,
+}
+
+/// How the decoder handles the alpha channel of surfaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlphaHandling {
+    /// Returns the colors as they are stored.
+    #[default]
+    Keep,
+    /// Multiplies the colors by alpha. Surfaces without alpha, and surfaces whose
+    /// [alpha mode](DdsDecoder::alpha_mode) is already [`AlphaMode::Premultiplied`], aren't
+    /// affected.
+    ///
+    /// For [`SrgbHandling::ToLinearF32`], colors are premultiplied in linear space. Otherwise,
+    /// 8-bit colors are premultiplied as `round(color * alpha / 255)`.
+    Premultiply,
+    /// Divides the colors of surfaces with the alpha mode [`AlphaMode::Premultiplied`] by alpha
+    /// to get straight alpha. Other surfaces aren't affected.
+    ///
+    /// 8-bit colors are divided as `round(color * 255 / alpha)`, clamped to 255. The colors of
+    /// pixels with an alpha of 0 are kept as they are.
+    Unpremultiply,
+}
+
+/// How the alpha channel of a DDS file is meant to be interpreted.
+///
+/// DX10 headers store this as [`Dx10Header::alpha_mode`]. Files without a DX10 header don't state
+/// it, except for the FourCCs `DXT2` and `DXT4`, which are the premultiplied variants of `DXT3`
+/// and `DXT5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AlphaMode {
+    /// The alpha mode isn't stated. Alpha is usually straight then.
+    #[default]
+    Unknown,
+    /// The colors aren't multiplied by alpha.
+    Straight,
+    /// The colors are already multiplied by alpha.
+    Premultiplied,
+    /// Alpha is fully opaque and should be ignored.
+    Opaque,
+    /// Alpha doesn't store transparency, but other data like a mask.
+    Custom,
+}
+
+impl AlphaMode {
+    /// Returns the alpha mode with the given `DDS_ALPHA_MODE` value. Invalid values are unknown.
+    fn from_u32(value: u32) -> Self {
+        match value {
+            1 => AlphaMode::Straight,
+            2 => AlphaMode::Premultiplied,
+            3 => AlphaMode::Opaque,
+            4 => AlphaMode::Custom,
+            _ => AlphaMode::Unknown,
+        }
+    }
+}
+
+/// How the decoder handles the transparent index of BC1 blocks in the 3-color mode.
+///
+/// Blocks with `color0 <= color1` decode index 3 to black. Some assets only use it for black
+/// pixels, and some GPU drivers historically decoded it as opaque.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bc1PunchThrough {
+    /// Decodes index 3 to transparent black, `[0, 0, 0, 0]`.
+    #[default]
+    Transparent,
+    /// Decodes index 3 to opaque black, `[0, 0, 0, 255]`. All pixels are opaque then.
+    BlackOpaque,
+}
+
+/// The source of an output channel of a [`Swizzle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwizzleSource {
+    /// The red channel of the decoded pixel.
+    R,
+    /// The green channel of the decoded pixel.
+    G,
+    /// The blue channel of the decoded pixel.
+    B,
+    /// The alpha channel of the decoded pixel.
+    A,
+    /// The maximum value, e.g. 255 for 8-bit channels.
+    One,
+    /// The value 0.
+    Zero,
+}
+
+/// Reorders the channels of decoded RGBA pixels.
+///
+/// Each element selects the source of the red, green, blue, and alpha channel of the output in
+/// turn. The default is [`Swizzle::IDENTITY`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Swizzle(pub [SwizzleSource; 4]);
+
+impl Swizzle {
+    /// Keeps all channels as they are.
+    pub const IDENTITY: Swizzle = Swizzle([
+        SwizzleSource::R,
+        SwizzleSource::G,
+        SwizzleSource::B,
+        SwizzleSource::A,
+    ]);
+
+    /// Unpacks BC3n (DXT5nm) normal maps, which store X in alpha and Y in green.
+    ///
+    /// X becomes red and Y stays green. Like in `BC5_UNORM` surfaces, blue is 0 and alpha is
+    /// opaque.
+    pub const BC3N: Swizzle = Swizzle([
+        SwizzleSource::A,
+        SwizzleSource::G,
+        SwizzleSource::Zero,
+        SwizzleSource::One,
+    ]);
+
+    /// Applies the swizzle to a pixel with the given values for 0 and 1.
+    fn apply<T: Copy>(self, pixel: [T; 4], zero: T, one: T) -> [T; 4] {
+        self.0.map(|source| match source {
+            SwizzleSource::R => pixel[0],
+            SwizzleSource::G => pixel[1],
+            SwizzleSource::B => pixel[2],
+            SwizzleSource::A => pixel[3],
+            SwizzleSource::One => one,
+            SwizzleSource::Zero => zero,
+        })
+    }
+}
+
+impl Default for Swizzle {
+    fn default() -> Self {
+        Swizzle::IDENTITY
+    }
+}
+
+/// The progress of [`DdsDecoder::decode_with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The mip level that is being decoded. Level 0 is the main surface.
+    pub level: u32,
+    /// The number of mip levels that are decoded.
+    pub level_count: u32,
+    /// The number of rows of 4x4 blocks of the level that have been decoded.
+    pub block_row: u32,
+    /// The number of rows of 4x4 blocks of the level.
+    pub block_rows: u32,
+}
+
+/// The error a decode returns if it was cancelled. See [`DdsDecoder::decode_with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("decoding was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// DDS decoder
+///
+/// [`ImageDecoder::read_image`] decodes the main surface of the file. Use
+/// [`DdsDecoder::into_mipmaps`] to decode the full mip chain,
+/// [`DdsDecoder::read_cubemap_face`] to decode the faces of a cubemap, and
+/// [`DdsDecoder::read_array_slice`] to decode the elements of a texture array.
+///
+/// With the `rayon` feature, the rows of blocks of large block compressed surfaces are decoded in
+/// parallel. The decoded pixels are the same as without it.
+pub struct DdsDecoder<R> {
+    reader: R,
+    header: Header,
+    format: Format,
+    srgb_handling: SrgbHandling,
+    reconstruct_normal_z: bool,
+    alpha_handling: AlphaHandling,
+    high_precision: bool,
+    gray_to_rgba: bool,
+    bc1_punch_through: Bc1PunchThrough,
+    swizzle: Swizzle,
+    limits: Limits,
+}
+
+impl<R: Read> DdsDecoder<R> {
+    /// Create a new decoder that decodes from the stream `reader`
+    pub fn new(mut reader: R) -> ImageResult<Self> {
+        let header = Header::from_reader(&mut reader)?;
+        let format = match &header.dx10 {
+            Some(dx10) => Format::from_dxgi_format(dx10.dxgi_format)
+                .ok_or_else(|| format!("DXGI format {}", dx10.dxgi_format)),
+            None => Format::from_pixel_format(&header.pixel_format)
+                .ok_or_else(|| format!("pixel format {:?}", header.pixel_format)),
+        }
+        .map_err(|feature| {
+            ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+                ImageFormat::Dds.into(),
+                UnsupportedErrorKind::GenericFeature(feature),
+            ))
+        })?;
+
+        let decoder = Self {
+            reader,
+            header,
+            format,
+            srgb_handling: SrgbHandling::default(),
+            reconstruct_normal_z: false,
+            alpha_handling: AlphaHandling::default(),
+            high_precision: false,
+            gray_to_rgba: false,
+            bc1_punch_through: Bc1PunchThrough::default(),
+            swizzle: Swizzle::default(),
+            limits: Limits::no_limits(),
+        };
+        // All other size computations are bounded by the size of the surface data, so they can't
+        // overflow if this doesn't.
+        if decoder.checked_data_size().is_none() {
+            return Err(ImageError::Limits(LimitError::from_kind(
+                LimitErrorKind::DimensionError,
+            )));
+        }
+        Ok(decoder)
+    }
+
+    /// The DX10 header of the file, if present.
+    ///
+    /// Its DXGI format tells apart formats that decode the same, e.g. `BC7_UNORM` and
+    /// `BC7_UNORM_SRGB`.
+    pub fn dx10_header(&self) -> Option<&Dx10Header> {
+        self.header.dx10.as_ref()
+    }
+
+    /// The format of the surfaces.
+    ///
+    /// For files without a DX10 header, this is the DXGI format equivalent to their pixel format,
+    /// or [`DxgiFormat::Unknown`] if there is none, e.g. for 24-bit RGB.
+    pub fn format(&self) -> DxgiFormat {
+        match &self.header.dx10 {
+            // All supported formats are listed.
+            Some(dx10) => DxgiFormat::from_u32(dx10.dxgi_format).unwrap_or(DxgiFormat::Unknown),
+            None => self.format.legacy_dxgi_format(),
+        }
+    }
+
+    /// How the alpha channel of the surfaces is meant to be interpreted. See [`AlphaMode`].
+    pub fn alpha_mode(&self) -> AlphaMode {
+        match &self.header.dx10 {
+            Some(dx10) => AlphaMode::from_u32(dx10.alpha_mode),
+            None => {
+                let pixel_format = &self.header.pixel_format;
+                let premultiplied = pixel_format.flags & DDPF_FOURCC != 0
+                    && matches!(&pixel_format.four_cc, b"DXT2" | b"DXT4");
+                if premultiplied {
+                    AlphaMode::Premultiplied
+                } else {
+                    AlphaMode::Unknown
+                }
+            }
+        }
+    }
+
+    /// Whether the surfaces store sRGB-encoded colors.
+    ///
+    /// Only files with a DX10 header can declare an sRGB format.
+    pub fn is_srgb(&self) -> bool {
+        self.format().is_srgb()
+    }
+
+    /// Sets how sRGB surfaces are decoded. See [`SrgbHandling`].
+    ///
+    /// Whether the surfaces are sRGB is determined by the DXGI format of the DX10 header, see
+    /// [`DdsDecoder::is_srgb`]. By default, their values are left sRGB-encoded.
+    pub fn set_srgb_handling(&mut self, srgb_handling: SrgbHandling) {
+        self.srgb_handling = srgb_handling;
+    }
+
+    /// Sets how the alpha channel is handled. See [`AlphaHandling`].
+    pub fn set_alpha_handling(&mut self, alpha_handling: AlphaHandling) {
+        self.alpha_handling = alpha_handling;
+    }
+
+    /// Sets whether BC4 and BC5 surfaces are decoded with 16 bits per channel.
+    ///
+    /// Their interpolated values have more than 8 bits of precision, so decoding them to 8 bits
+    /// causes banding in smooth gradients like height maps. If enabled, the color types become
+    /// [`ColorType::L16`] and [`ColorType::Rgb16`]. Other formats aren't affected.
+    pub fn set_high_precision(&mut self, high_precision: bool) {
+        self.high_precision = high_precision;
+    }
+
+    /// Sets whether BC4 surfaces are decoded to RGBA instead of gray.
+    ///
+    /// If enabled, the single channel is copied to red, green, and blue, and alpha is opaque. The
+    /// color type becomes [`ColorType::Rgba8`], or [`ColorType::Rgba16`] with
+    /// [`DdsDecoder::set_high_precision`]. Disabled by default.
+    pub fn set_gray_to_rgba(&mut self, gray_to_rgba: bool) {
+        self.gray_to_rgba = gray_to_rgba;
+    }
+
+    /// Sets how BC1 blocks in the 3-color mode decode their transparent index. See
+    /// [`Bc1PunchThrough`].
+    ///
+    /// Other formats aren't affected. BC2 and BC3 blocks always use the 4-color mode.
+    pub fn set_bc1_punch_through(&mut self, bc1_punch_through: Bc1PunchThrough) {
+        self.bc1_punch_through = bc1_punch_through;
+    }
+
+    /// Sets how the channels of decoded pixels are reordered. See [`Swizzle`].
+    ///
+    /// The swizzle is applied before the sRGB and alpha handling, so e.g.
+    /// [`AlphaHandling::Premultiply`] uses the new alpha. It only affects surfaces whose pixels
+    /// decode to RGBA. The color type doesn't change.
+    pub fn set_swizzle(&mut self, swizzle: Swizzle) {
+        self.swizzle = swizzle;
+    }
+
+    /// Sets whether BC5 surfaces are decoded as normal maps.
+    ///
+    /// If enabled, blue is reconstructed as `sqrt(1 - x² - y²)`, where x and y are red and green
+    /// mapped to -1..=1. Otherwise, blue is 0 for `BC5_UNORM` and 128 for `BC5_SNORM`. Disabled
+    /// by default.
+    pub fn set_reconstruct_normal_z(&mut self, reconstruct_normal_z: bool) {
+        self.reconstruct_normal_z = reconstruct_normal_z;
+    }
+
+    /// The sRGB handling that applies to the surfaces of this file.
+    fn effective_srgb_handling(&self) -> SrgbHandling {
+        if self.is_srgb() {
+            self.srgb_handling
+        } else {
+            SrgbHandling::KeepEncoded
+        }
+    }
+
+    /// The number of mip levels in the file, including the main surface.
+    pub fn mipmap_count(&self) -> u32 {
+        self.header.mipmap_count.max(1)
+    }
+
+    /// The number of bytes [`ImageDecoder::read_image`] writes with the current settings.
+    ///
+    /// This is [`ImageDecoder::total_bytes`] as a `usize`, or `usize::MAX` if it doesn't fit. With
+    /// the default settings, it's the same as [`decoded_len`] with the number of channels of the
+    /// [color type](ImageDecoder::color_type).
+    pub fn output_size(&self) -> usize {
+        usize::try_from(self.total_bytes()).unwrap_or(usize::MAX)
+    }
+
+    /// The dimensions of the given mip level.
+    ///
+    /// Each level is half the size of the previous one, but at least 1x1.
+    pub fn mipmap_dimensions(&self, level: u32) -> (u32, u32) {
+        let (width, height) = self.dimensions();
+        let halve = |x: u32| x.checked_shr(level).unwrap_or(0).max(1);
+        (halve(width), halve(height))
+    }
+
+    /// The largest mip level whose width and height are both at most `max_dim`.
+    ///
+    /// If no level is small enough, this is the smallest level. Files without mip levels only
+    /// have the main surface, level 0.
+    pub fn closest_mip_level(&self, max_dim: u32) -> u32 {
+        (0..self.mipmap_count())
+            .find(|&level| {
+                let (width, height) = self.mipmap_dimensions(level);
+                width <= max_dim && height <= max_dim
+            })
+            .unwrap_or(self.mipmap_count() - 1)
+    }
+
+    /// Whether the file contains a cubemap.
+    ///
+    /// For cubemaps, the main surface is the first face present in the file.
+    pub fn is_cubemap(&self) -> bool {
+        match &self.header.dx10 {
+            Some(dx10) => dx10.misc_flag & D3D10_RESOURCE_MISC_TEXTURECUBE != 0,
+            None => self.header.caps2 & DDSCAPS2_CUBEMAP != 0,
+        }
+    }
+
+    /// Whether the face is stored in the file.
+    ///
+    /// Cubemaps without a DX10 header don't have to contain all faces. Always `false` if the file
+    /// isn't a cubemap.
+    pub fn has_cubemap_face(&self, face: CubeFace) -> bool {
+        match &self.header.dx10 {
+            Some(_) => self.is_cubemap(),
+            None => self.is_cubemap() && self.header.caps2 & face.caps2_flag() != 0,
+        }
+    }
+
+    /// The faces stored in the file, in the order they are stored.
+    pub fn cubemap_faces(&self) -> impl Iterator<Item = CubeFace> + '_ {
+        CubeFace::ALL
+            .into_iter()
+            .filter(|&face| self.has_cubemap_face(face))
+    }
+
+    /// Whether the file contains a volume texture.
+    pub fn is_volume(&self) -> bool {
+        match &self.header.dx10 {
+            Some(dx10) => dx10.resource_dimension == D3D10_RESOURCE_DIMENSION_TEXTURE3D,
+            None => self.header.caps2 & DDSCAPS2_VOLUME != 0,
+        }
+    }
+
+    /// The number of 2D slices of the main surface. This is 1 for files that aren't volumes.
+    pub fn depth(&self) -> u32 {
+        if self.is_volume() {
+            self.header.depth.max(1)
+        } else {
+            1
+        }
+    }
+
+    /// The number of 2D slices of the given mip level. Like the width and height, the depth is
+    /// halved for each level, but is at least 1.
+    pub fn mipmap_depth(&self, level: u32) -> u32 {
+        self.depth().checked_shr(level).unwrap_or(0).max(1)
+    }
+
+    /// The number of textures in the texture array. This is 1 for files that aren't arrays.
+    ///
+    /// Only files with a DX10 header can contain arrays. For cubemaps, each element of the
+    /// array is a cube.
+    pub fn array_len(&self) -> u32 {
+        self.header.dx10.map_or(1, |dx10| dx10.array_size)
+    }
+
+    /// The number of faces in each element of the texture array, 1 if the file isn't a cubemap.
+    fn faces_per_array_element(&self) -> u64 {
+        if self.is_cubemap() {
+            self.cubemap_faces().count() as u64
+        } else {
+            1
+        }
+    }
+
+    /// The offset of a surface from the start of the surface data.
+    ///
+    /// Each array element stores its faces one after another, and each face is stored with its
+    /// full mip chain.
+    fn surface_offset(&self, element: u32, face_index: u64, level: u32) -> u64 {
+        let chains = u64::from(element) * self.faces_per_array_element() + face_index;
+        chains * self.mipmap_chain_size() + self.mipmap_offset(level)
+    }
+
+    /// The number of bytes all surfaces take up in the file, or `None` if that overflows.
+    fn checked_data_size(&self) -> Option<u64> {
+        let mut chain_size: u64 = 0;
+        for level in 0..self.mipmap_count() {
+            let (width, height) = self.mipmap_dimensions(level);
+            let slice_size = match self.format.block_size() {
+                Some(block_size) => u64::from(width.div_ceil(4))
+                    .checked_mul(u64::from(height.div_ceil(4)))?
+                    .checked_mul(block_size as u64)?,
+                None => self.row_pitch(level).checked_mul(u64::from(height))?,
+            };
+            let level_size = slice_size.checked_mul(u64::from(self.mipmap_depth(level)))?;
+            chain_size = chain_size.checked_add(level_size)?;
+        }
+        chain_size
+            .checked_mul(self.faces_per_array_element())?
+            .checked_mul(u64::from(self.array_len()))
+    }
+
+    /// Checks that decoding `height` rows of a mip level fits into the memory limit, together with
+    /// `output_len` bytes for the caller.
+    ///
+    /// The limits are cloned, because all buffers are freed again after decoding.
+    fn check_alloc(&self, level: u32, height: u32, output_len: u64) -> ImageResult<()> {
+        let (width, _) = self.mipmap_dimensions(level);
+        let pixels = u64::from(width) * u64::from(height);
+        // The buffers `read_surface` and `read_encoded_surface` allocate
+        let mut intermediate_len = 0;
+        if self.effective_srgb_handling() == SrgbHandling::ToLinearF32 {
+            intermediate_len += pixels.saturating_mul(4);
+        }
+        if self.format.bytes_per_pixel() != Some(4) {
+            intermediate_len += self.format.surface_size(width, height);
+        }
+
+        let mut limits = self.limits.clone();
+        limits.reserve(output_len)?;
+        limits.reserve(intermediate_len)?;
+        Ok(())
+    }
+
+    /// The number of bytes a full mip chain takes up in the file.
+    fn mipmap_chain_size(&self) -> u64 {
+        self.mipmap_offset(self.mipmap_count())
+    }
+
+    /// The number of bytes the mip levels before `level` take up in the file.
+    fn mipmap_offset(&self, level: u32) -> u64 {
+        (0..level).map(|level| self.mipmap_size(level)).sum()
+    }
+
+    /// The number of bytes a mip level takes up in the file, including all its volume slices.
+    fn mipmap_size(&self, level: u32) -> u64 {
+        u64::from(self.mipmap_depth(level)) * self.mipmap_slice_size(level)
+    }
+
+    /// The number of bytes a 2D slice of a mip level takes up in the file.
+    ///
+    /// Block compressed volumes compress each slice separately, so depth isn't split into blocks.
+    fn mipmap_slice_size(&self, level: u32) -> u64 {
+        let (width, height) = self.mipmap_dimensions(level);
+        match self.format.block_size() {
+            Some(_) => self.format.surface_size(width, height),
+            None => self.row_pitch(level) * u64::from(height),
+        }
+    }
+
+    /// The number of bytes between the starts of two rows of an uncompressed mip level.
+    ///
+    /// Rows of the main surface may be padded, in which case the header stores the padded
+    /// pitch. The pitch is ignored if it's smaller than a row, because some writers get it
+    /// wrong. The header doesn't describe the pitch of other levels, so they are assumed to be
+    /// tightly packed.
+    fn row_pitch(&self, level: u32) -> u64 {
+        let (width, _) = self.mipmap_dimensions(level);
+        let row_len = u64::from(width) * self.format.bytes_per_pixel().unwrap() as u64;
+        if level == 0 && self.header.flags & DDSD_PITCH != 0 {
+            u64::from(self.header.pitch_or_linear_size).max(row_len)
+        } else {
+            row_len
+        }
+    }
+
+    /// Returns the layout of all mip levels in the file, starting with the main surface.
+    ///
+    /// This describes the first face of the first array element. Use it to copy the stored
+    /// surfaces, e.g. for uploading block compressed levels to a GPU without decoding them.
+    pub fn mip_layout(&self) -> impl Iterator<Item = MipLevel> + '_ {
+        (0..self.mipmap_count()).map(|level| {
+            let (width, height) = self.mipmap_dimensions(level);
+            MipLevel {
+                level,
+                width,
+                height,
+                depth: self.mipmap_depth(level),
+                offset: self.mipmap_offset(level),
+                len: self.mipmap_size(level),
+            }
+        })
+    }
+
+    /// Returns where a subresource is stored in the file.
+    ///
+    /// A subresource is a mip level of a face of an element of the texture array, and includes
+    /// all slices of volumes. Like for [`DdsDecoder::read_raw_surface`], `face` selects the face
+    /// of the cube at `index`, and `None` is the first face. Each array element stores its faces
+    /// one after another, and each face is stored with its full mip chain.
+    ///
+    /// Returns `None` if `index` or `level` is out of bounds or the file doesn't contain the face.
+    pub fn subresource_layout(
+        &self,
+        index: u32,
+        face: Option<CubeFace>,
+        level: u32,
+    ) -> Option<SubresourceInfo> {
+        if index >= self.array_len() || level >= self.mipmap_count() {
+            return None;
+        }
+        // Missing faces take up no space.
+        let face_index = match face {
+            Some(face) if self.has_cubemap_face(face) => {
+                self.cubemap_faces().take_while(|&f| f != face).count()
+            }
+            Some(_) => return None,
+            None => 0,
+        };
+
+        let (width, height) = self.mipmap_dimensions(level);
+        let row_pitch = match self.format.block_size() {
+            Some(block_size) => u64::from(width.div_ceil(4)) * block_size as u64,
+            None => self.row_pitch(level),
+        };
+        Some(SubresourceInfo {
+            offset: self.header.data_offset()
+                + self.surface_offset(index, face_index as u64, level),
+            size: self.mipmap_size(level),
+            row_pitch,
+            width,
+            height,
+            depth: self.mipmap_depth(level),
+        })
+    }
+
+    /// Returns the dimensions and layout of the file as described by its headers.
+    pub fn metadata(&self) -> DdsMetadata {
+        let (width, height) = self.dimensions();
+        DdsMetadata {
+            width,
+            height,
+            depth: self.depth(),
+            mip_count: self.mipmap_count(),
+            array_size: self.array_len(),
+            is_cubemap: self.is_cubemap(),
+            format: self.format(),
+            color_type: self.color_type(),
+            alpha_mode: self.alpha_mode(),
+            dx10_header: self.header.dx10,
+        }
+    }
+
+    /// Returns an iterator that decodes all mip levels in order, starting with the main surface.
+    pub fn into_mipmaps(self) -> Mipmaps<R> {
+        Mipmaps {
+            decoder: self,
+            level: 0,
+        }
+    }
+
+    /// Returns an iterator that decodes all mip levels to RGBA in order, starting with the main
+    /// surface.
+    ///
+    /// Each item holds the index of the level, its width and height, and its pixels with 8 bits
+    /// per channel, e.g. for uploading the levels to a GPU. Use [`DdsDecoder::into_mipmaps`] to
+    /// keep the color type of the file.
+    pub fn mip_levels(self) -> MipLevels<R> {
+        MipLevels {
+            mipmaps: self.into_mipmaps(),
+        }
+    }
+
+    /// Decodes all mip levels like [`DdsDecoder::into_mipmaps`], and calls `progress` after each
+    /// row of 4x4 blocks.
+    ///
+    /// Formats that aren't block compressed are decoded 4 rows of pixels at a time as well. If
+    /// `progress` returns [`ControlFlow::Break`], no further rows are decoded, and an
+    /// [`ImageError::IoError`] of the kind [`io::ErrorKind::Other`] wrapping [`Cancelled`] is
+    /// returned. Check for it with `error.get_ref()` and `downcast_ref::<Cancelled>()`. The kind
+    /// isn't [`io::ErrorKind::Interrupted`], because that is retried by loops like the one of
+    /// [`Read::read_exact`]. If there are no further rows, i.e. `progress` returns `Break` after
+    /// the last row of the last level, the decode succeeds.
+    pub fn decode_with_progress(
+        mut self,
+        mut progress: impl FnMut(Progress) -> ControlFlow<()>,
+    ) -> ImageResult<Vec<DynamicImage>> {
+        let level_count = self.mipmap_count();
+        let mut images = Vec::new();
+        let mut cancelled = false;
+        for level in 0..level_count {
+            if cancelled {
+                return Err(ImageError::IoError(io::Error::other(Cancelled)));
+            }
+            let (_, height) = self.mipmap_dimensions(level);
+            let block_rows = height.div_ceil(4);
+            let mut progress = |block_row| {
+                let flow = progress(Progress {
+                    level,
+                    level_count,
+                    block_row,
+                    block_rows,
+                });
+                // The level is complete after its last row, so it is only discarded if another
+                // level follows.
+                if block_row == block_rows && flow.is_break() {
+                    cancelled = true;
+                    return ControlFlow::Continue(());
+                }
+                flow
+            };
+            images.push(self.read_dynamic_image(level, Some(&mut progress))?);
+            self.skip_other_slices(level)?;
+        }
+        Ok(images)
+    }
+
+    /// Returns a decoder that decodes the main surface 4 rows at a time.
+    ///
+    /// Only one row of blocks is read and decoded at a time, so the memory it needs is
+    /// proportional to the width of the surface instead of its size.
+    pub fn into_block_decoder(self) -> BlockDecoder<R> {
+        BlockDecoder {
+            decoder: self,
+            y: 0,
+        }
+    }
+
+    /// Returns a decoder for single pixels of the main surface, which has to be block compressed.
+    ///
+    /// The compressed surface is read into memory, and its blocks are only decoded when their
+    /// pixels are looked up. Blocks are decoded like with the default settings. Returns an error
+    /// if the format is BC6H or isn't block compressed.
+    pub fn into_random_access(mut self) -> ImageResult<RandomAccessDecoder> {
+        let Some(decode_block) = rgba8_block_decoder(self.format) else {
+            return Err(ImageError::Unsupported(
+                UnsupportedError::from_format_and_kind(
+                    ImageFormat::Dds.into(),
+                    UnsupportedErrorKind::GenericFeature(format!(
+                        "random access to {:?} surfaces",
+                        self.format()
+                    )),
+                ),
+            ));
+        };
+
+        let (width, height) = self.dimensions();
+        let len = self.format.surface_size(width, height);
+        self.limits.reserve(len)?;
+        let len = usize::try_from(len).map_err(|_| {
+            ImageError::Limits(LimitError::from_kind(LimitErrorKind::InsufficientMemory))
+        })?;
+        let mut data = Vec::new();
+        self.reader.read_exact_vec(&mut data, len)?;
+        Ok(RandomAccessDecoder::new(
+            self.format,
+            decode_block,
+            width,
+            height,
+            data,
+        ))
+    }
+
+    /// Decodes the main surface of a BC5 file into one gray image for red and one for green.
+    ///
+    /// BC5 stores two independent channels, e.g. the x and y of a normal map, so this avoids the
+    /// blue channel of [`ImageDecoder::read_image`]. The images have 16 bits per channel if
+    /// [`DdsDecoder::set_high_precision`] is enabled. Returns an error if the format isn't BC5.
+    pub fn read_bc5_channels(self) -> ImageResult<(DynamicImage, DynamicImage)> {
+        /// Returns the first and second channel of each RGB pixel.
+        fn split<T: Copy>(rgb: &[T]) -> [Vec<T>; 2] {
+            [0, 1].map(|channel| rgb.iter().skip(channel).step_by(3).copied().collect())
+        }
+
+        if !matches!(self.format, Format::Bc5Unsigned | Format::Bc5Signed) {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::Generic("the surfaces aren't BC5".into()),
+            )));
+        }
+        let (width, height) = self.dimensions();
+        let [red, green] = match DynamicImage::from_decoder(self)? {
+            DynamicImage::ImageRgb8(rgb) => split(rgb.as_raw()).map(|channel| {
+                DynamicImage::from(GrayImage::from_raw(width, height, channel).unwrap())
+            }),
+            DynamicImage::ImageRgb16(rgb) => split(rgb.as_raw()).map(|channel| {
+                let gray = ImageBuffer::<Luma<u16>, _>::from_raw(width, height, channel);
+                DynamicImage::from(gray.unwrap())
+            }),
+            _ => unreachable!("BC5 decodes to RGB"),
+        };
+        Ok((red, green))
+    }
+
+    /// Skips the next `len` bytes of the reader.
+    fn skip(&mut self, len: u64) -> ImageResult<()> {
+        let skipped = io::copy(&mut (&mut self.reader).take(len), &mut io::sink())?;
+        if skipped < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(())
+    }
+
+    /// Skips all but the first slice of a level of a volume, which has just been decoded.
+    fn skip_other_slices(&mut self, level: u32) -> ImageResult<()> {
+        let slices = self.mipmap_depth(level) - 1;
+        self.skip(u64::from(slices) * self.mipmap_slice_size(level))
+    }
+
+    /// Reads the next surface of the given mip level from the reader and decodes it into an
+    /// image.
+    ///
+    /// If `progress` is set, the surface is decoded one row of blocks at a time, and `progress` is
+    /// called with the number of decoded rows of blocks after each.
+    fn read_dynamic_image(
+        &mut self,
+        level: u32,
+        progress: Option<&mut dyn FnMut(u32) -> ControlFlow<()>>,
+    ) -> ImageResult<DynamicImage> {
+        let (width, height) = self.mipmap_dimensions(level);
+        let output_len = u64::from(width)
+            .saturating_mul(u64::from(height))
+            .saturating_mul(u64::from(self.color_type().bytes_per_pixel()));
+        self.check_alloc(level, height, output_len)?;
+        DynamicImage::from_decoder(SurfaceDecoder {
+            decoder: self,
+            level,
+            progress,
+        })
+    }
+
+    /// Reads the next `height` rows of a mip level from the reader, decodes them into `buf`, and
+    /// applies the sRGB and alpha handling.
+    ///
+    /// For block compressed formats, `height` has to be a multiple of 4 unless the rows reach the
+    /// bottom of the level.
+    fn read_surface(&mut self, level: u32, height: u32, buf: &mut [u8]) -> ImageResult<()> {
+        self.check_alloc(level, height, 0)?;
+
+        // All sRGB formats are decoded to RGBA8.
+        match self.effective_srgb_handling() {
+            SrgbHandling::KeepEncoded => self.read_swizzled_surface(level, height, buf)?,
+            SrgbHandling::ToLinear8 => {
+                self.read_swizzled_surface(level, height, buf)?;
+                let lut = &*SRGB_TO_LINEAR8;
+                for pixel in buf.chunks_exact_mut(4) {
+                    for channel in &mut pixel[..3] {
+                        *channel = lut[*channel as usize];
+                    }
+                }
+            }
+            SrgbHandling::ToLinearF32 => {
+                let mut encoded = vec![0; buf.len() / size_of::<f32>()];
+                self.read_swizzled_surface(level, height, &mut encoded)?;
+                let lut = &*SRGB_TO_LINEAR_F32;
+                let channels = encoded.iter().zip(buf.chunks_exact_mut(size_of::<f32>()));
+                for (i, (&value, out)) in channels.enumerate() {
+                    let value = if i % 4 == 3 {
+                        value as f32 / 255.0
+                    } else {
+                        lut[value as usize]
+                    };
+                    out.copy_from_slice(&value.to_ne_bytes());
+                }
+            }
+        }
+
+        // Colors are premultiplied after linearization, like a GPU would blend them.
+        let premultiplied = self.alpha_mode() == AlphaMode::Premultiplied;
+        match self.alpha_handling {
+            AlphaHandling::Premultiply if !premultiplied => {
+                map_alpha_pixels(self.color_type(), buf, premultiply_alpha, |[r, g, b, a]| {
+                    [r * a, g * a, b * a, a]
+                })
+            }
+            AlphaHandling::Unpremultiply if premultiplied => {
+                map_alpha_pixels(self.color_type(), buf, unpremultiply_alpha, |pixel| {
+                    let [r, g, b, a] = pixel;
+                    if a > 0.0 {
+                        [r / a, g / a, b / a, a]
+                    } else {
+                        pixel
+                    }
+                })
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Like [`DdsDecoder::read_encoded_surface`], but also applies the swizzle.
+    fn read_swizzled_surface(
+        &mut self,
+        level: u32,
+        height: u32,
+        buf: &mut [u8],
+    ) -> ImageResult<()> {
+        self.read_encoded_surface(level, height, buf)?;
+        if self.swizzle == Swizzle::IDENTITY {
+            return Ok(());
+        }
+
+        let color_type = match self.effective_srgb_handling() {
+            SrgbHandling::ToLinearF32 => ColorType::Rgba8,
+            _ => self.color_type(),
+        };
+        match color_type {
+            ColorType::Rgba8 => {
+                for pixel in buf.as_chunks_mut::<4>().0 {
+                    *pixel = self.swizzle.apply(*pixel, 0, u8::MAX);
+                }
+            }
+            ColorType::Rgba16 => {
+                for pixel in buf.as_chunks_mut::<{ 4 * size_of::<u16>() }>().0 {
+                    let channels: [[u8; 2]; 4] = bytemuck::cast(*pixel);
+                    let channels = self.swizzle.apply(channels, [0; 2], u16::MAX.to_ne_bytes());
+                    *pixel = bytemuck::cast(channels);
+                }
+            }
+            ColorType::Rgba32F => {
+                for pixel in buf.as_chunks_mut::<{ 4 * size_of::<f32>() }>().0 {
+                    let channels: [[u8; 4]; 4] = bytemuck::cast(*pixel);
+                    let one = 1.0_f32.to_ne_bytes();
+                    *pixel = bytemuck::cast(self.swizzle.apply(channels, [0; 4], one));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Like [`DdsDecoder::read_surface`], but decodes the rows into `buf` as they are stored.
+    fn read_encoded_surface(&mut self, level: u32, height: u32, buf: &mut [u8]) -> ImageResult<()> {
+        let (width, _) = self.mipmap_dimensions(level);
+        if self.format.block_size().is_some() {
+            let len = usize::try_from(self.format.surface_size(width, height)).map_err(|_| {
+                ImageError::Limits(LimitError::from_kind(LimitErrorKind::InsufficientMemory))
+            })?;
+            let mut data = Vec::new();
+            self.reader.read_exact_vec(&mut data, len)?;
+            let data = &data;
+
+            use surface::decode_blocks;
+            match self.format {
+                Format::Bc1 if self.bc1_punch_through == Bc1PunchThrough::BlackOpaque => {
+                    decode_blocks(data, width, height, buf, bc::decode_bc1_block_opaque)
+                }
+                #[cfg(feature = "simd")]
+                Format::Bc1 => surface::decode_blocks_batched(
+                    data,
+                    width,
+                    height,
+                    buf,
+                    bc1_batch::decode_bc1_blocks,
+                    bc::decode_bc1_block,
+                ),
+                #[cfg(not(feature = "simd"))]
+                Format::Bc1 => decode_blocks(data, width, height, buf, bc::decode_bc1_block),
+                Format::Bc2 => decode_blocks(data, width, height, buf, bc::decode_bc2_block),
+                Format::Bc3 => decode_blocks(data, width, height, buf, bc::decode_bc3_block),
+                Format::Bc4Unsigned if self.gray_to_rgba && self.high_precision => {
+                    decode_blocks(data, width, height, buf, |block| {
+                        bc4_to_rgba(bc::decode_bc4_unsigned_block_u16(block))
+                    })
+                }
+                Format::Bc4Unsigned if self.gray_to_rgba => {
+                    decode_blocks(data, width, height, buf, |block| {
+                        bc4_to_rgba(bc::decode_bc4_unsigned_block(block))
+                    })
+                }
+                Format::Bc4Signed if self.gray_to_rgba && self.high_precision => {
+                    decode_blocks(data, width, height, buf, |block| {
+                        bc4_to_rgba(bc::decode_bc4_signed_block_u16(block))
+                    })
+                }
+                Format::Bc4Signed if self.gray_to_rgba => {
+                    decode_blocks(data, width, height, buf, |block| {
+                        bc4_to_rgba(bc::decode_bc4_signed_block(block))
+                    })
+                }
+                Format::Bc4Unsigned if self.high_precision => {
+                    decode_blocks(data, width, height, buf, bc::decode_bc4_unsigned_block_u16)
+                }
+                #[cfg(feature = "simd")]
+                Format::Bc4Unsigned => surface::decode_blocks_batched(
+                    data,
+                    width,
+                    height,
+                    buf,
+                    bc4_batch::decode_bc4_unsigned_blocks,
+                    bc::decode_bc4_unsigned_block,
+                ),
+                #[cfg(not(feature = "simd"))]
+                Format::Bc4Unsigned => {
+                    decode_blocks(data, width, height, buf, bc::decode_bc4_unsigned_block)
+                }
+                Format::Bc4Signed if self.high_precision => {
+                    decode_blocks(data, width, height, buf, bc::decode_bc4_signed_block_u16)
+                }
+                Format::Bc4Signed => {
+                    decode_blocks(data, width, height, buf, bc::decode_bc4_signed_block)
+                }
+                Format::Bc5Unsigned if self.high_precision => {
+                    let decode_block = if self.reconstruct_normal_z {
+                        bc::decode_bc5_unsigned_block_normal_u16
+                    } else {
+                        bc::decode_bc5_unsigned_block_u16
+                    };
+                    decode_blocks(data, width, height, buf, decode_block)
+                }
+                Format::Bc5Unsigned => {
+                    let decode_block = if self.reconstruct_normal_z {
+                        bc::decode_bc5_unsigned_block_normal
+                    } else {
+                        bc::decode_bc5_unsigned_block
+                    };
+                    decode_blocks(data, width, height, buf, decode_block)
+                }
+                Format::Bc5Signed if self.high_precision => {
+                    let decode_block = if self.reconstruct_normal_z {
+                        bc::decode_bc5_signed_block_normal_u16
+                    } else {
+                        bc::decode_bc5_signed_block_u16
+                    };
+                    decode_blocks(data, width, height, buf, decode_block)
+                }
+                Format::Bc5Signed => {
+                    let decode_block = if self.reconstruct_normal_z {
+                        bc::decode_bc5_signed_block_normal
+                    } else {
+                        bc::decode_bc5_signed_block
+                    };
+                    decode_blocks(data, width, height, buf, decode_block)
+                }
+                Format::Bc6hUnsigned => {
+                    decode_blocks(data, width, height, buf, bc::decode_bc6h_unsigned_block_f32)
+                }
+                Format::Bc6hSigned => {
+                    decode_blocks(data, width, height, buf, bc::decode_bc6h_signed_block_f32)
+                }
+                Format::Bc7 => decode_blocks(data, width, height, buf, bc::decode_bc7_block),
+                _ => unreachable!(),
+            }
+            return Ok(());
+        }
+
+        // Formats whose pixels are decoded to the same number of bytes are converted in place. This
+        // excludes gray pixels that the settings expand to RGBA or 16 bits.
+        let decoded_bytes_per_pixel = usize::from(self.format.color_type().bytes_per_pixel());
+        let expanded = self.format == Format::R8 && (self.gray_to_rgba || self.high_precision);
+        if self.format.bytes_per_pixel() == Some(decoded_bytes_per_pixel) && !expanded {
+            self.read_rows(level, buf)?;
+            let pixels = buf.chunks_exact_mut(4);
+            match self.format {
+                // 16-bit channels are stored in little endian.
+                Format::R16 | Format::R16G16B16A16 => {
+                    for channel in buf.as_chunks_mut::<2>().0 {
+                        *channel = u16::from_le_bytes(*channel).to_ne_bytes();
+                    }
+                }
+                Format::R32G32B32A32Float => {
+                    for channel in buf.as_chunks_mut::<4>().0 {
+                        *channel = u32::from_le_bytes(*channel).to_ne_bytes();
+                    }
+                }
+                Format::R8 | Format::L8A8 | Format::R8G8B8A8 => {}
+                Format::R8G8B8X8 => pixels.for_each(|pixel| pixel[3] = 255),
+                Format::B8G8R8A8 => pixels.for_each(|pixel| pixel.swap(0, 2)),
+                Format::B8G8R8X8 => pixels.for_each(|pixel| {
+                    pixel.swap(0, 2);
+                    pixel[3] = 255;
+                }),
+                _ => unreachable!(),
+            }
+            return Ok(());
+        }
+
+        let len = usize::try_from(self.format.surface_size(width, height)).map_err(|_| {
+            ImageError::Limits(LimitError::from_kind(LimitErrorKind::InsufficientMemory))
+        })?;
+        let mut data = vec![0; len];
+        self.read_rows(level, &mut data)?;
+        let pixels = buf.chunks_exact_mut(4);
+        match self.format {
+            Format::R8G8B8 => {
+                for (pixel, [r, g, b]) in pixels.zip(data.as_chunks::<3>().0) {
+                    pixel.copy_from_slice(&[*r, *g, *b, 255]);
+                }
+            }
+            Format::B8G8R8 => {
+                for (pixel, [b, g, r]) in pixels.zip(data.as_chunks::<3>().0) {
+                    pixel.copy_from_slice(&[*r, *g, *b, 255]);
+                }
+            }
+            // 8-bit channels are expanded to 16 bits by multiplying with 257, so 255 is 65535.
+            Format::R8 if self.gray_to_rgba && self.high_precision => {
+                let pixels = buf.as_chunks_mut::<{ 4 * size_of::<u16>() }>().0;
+                for (pixel, &value) in pixels.iter_mut().zip(&data) {
+                    let value = u16::from(value) * 257;
+                    *pixel = bytemuck::cast([value, value, value, u16::MAX].map(u16::to_ne_bytes));
+                }
+            }
+            Format::R8 if self.gray_to_rgba => {
+                for (pixel, &value) in pixels.zip(&data) {
+                    pixel.copy_from_slice(&[value, value, value, 255]);
+                }
+            }
+            Format::R8 => {
+                let pixels = buf.as_chunks_mut::<2>().0;
+                for (pixel, &value) in pixels.iter_mut().zip(&data) {
+                    *pixel = (u16::from(value) * 257).to_ne_bytes();
+                }
+            }
+            Format::R8G8 if self.high_precision => {
+                let pixels = buf.as_chunks_mut::<{ 3 * size_of::<u16>() }>().0;
+                for (pixel, &[r, g]) in pixels.iter_mut().zip(data.as_chunks::<2>().0) {
+                    let [r, g] = [r, g].map(|c| (u16::from(c) * 257).to_ne_bytes());
+                    *pixel = bytemuck::cast([r, g, [0; 2]]);
+                }
+            }
+            Format::R8G8 => {
+                let pixels = buf.as_chunks_mut::<3>().0;
+                for (pixel, &[r, g]) in pixels.iter_mut().zip(data.as_chunks::<2>().0) {
+                    *pixel = [r, g, 0];
+                }
+            }
+            Format::B5G6R5 => {
+                for (pixel, &value) in pixels.zip(data.as_chunks::<2>().0) {
+                    let color = B5G6R5::from_u16(u16::from_le_bytes(value));
+                    pixel.copy_from_slice(&color.to_rgba8());
+                }
+            }
+            Format::B5G5R5A1 => {
+                for (pixel, &value) in pixels.zip(data.as_chunks::<2>().0) {
+                    let color = B5G5R5A1::from_u16(u16::from_le_bytes(value));
+                    pixel.copy_from_slice(&color.to_rgba8());
+                }
+            }
+            Format::B5G5R5X1 => {
+                for (pixel, &value) in pixels.zip(data.as_chunks::<2>().0) {
+                    let color = B5G5R5A1::from_u16(u16::from_le_bytes(value) | 0x8000);
+                    pixel.copy_from_slice(&color.to_rgba8());
+                }
+            }
+            Format::B4G4R4A4 => {
+                for (pixel, &value) in pixels.zip(data.as_chunks::<2>().0) {
+                    let color = B4G4R4A4::from_u16(u16::from_le_bytes(value));
+                    pixel.copy_from_slice(&color.to_rgba8());
+                }
+            }
+            Format::R16G16 => {
+                let pixels = buf.as_chunks_mut::<6>().0;
+                for (pixel, &value) in pixels.iter_mut().zip(data.as_chunks::<4>().0) {
+                    let [r, g]: [[u8; 2]; 2] = bytemuck::cast(value);
+                    let [r, g] = [r, g].map(|c| u16::from_le_bytes(c).to_ne_bytes());
+                    *pixel = bytemuck::cast([r, g, [0; 2]]);
+                }
+            }
+            // Half floats are stored in little endian.
+            Format::R16Float => {
+                let pixels = buf.as_chunks_mut::<{ 3 * size_of::<f32>() }>().0;
+                for (pixel, &value) in pixels.iter_mut().zip(data.as_chunks::<2>().0) {
+                    let value = half_to_f32(u16::from_le_bytes(value)).to_ne_bytes();
+                    *pixel = bytemuck::cast([value; 3]);
+                }
+            }
+            Format::R16G16B16A16Float => {
+                let pixels = buf.as_chunks_mut::<{ 4 * size_of::<f32>() }>().0;
+                for (pixel, &value) in pixels.iter_mut().zip(data.as_chunks::<8>().0) {
+                    let channels: [[u8; 2]; 4] = bytemuck::cast(value);
+                    let channels =
+                        channels.map(|c| half_to_f32(u16::from_le_bytes(c)).to_ne_bytes());
+                    *pixel = bytemuck::cast(channels);
+                }
+            }
+            // The bits of floats are converted like integers, so NaN payloads are kept.
+            Format::R32Float => {
+                let pixels = buf.as_chunks_mut::<{ 3 * size_of::<f32>() }>().0;
+                for (pixel, &value) in pixels.iter_mut().zip(data.as_chunks::<4>().0) {
+                    *pixel = bytemuck::cast([u32::from_le_bytes(value).to_ne_bytes(); 3]);
+                }
+            }
+            Format::R32G32Float => {
+                let pixels = buf.as_chunks_mut::<{ 3 * size_of::<f32>() }>().0;
+                for (pixel, &value) in pixels.iter_mut().zip(data.as_chunks::<8>().0) {
+                    let [r, g]: [[u8; 4]; 2] = bytemuck::cast(value);
+                    let [r, g] = [r, g].map(|c| u32::from_le_bytes(c).to_ne_bytes());
+                    *pixel = bytemuck::cast([r, g, [0; 4]]);
+                }
+            }
+            Format::R32G32B32Float => {
+                let pixels = buf.as_chunks_mut::<{ 4 * size_of::<f32>() }>().0;
+                for (pixel, &value) in pixels.iter_mut().zip(data.as_chunks::<12>().0) {
+                    let [r, g, b]: [[u8; 4]; 3] = bytemuck::cast(value);
+                    let [r, g, b] = [r, g, b].map(|c| u32::from_le_bytes(c).to_ne_bytes());
+                    *pixel = bytemuck::cast([r, g, b, 1.0_f32.to_ne_bytes()]);
+                }
+            }
+            Format::R10G10B10A2 => {
+                let pixels = buf.as_chunks_mut::<{ 4 * size_of::<u16>() }>().0;
+                for (pixel, &value) in pixels.iter_mut().zip(data.as_chunks::<4>().0) {
+                    let channels = rgb10a2_unpack(u32::from_le_bytes(value));
+                    *pixel = bytemuck::cast(channels.map(u16::to_ne_bytes));
+                }
+            }
+            Format::R9G9B9E5 => {
+                let pixels = buf.as_chunks_mut::<{ 3 * size_of::<f32>() }>().0;
+                for (pixel, &value) in pixels.iter_mut().zip(data.as_chunks::<4>().0) {
+                    let channels = rgb9e5_to_f32(u32::from_le_bytes(value));
+                    *pixel = bytemuck::cast(channels.map(f32::to_ne_bytes));
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Reads the rows of an uncompressed mip level into `buf` without their padding.
+    fn read_rows(&mut self, level: u32, buf: &mut [u8]) -> ImageResult<()> {
+        let (width, _) = self.mipmap_dimensions(level);
+        let row_len = width as usize * self.format.bytes_per_pixel().unwrap();
+        let padding = self.row_pitch(level) - row_len as u64;
+        if padding == 0 {
+            self.reader.read_exact(buf)?;
+        } else {
+            for row in buf.chunks_exact_mut(row_len) {
+                self.reader.read_exact(row)?;
+                self.skip(padding)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> DdsDecoder<R> {
+    /// Decodes the main surface of a cubemap face.
+    ///
+    /// Returns `None` if the file isn't a cubemap or doesn't contain the face. The reader is
+    /// restored to its previous position afterwards, so the faces can be read in any order.
+    pub fn read_cubemap_face(&mut self, face: CubeFace) -> Option<ImageResult<DynamicImage>> {
+        self.read_cubemap_face_mipmap(face, 0)
+    }
+
+    /// Decodes a mip level of a cubemap face.
+    ///
+    /// Returns `None` if the file isn't a cubemap, doesn't contain the face, or doesn't contain
+    /// the mip level. Like [`DdsDecoder::read_cubemap_face`], this restores the position of the
+    /// reader.
+    pub fn read_cubemap_face_mipmap(
+        &mut self,
+        face: CubeFace,
+        level: u32,
+    ) -> Option<ImageResult<DynamicImage>> {
+        self.read_array_cubemap_face(0, face, level)
+    }
+
+    /// Decodes a 2D slice of the main surface of a volume texture.
+    ///
+    /// Returns `None` if `z` is out of bounds. For files that aren't volumes, the only slice is
+    /// the main surface. Like [`DdsDecoder::read_cubemap_face`], this restores the position of
+    /// the reader.
+    pub fn read_volume_slice(&mut self, z: u32) -> Option<ImageResult<DynamicImage>> {
+        self.read_volume_slice_mipmap(z, 0)
+    }
+
+    /// Decodes a 2D slice of a mip level of a volume texture.
+    ///
+    /// Returns `None` if `level` is out of bounds, or if `z` is out of bounds for the
+    /// [depth of the level](DdsDecoder::mipmap_depth). Like [`DdsDecoder::read_cubemap_face`],
+    /// this restores the position of the reader.
+    pub fn read_volume_slice_mipmap(
+        &mut self,
+        z: u32,
+        level: u32,
+    ) -> Option<ImageResult<DynamicImage>> {
+        if level >= self.mipmap_count() || z >= self.mipmap_depth(level) {
+            return None;
+        }
+
+        let offset = self.mipmap_offset(level) + u64::from(z) * self.mipmap_slice_size(level);
+        Some(self.read_dynamic_image_at(offset, level))
+    }
+
+    /// Decodes only the [closest mip level](DdsDecoder::closest_mip_level) to `max_dim`.
+    ///
+    /// This is cheaper than decoding the main surface and downscaling it, e.g. for thumbnails.
+    /// Like [`DdsDecoder::read_cubemap_face`], this restores the position of the reader.
+    pub fn decode_closest_mip(&mut self, max_dim: u32) -> ImageResult<DynamicImage> {
+        let level = self.closest_mip_level(max_dim);
+        self.read_dynamic_image_at(self.mipmap_offset(level), level)
+    }
+
+    /// Decodes the main surface of an element of the texture array.
+    ///
+    /// Returns `None` if `index` is out of bounds. For cubemap arrays, this is the first face of
+    /// the cube. Like [`DdsDecoder::read_cubemap_face`], this restores the position of the reader.
+    pub fn read_array_slice(&mut self, index: u32) -> Option<ImageResult<DynamicImage>> {
+        self.read_array_slice_mipmap(index, 0)
+    }
+
+    /// Decodes a mip level of an element of the texture array.
+    ///
+    /// Returns `None` if `index` or `level` is out of bounds. For cubemap arrays, this is the
+    /// first face of the cube. Like [`DdsDecoder::read_cubemap_face`], this restores the position
+    /// of the reader.
+    pub fn read_array_slice_mipmap(
+        &mut self,
+        index: u32,
+        level: u32,
+    ) -> Option<ImageResult<DynamicImage>> {
+        if index >= self.array_len() || level >= self.mipmap_count() {
+            return None;
+        }
+
+        let offset = self.surface_offset(index, 0, level);
+        Some(self.read_dynamic_image_at(offset, level))
+    }
+
+    /// Decodes a mip level of a face of a cube in a cubemap array.
+    ///
+    /// Returns `None` if the file isn't a cubemap, doesn't contain the face, or if `index` or
+    /// `level` is out of bounds. Like [`DdsDecoder::read_cubemap_face`], this restores the
+    /// position of the reader.
+    pub fn read_array_cubemap_face(
+        &mut self,
+        index: u32,
+        face: CubeFace,
+        level: u32,
+    ) -> Option<ImageResult<DynamicImage>> {
+        if !self.has_cubemap_face(face) || index >= self.array_len() || level >= self.mipmap_count()
+        {
+            return None;
+        }
+
+        // Missing faces take up no space.
+        let face_index = self.cubemap_faces().take_while(|&f| f != face).count();
+        let offset = self.surface_offset(index, face_index as u64, level);
+        Some(self.read_dynamic_image_at(offset, level))
+    }
+
+    /// Reads the stored bytes of a mip level without decoding them.
+    ///
+    /// This is useful for uploading block compressed surfaces to the GPU as they are, or for
+    /// copying them to another container, e.g. KTX2, without the loss of decoding and encoding
+    /// them again. The bytes are the same ones the decoding functions read. For cubemaps, `face`
+    /// selects the face of the cube at `index`, and `None` is the first face. For volumes, all
+    /// slices of the level are returned one after another.
+    ///
+    /// Returns `None` if `index` or `level` is out of bounds or the file doesn't contain the face.
+    /// Like [`DdsDecoder::read_cubemap_face`], this restores the position of the reader.
+    pub fn read_raw_surface(
+        &mut self,
+        index: u32,
+        face: Option<CubeFace>,
+        level: u32,
+    ) -> Option<ImageResult<RawSurface>> {
+        let layout = self.subresource_layout(index, face, level)?;
+        let (blocks_x, blocks_y, bytes_per_block) = match self.format.block_size() {
+            Some(block_size) => (
+                layout.width.div_ceil(4),
+                layout.height.div_ceil(4),
+                block_size as u32,
+            ),
+            None => {
+                let bytes_per_pixel = self.format.bytes_per_pixel().unwrap() as u32;
+                (layout.width, layout.height, bytes_per_pixel)
+            }
+        };
+        // The reader is at the start of the surface data.
+        let offset = layout.offset - self.header.data_offset();
+        let result = self
+            .read_raw_at(offset, layout.size)
+            .map(|data| RawSurface {
+                width: layout.width,
+                height: layout.height,
+                depth: layout.depth,
+                format: self.format(),
+                blocks_x,
+                blocks_y,
+                bytes_per_block,
+                row_pitch: layout.row_pitch,
+                data,
+            });
+        Some(result)
+    }
+
+    /// Counts the modes and indexes of the blocks of a BC1 main surface, without decoding them.
+    ///
+    /// This helps to find out why parts of a texture are unexpectedly transparent: 3-color blocks
+    /// decode index 3 to transparent black, unless [`Bc1PunchThrough::BlackOpaque`] is set. Returns
+    /// an error if the format isn't BC1. Like [`DdsDecoder::read_cubemap_face`], this restores the
+    /// position of the reader.
+    pub fn analyze_bc1(&mut self) -> ImageResult<Bc1Stats> {
+        if self.format != Format::Bc1 {
+            return Err(ImageError::Unsupported(
+                UnsupportedError::from_format_and_kind(
+                    ImageFormat::Dds.into(),
+                    UnsupportedErrorKind::GenericFeature(format!(
+                        "BC1 statistics of {:?} surfaces",
+                        self.format()
+                    )),
+                ),
+            ));
+        }
+
+        let (width, height) = self.dimensions();
+        let data = self.read_raw_at(0, self.format.surface_size(width, height))?;
+        let mut stats = Bc1Stats::default();
+        for &block in data.as_chunks::<8>().0 {
+            stats.add_block(block);
+        }
+        Ok(stats)
+    }
+
+    /// Reads `len` bytes `offset` bytes after the current position of the reader, and restores
+    /// the position afterwards.
+    fn read_raw_at(&mut self, offset: u64, len: u64) -> ImageResult<Vec<u8>> {
+        self.limits.clone().reserve(len)?;
+        let start = self.reader.stream_position()?;
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        let result = if start.saturating_add(offset).saturating_add(len) > end {
+            Err(io::Error::from(io::ErrorKind::UnexpectedEof))
+        } else {
+            self.reader.seek(SeekFrom::Start(start + offset))?;
+            let mut data = vec![0; len as usize];
+            self.reader.read_exact(&mut data).map(|()| data)
+        };
+        self.reader.seek(SeekFrom::Start(start))?;
+        Ok(result?)
+    }
+
+    /// Decodes a mip level of the main surface into `buf`.
+    ///
+    /// Unlike [`DdsDecoder::read_volume_slice_mipmap`] and friends, this doesn't allocate, so the
+    /// same buffer can be reused for many levels or files. The pixels are written in the
+    /// [color type](ImageDecoder::color_type) of the decoder, so `buf` has to be exactly
+    /// `width * height * color_type().bytes_per_pixel()` bytes long for the
+    /// [dimensions of the level](DdsDecoder::mipmap_dimensions). Returns an error if it isn't, or
+    /// if `level` is out of bounds. Like [`DdsDecoder::read_cubemap_face`], this restores the
+    /// position of the reader.
+    pub fn read_mipmap_into(&mut self, level: u32, buf: &mut [u8]) -> ImageResult<()> {
+        let (width, height) = self.mipmap_dimensions(level);
+        let len =
+            u64::from(width) * u64::from(height) * u64::from(self.color_type().bytes_per_pixel());
+        if level >= self.mipmap_count() || u64::try_from(buf.len()) != Ok(len) {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+
+        let start = self.reader.stream_position()?;
+        let result = self
+            .seek_to_surface(start + self.mipmap_offset(level), level)
+            .and_then(|()| self.read_surface(level, height, buf));
+        self.reader.seek(SeekFrom::Start(start))?;
+        result
+    }
+
+    /// Decodes the main surface into the start of `buf`, like [`DdsDecoder::read_mipmap_into`].
+    ///
+    /// `buf` has to be at least [`DdsDecoder::output_size`] bytes long, and bytes after that are
+    /// left as they are. Returns an error if it's too small.
+    pub fn read_image_into(&mut self, buf: &mut [u8]) -> ImageResult<()> {
+        let Some(buf) = buf.get_mut(..self.output_size()) else {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        };
+        self.read_mipmap_into(0, buf)
+    }
+
+    /// Decodes the surface `offset` bytes after the current position of the reader, and restores
+    /// the position afterwards.
+    fn read_dynamic_image_at(&mut self, offset: u64, level: u32) -> ImageResult<DynamicImage> {
+        let start = self.reader.stream_position()?;
+        let result = self
+            .seek_to_surface(start + offset, level)
+            .and_then(|()| self.read_dynamic_image(level, None));
+        self.reader.seek(SeekFrom::Start(start))?;
+        result
+    }
+
+    /// Seeks to the 2D surface of a mip level at `position`.
+    ///
+    /// Returns an error if the reader ends before the surface does. This is checked before
+    /// anything is allocated, so truncated files can't make the decoder allocate buffers for data
+    /// they don't contain.
+    fn seek_to_surface(&mut self, position: u64, level: u32) -> ImageResult<()> {
+        let len = self.reader.seek(SeekFrom::End(0))?;
+        if position.saturating_add(self.mipmap_slice_size(level)) > len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.reader.seek(SeekFrom::Start(position))?;
+        Ok(())
+    }
+}
+
+/// The number of bytes an image with the given format and dimensions takes up when decoded to
+/// `channels` channels per pixel, e.g. 4 for RGBA.
+///
+/// Channels of formats with floating point values, like BC6H, are decoded as `f32`. Channels with
+/// more than 8 bits, like those of `R16_UNORM`, take up two bytes, and all other channels one
+/// byte. Saturates at `usize::MAX` instead of overflowing.
+///
+/// ```rust
+/// use image::codecs::dds::{decoded_len, DxgiFormat};
+///
+/// assert_eq!(decoded_len(DxgiFormat::Bc1Unorm, 10, 10, 4), 400);
+/// assert_eq!(decoded_len(DxgiFormat::Bc6hUf16, 10, 10, 3), 1200);
+/// ```
+pub fn decoded_len(format: DxgiFormat, width: u32, height: u32, channels: usize) -> usize {
+    use DxgiFormat::*;
+    let bytes_per_channel = match format {
+        R32G32B32A32Float | R32G32B32Float | R16G16B16A16Float | R32G32Float | R11G11B10Float
+        | R16G16Float | R32Float | R16Float | R9G9B9E5SharedExp | Bc6hUf16 | Bc6hSf16 => {
+            size_of::<f32>()
+        }
+        R16G16B16A16Unorm | R16G16Unorm | R16Unorm | R10G10B10A2Unorm => size_of::<u16>(),
+        _ => 1,
+    };
+    (width as usize)
+        .saturating_mul(height as usize)
+        .saturating_mul(channels)
+        .saturating_mul(bytes_per_channel)
+}
+
+/// Replaces the pixels of a surface with `rgba8` or `rgba32f` applied to them, depending on the
+/// color type. 16-bit pixels are mapped to `0.0..=1.0` for `rgba32f`. The other color types don't
+/// have alpha, so their pixels are kept.
+fn map_alpha_pixels(
+    color_type: ColorType,
+    buf: &mut [u8],
+    rgba8: fn([u8; 4]) -> [u8; 4],
+    rgba32f: fn([f32; 4]) -> [f32; 4],
+) {
+    match color_type {
+        ColorType::Rgba8 => {
+            for pixel in buf.as_chunks_mut::<4>().0 {
+                *pixel = rgba8(*pixel);
+            }
+        }
+        ColorType::Rgba32F => {
+            for pixel in buf.as_chunks_mut::<{ 4 * size_of::<f32>() }>().0 {
+                let channels: [[u8; 4]; 4] = bytemuck::cast(*pixel);
+                let channels = rgba32f(channels.map(f32::from_ne_bytes));
+                *pixel = bytemuck::cast(channels.map(f32::to_ne_bytes));
+            }
+        }
+        ColorType::La8 => {
+            for pixel in buf.as_chunks_mut::<2>().0 {
+                let [l, a] = *pixel;
+                let [l, _, _, a] = rgba8([l, l, l, a]);
+                *pixel = [l, a];
+            }
+        }
+        ColorType::Rgba16 => {
+            for pixel in buf.as_chunks_mut::<{ 4 * size_of::<u16>() }>().0 {
+                let channels: [[u8; 2]; 4] = bytemuck::cast(*pixel);
+                let channels = channels.map(|c| f32::from(u16::from_ne_bytes(c)) / 65535.0);
+                let channels = rgba32f(channels).map(|c| {
+                    let c = (c.clamp(0.0, 1.0) * 65535.0).round() as u16;
+                    c.to_ne_bytes()
+                });
+                *pixel = bytemuck::cast(channels);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The layout of a mip level in a DDS file, as returned by [`DdsDecoder::mip_layout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MipLevel {
+    /// The index of the level. Level 0 is the main surface.
+    pub level: u32,
+    /// The width of the level in pixels.
+    pub width: u32,
+    /// The height of the level in pixels.
+    pub height: u32,
+    /// The number of 2D slices of the level. This is 1 for files that aren't volumes.
+    pub depth: u32,
+    /// The offset of the stored level in bytes, relative to the start of the surface data after
+    /// the headers.
+    pub offset: u64,
+    /// The size of the stored level in bytes. Block compressed levels are padded to whole blocks.
+    pub len: u64,
+}
+
+/// Where a subresource is stored in a DDS file, as returned by [`DdsDecoder::subresource_layout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubresourceInfo {
+    /// The offset of the stored subresource in bytes from the start of the file, i.e. from the
+    /// position of the reader when the decoder was created.
+    pub offset: u64,
+    /// The size of the stored subresource in bytes, including all slices of volumes.
+    pub size: u64,
+    /// The number of bytes between the starts of two rows of 4x4 blocks for block compressed
+    /// formats, or of two rows of pixels for all other formats.
+    pub row_pitch: u64,
+    /// The width of the subresource in pixels.
+    pub width: u32,
+    /// The height of the subresource in pixels.
+    pub height: u32,
+    /// The number of 2D slices of the subresource. This is 1 for files that aren't volumes.
+    pub depth: u32,
+}
+
+/// The modes and indexes of the blocks of a BC1 surface, as returned by
+/// [`DdsDecoder::analyze_bc1`].
+///
+/// The indexes of partial blocks at the right and bottom edges are counted for all 16 pixels,
+/// including the ones outside the surface.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bc1Stats {
+    /// The number of blocks with `color0 > color1`, which interpolate 2 colors between the
+    /// endpoints.
+    pub four_color_blocks: u64,
+    /// The number of blocks with `color0 <= color1`, which interpolate 1 color between the
+    /// endpoints, and use index 3 for transparent black.
+    pub three_color_blocks: u64,
+    /// The number of 3-color blocks with at least one pixel of index 3.
+    pub transparent_blocks: u64,
+    /// How often each index is used by the pixels of 4-color blocks.
+    pub four_color_indexes: [u64; 4],
+    /// How often each index is used by the pixels of 3-color blocks. Index 3 is transparent.
+    pub three_color_indexes: [u64; 4],
+}
+
+impl Bc1Stats {
+    fn add_block(&mut self, block: [u8; 8]) {
+        let color0 = u16::from_le_bytes([block[0], block[1]]);
+        let color1 = u16::from_le_bytes([block[2], block[3]]);
+        let indexes = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+        let counts = if color0 > color1 {
+            self.four_color_blocks += 1;
+            &mut self.four_color_indexes
+        } else {
+            self.three_color_blocks += 1;
+            // Every pair of bits is 0b11 for the pixels of index 3.
+            if indexes & (indexes >> 1) & 0x5555_5555 != 0 {
+                self.transparent_blocks += 1;
+            }
+            &mut self.three_color_indexes
+        };
+        for i in 0..16 {
+            counts[(indexes >> (i * 2)) as usize & 0b11] += 1;
+        }
+    }
+}
+
+/// The stored bytes of a mip level, as returned by [`DdsDecoder::read_raw_surface`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawSurface {
+    /// The width of the level in pixels.
+    pub width: u32,
+    /// The height of the level in pixels.
+    pub height: u32,
+    /// The number of 2D slices of the level. This is 1 for files that aren't volumes.
+    pub depth: u32,
+    /// The format of the stored bytes, the same as [`DdsDecoder::format`].
+    pub format: DxgiFormat,
+    /// The number of 4x4 blocks in each row, or the width for formats that aren't block
+    /// compressed.
+    pub blocks_x: u32,
+    /// The number of rows of 4x4 blocks, or the height for formats that aren't block compressed.
+    pub blocks_y: u32,
+    /// The number of bytes in each block, or in each pixel for formats that aren't block
+    /// compressed.
+    pub bytes_per_block: u32,
+    /// The number of bytes between the starts of two rows of blocks or pixels. Rows of
+    /// uncompressed main surfaces may be padded, so this can be more than
+    /// `blocks_x * bytes_per_block`.
+    pub row_pitch: u64,
+    /// The stored bytes, `row_pitch * blocks_y` for each slice.
+    pub data: Vec<u8>,
+}
+
+/// The dimensions and layout of a DDS file, as returned by [`DdsMetadata::from_reader`] and
+/// [`DdsDecoder::metadata`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DdsMetadata {
+    /// The width of the main surface in pixels.
+    pub width: u32,
+    /// The height of the main surface in pixels.
+    pub height: u32,
+    /// The number of 2D slices of the main surface. This is 1 for files that aren't volumes.
+    pub depth: u32,
+    /// The number of mip levels, including the main surface.
+    pub mip_count: u32,
+    /// The number of textures in the texture array. For cubemaps, this is the number of cubes.
+    pub array_size: u32,
+    /// Whether the file contains a cubemap.
+    pub is_cubemap: bool,
+    /// The format of the surfaces. See [`DdsDecoder::format`].
+    pub format: DxgiFormat,
+    /// The color type the surfaces decode to with the default options.
+    pub color_type: ColorType,
+    /// How the alpha channel is meant to be interpreted. See [`DdsDecoder::alpha_mode`].
+    pub alpha_mode: AlphaMode,
+    /// The DX10 header of the file, if present.
+    pub dx10_header: Option<Dx10Header>,
+}
+
+impl DdsMetadata {
+    /// Reads the headers from a stream positioned at the magic number.
+    ///
+    /// Only the headers are read, so the stream is left at the start of the surface data. Returns
+    /// an error if the headers are invalid or the format isn't supported.
+    pub fn from_reader(reader: &mut impl Read) -> ImageResult<Self> {
+        DdsDecoder::new(reader).map(|decoder| decoder.metadata())
+    }
+}
+
+/// An iterator over the decoded mip levels of a DDS file.
+///
+/// Created by [`DdsDecoder::into_mipmaps`]. For volume textures, only the first slice of each level
+/// is decoded. The iterator stops after the first error.
+pub struct Mipmaps<R> {
+    decoder: DdsDecoder<R>,
+    level: u32,
+}
+
+impl<R: Read> Iterator for Mipmaps<R> {
+    type Item = ImageResult<DynamicImage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.level >= self.decoder.mipmap_count() {
+            return None;
+        }
+
+        let mut result = self.decoder.read_dynamic_image(self.level, None);
+        if result.is_ok() {
+            // Only the first slice of each level of a volume is decoded.
+            if let Err(err) = self.decoder.skip_other_slices(self.level) {
+                result = Err(err);
+            }
+        }
+        self.level = if result.is_ok() {
+            self.level + 1
+        } else {
+            u32::MAX
+        };
+
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.decoder.mipmap_count().saturating_sub(self.level) as usize;
+        (0, Some(remaining))
+    }
+}
+
+/// An iterator over the mip levels of a DDS file decoded to RGBA.
+///
+/// Created by [`DdsDecoder::mip_levels`]. Each item is `(level, width, height, pixels)`. Like
+/// [`Mipmaps`], only the first slice of each level of a volume is decoded, and the iterator stops
+/// after the first error.
+pub struct MipLevels<R> {
+    mipmaps: Mipmaps<R>,
+}
+
+impl<R: Read> Iterator for MipLevels<R> {
+    type Item = ImageResult<(u32, u32, u32, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let level = self.mipmaps.level;
+        let image = self.mipmaps.next()?;
+        Some(image.map(|image| {
+            (
+                level,
+                image.width(),
+                image.height(),
+                image.into_rgba8().into_raw(),
+            )
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.mipmaps.size_hint()
+    }
+}
+
+/// A decoder for the main surface of a DDS file that decodes one row of blocks at a time.
+///
+/// Created by [`DdsDecoder::into_block_decoder`]. Each item of the iterator holds the next 4 rows
+/// of pixels, or fewer for the last item if the height isn't a multiple of 4, in the
+/// [color type](BlockDecoder::color_type) of the decoder. The iterator stops after the first
+/// error.
+pub struct BlockDecoder<R> {
+    decoder: DdsDecoder<R>,
+    y: u32,
+}
+
+impl<R: Read> BlockDecoder<R> {
+    /// The dimensions of the main surface.
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.decoder.dimensions()
+    }
+
+    /// The color type of the decoded rows.
+    pub fn color_type(&self) -> ColorType {
+        self.decoder.color_type()
+    }
+}
+
+impl<R: Read> Iterator for BlockDecoder<R> {
+    type Item = ImageResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (width, height) = self.dimensions();
+        if self.y >= height {
+            return None;
+        }
+
+        let rows = (height - self.y).min(4);
+        let len =
+            u64::from(width) * u64::from(rows) * u64::from(self.color_type().bytes_per_pixel());
+        let result = self.decoder.check_alloc(0, rows, len).and_then(|()| {
+            let mut buf = vec![0; len as usize];
+            self.decoder.read_surface(0, rows, &mut buf).map(|()| buf)
+        });
+        self.y = if result.is_ok() {
+            self.y + rows
+        } else {
+            height
+        };
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, height) = self.dimensions();
+        let remaining = height.saturating_sub(self.y).div_ceil(4) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+/// Decodes the next surface of a [`DdsDecoder`], which may be a smaller mip level than the main
+/// surface.
+struct SurfaceDecoder<'a, 'p, R> {
+    decoder: &'a mut DdsDecoder<R>,
+    level: u32,
+    progress: Option<&'p mut dyn FnMut(u32) -> ControlFlow<()>>,
+}
+
+impl<R: Read> ImageDecoder for SurfaceDecoder<'_, '_, R> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.decoder.mipmap_dimensions(self.level)
+    }
+
+    fn color_type(&self) -> ColorType {
+        self.decoder.color_type()
+    }
+
+    fn read_image(self, buf: &mut [u8]) -> ImageResult<()> {
+        assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
+        let (width, height) = self.dimensions();
+        let row_len = width as usize * usize::from(self.color_type().bytes_per_pixel());
+        let Some(progress) = self.progress else {
+            return self.decoder.read_surface(self.level, height, buf);
+        };
+
+        for (block_row, rows) in buf.chunks_mut(row_len * 4).enumerate() {
+            let height = (rows.len() / row_len) as u32;
+            self.decoder.read_surface(self.level, height, rows)?;
+            if progress(block_row as u32 + 1).is_break() {
+                let error = io::Error::other(Cancelled);
+                return Err(ImageError::IoError(error));
+            }
+        }
+        Ok(())
+    }
+
+    fn read_image_boxed(self: Box<Self>, buf: &mut [u8]) -> ImageResult<()> {
+        (*self).read_image(buf)
+    }
+}
+
+impl<R: Read> ImageDecoder for DdsDecoder<R> {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.header.width, self.header.height)
+    }
+
+    fn color_type(&self) -> ColorType {
+        match self.format.color_type() {
+            ColorType::L8 if self.gray_to_rgba && self.high_precision => ColorType::Rgba16,
+            ColorType::L8 if self.gray_to_rgba => ColorType::Rgba8,
+            ColorType::L8 if self.high_precision => ColorType::L16,
+            ColorType::Rgb8 if self.high_precision => ColorType::Rgb16,
+            _ if self.effective_srgb_handling() == SrgbHandling::ToLinearF32 => ColorType::Rgba32F,
+            color_type => color_type,
+        }
+    }
+
+    fn set_limits(&mut self, limits: Limits) -> ImageResult<()> {
+        limits.check_support(&crate::LimitSupport::default())?;
+        let (width, height) = self.dimensions();
+        limits.check_dimensions(width, height)?;
+        self.limits = limits;
+        Ok(())
+    }
+
+    fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()> {
+        assert_eq!(u64::try_from(buf.len()), Ok(self.total_bytes()));
+
+        let (_, height) = self.dimensions();
+        self.read_surface(0, height, buf)
+    }
+
+    fn read_image_boxed(self: Box<Self>, buf: &mut [u8]) -> ImageResult<()> {
+        (*self).read_image(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codecs::dds::header::{
+        PixelFormat, DDPF_ALPHAPIXELS, DDPF_FOURCC, DDPF_LUMINANCE, DDPF_RGB, MAGIC,
+    };
+    use crate::GenericImageView;
+    use std::io::Cursor;
+
+    /// Builds a DDS file with a single surface.
+    fn dds_file(pixel_format: PixelFormat, width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+        let mut file = MAGIC.to_vec();
+        // DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT
+        let flags = 0x1007;
+        for value in [Header::SIZE, flags, height, width, 0, 0, 0] {
+            file.extend(value.to_le_bytes());
+        }
+        file.extend([0; 44]);
+        for value in [
+            32,
+            pixel_format.flags,
+            u32::from_le_bytes(pixel_format.four_cc),
+            pixel_format.rgb_bit_count,
+            pixel_format.r_bit_mask,
+            pixel_format.g_bit_mask,
+            pixel_format.b_bit_mask,
+            pixel_format.a_bit_mask,
+        ] {
+            file.extend(value.to_le_bytes());
+        }
+        // DDSCAPS_TEXTURE
+        for value in [0x1000_u32, 0, 0, 0, 0] {
+            file.extend(value.to_le_bytes());
+        }
+        file.extend(data);
+        file
+    }
+
+    fn rgb_pixel_format(masks: [u32; 4]) -> PixelFormat {
+        let [r, g, b, a] = masks;
+        PixelFormat {
+            flags: DDPF_RGB | if a != 0 { DDPF_ALPHAPIXELS } else { 0 },
+            rgb_bit_count: 32,
+            r_bit_mask: r,
+            g_bit_mask: g,
+            b_bit_mask: b,
+            a_bit_mask: a,
+            ..Default::default()
+        }
+    }
+
+    fn d3dfmt_pixel_format(d3dfmt: u32) -> PixelFormat {
+        PixelFormat {
+            flags: DDPF_FOURCC,
+            four_cc: d3dfmt.to_le_bytes(),
+            ..Default::default()
+        }
+    }
+
+    fn decode(file: &[u8]) -> ImageResult<Vec<u8>> {
+        let decoder = DdsDecoder::new(Cursor::new(file))?;
+        let mut buf = vec![0; decoder.total_bytes() as usize];
+        decoder.read_image(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// A 2x2 surface with 4 bytes per pixel.
+    const DATA: [u8; 16] = [
+        1, 2, 3, 4, 5, 6, 7, 8, //
+        9, 10, 11, 12, 13, 14, 15, 16,
+    ];
+
+    #[test]
+    fn r8g8b8a8() {
+        let expected = DATA;
+        let pixel_format = rgb_pixel_format([0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000]);
+        assert_eq!(
+            decode(&dds_file(pixel_format, 2, 2, &DATA)).unwrap(),
+            expected
+        );
+        let pixel_format = d3dfmt_pixel_format(32);
+        assert_eq!(
+            decode(&dds_file(pixel_format, 2, 2, &DATA)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn r8g8b8x8() {
+        let expected = [
+            1, 2, 3, 255, 5, 6, 7, 255, //
+            9, 10, 11, 255, 13, 14, 15, 255,
+        ];
+        let pixel_format = rgb_pixel_format([0xFF, 0xFF00, 0xFF_0000, 0]);
+        assert_eq!(
+            decode(&dds_file(pixel_format, 2, 2, &DATA)).unwrap(),
+            expected
+        );
+        let pixel_format = d3dfmt_pixel_format(33);
+        assert_eq!(
+            decode(&dds_file(pixel_format, 2, 2, &DATA)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn b8g8r8a8() {
+        let expected = [
+            3, 2, 1, 4, 7, 6, 5, 8, //
+            11, 10, 9, 12, 15, 14, 13, 16,
+        ];
+        let pixel_format = rgb_pixel_format([0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000]);
+        assert_eq!(
+            decode(&dds_file(pixel_format, 2, 2, &DATA)).unwrap(),
+            expected
+        );
+        let pixel_format = d3dfmt_pixel_format(21);
+        assert_eq!(
+            decode(&dds_file(pixel_format, 2, 2, &DATA)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn b8g8r8x8() {
+        let expected = [
+            3, 2, 1, 255, 7, 6, 5, 255, //
+            11, 10, 9, 255, 15, 14, 13, 255,
+        ];
+        let pixel_format = rgb_pixel_format([0xFF_0000, 0xFF00, 0xFF, 0]);
+        assert_eq!(
+            decode(&dds_file(pixel_format, 2, 2, &DATA)).unwrap(),
+            expected
+        );
+        let pixel_format = d3dfmt_pixel_format(22);
+        assert_eq!(
+            decode(&dds_file(pixel_format, 2, 2, &DATA)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn rgb24() {
+        // 3x2 with each row padded to 12 bytes
+        let data = [
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 0xEE, 0xEE, 0xEE, //
+            10, 11, 12, 13, 14, 15, 16, 17, 18, 0xEE, 0xEE, 0xEE,
+        ];
+        let rgb = [
+            1, 2, 3, 255, 4, 5, 6, 255, 7, 8, 9, 255, //
+            10, 11, 12, 255, 13, 14, 15, 255, 16, 17, 18, 255,
+        ];
+        let bgr = [
+            3, 2, 1, 255, 6, 5, 4, 255, 9, 8, 7, 255, //
+            12, 11, 10, 255, 15, 14, 13, 255, 18, 17, 16, 255,
+        ];
+
+        let rgb24_pixel_format = |masks| PixelFormat {
+            rgb_bit_count: 24,
+            ..rgb_pixel_format(masks)
+        };
+        for (pixel_format, expected) in [
+            (rgb24_pixel_format([0xFF, 0xFF00, 0xFF_0000, 0]), rgb),
+            (rgb24_pixel_format([0xFF_0000, 0xFF00, 0xFF, 0]), bgr),
+            (d3dfmt_pixel_format(20), bgr),
+        ] {
+            let mut file = dds_file(pixel_format, 3, 2, &data);
+            set_pitch(&mut file, 12);
+            assert_eq!(decode(&file).unwrap(), expected);
+
+            // Without a pitch, rows are tightly packed.
+            let file = dds_file(pixel_format, 3, 2, &[&data[..9], &data[12..21]].concat());
+            assert_eq!(decode(&file).unwrap(), expected);
+        }
+
+        // 24-bit formats don't have alpha.
+        let pixel_format = rgb24_pixel_format([0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000]);
+        assert!(DdsDecoder::new(Cursor::new(dds_file(pixel_format, 3, 2, &data))).is_err());
+    }
+
+    #[test]
+    fn packed_16_bit() {
+        let rgb16_pixel_format = |masks| PixelFormat {
+            rgb_bit_count: 16,
+            ..rgb_pixel_format(masks)
+        };
+
+        // red, green, blue, and a mix of all channels
+        let cases = [
+            (
+                rgb16_pixel_format([0xF800, 0x07E0, 0x001F, 0]),
+                [0xF800, 0x07E0, 0x001F, 0x8410],
+                // 0x8410 is 16 / 31, 32 / 63, 16 / 31
+                [
+                    [255, 0, 0, 255],
+                    [0, 255, 0, 255],
+                    [0, 0, 255, 255],
+                    [132, 130, 132, 255],
+                ],
+            ),
+            (
+                rgb16_pixel_format([0x7C00, 0x03E0, 0x001F, 0x8000]),
+                [0xFC00, 0x83E0, 0x001F, 0x4210],
+                // 0x4210 is 16 / 31 for all channels and transparent
+                [
+                    [255, 0, 0, 255],
+                    [0, 255, 0, 255],
+                    [0, 0, 255, 0],
+                    [132, 132, 132, 0],
+                ],
+            ),
+            (
+                rgb16_pixel_format([0x7C00, 0x03E0, 0x001F, 0]),
+                [0x7C00, 0x03E0, 0x001F, 0x4210],
+                // The unused bit is ignored.
+                [
+                    [255, 0, 0, 255],
+                    [0, 255, 0, 255],
+                    [0, 0, 255, 255],
+                    [132, 132, 132, 255],
+                ],
+            ),
+            (
+                rgb16_pixel_format([0x0F00, 0x00F0, 0x000F, 0xF000]),
+                [0xFF00, 0xF0F0, 0x000F, 0x5A3C],
+                [
+                    [255, 0, 0, 255],
+                    [0, 255, 0, 255],
+                    [0, 0, 255, 0],
+                    [170, 51, 204, 85],
+                ],
+            ),
+        ];
+        for (pixel_format, values, expected) in cases {
+            let data: Vec<u8> = values.iter().flat_map(|v: &u16| v.to_le_bytes()).collect();
+            let file = dds_file(pixel_format, 2, 2, &data);
+            assert_eq!(decode(&file).unwrap(), expected.concat());
+        }
+
+        // D3DFMT codes instead of masks
+        for (d3dfmt, (_, values, expected)) in [23, 25, 24, 26].into_iter().zip(cases) {
+            let data: Vec<u8> = values.iter().flat_map(|v: &u16| v.to_le_bytes()).collect();
+            let file = dds_file(d3dfmt_pixel_format(d3dfmt), 2, 2, &data);
+            assert_eq!(decode(&file).unwrap(), expected.concat());
+        }
+
+        // Fully zero and fully saturated pixels
+        let extremes = [
+            (23, [0, 0, 0, 255], [255; 4]),
+            (24, [0, 0, 0, 255], [255; 4]),
+            (25, [0; 4], [255; 4]),
+            (26, [0; 4], [255; 4]),
+        ];
+        for (d3dfmt, zero, saturated) in extremes {
+            let file = dds_file(d3dfmt_pixel_format(d3dfmt), 2, 1, &[0, 0, 0xFF, 0xFF]);
+            assert_eq!(
+                decode(&file).unwrap(),
+                [zero, saturated].concat(),
+                "{d3dfmt}"
+            );
+        }
+
+        // DXGI formats
+        let cases = [cases[0], cases[1], cases[3]];
+        for (dxgi_format, (_, values, expected)) in [85, 86, 115].into_iter().zip(cases) {
+            let data: Vec<u8> = values.iter().flat_map(|v: &u16| v.to_le_bytes()).collect();
+            let file = dx10_file(dxgi_format, 2, 2, &data);
+            assert_eq!(decode(&file).unwrap(), expected.concat());
+        }
+    }
+
+    fn set_pitch(file: &mut [u8], pitch: u32) {
+        // DDSD_PITCH
+        file[8] |= 0x8;
+        file[20..24].copy_from_slice(&pitch.to_le_bytes());
+    }
+
+    #[test]
+    fn half_floats() {
+        let le_bytes = |values: &[u16]| -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect()
+        };
+        let f32s = |image: &DynamicImage| -> Vec<u32> {
+            let raw = image.as_bytes().as_chunks::<4>().0;
+            raw.iter()
+                .map(|&c| f32::from_ne_bytes(c).to_bits())
+                .collect()
+        };
+
+        // R16_FLOAT with 1.0, -2.0, the smallest subnormal, infinity, and a NaN with a payload
+        let values = [0x3C00, 0xC000, 0x0001, 0x7C00, 0x7E01, 0x8000];
+        let file = dx10_file(54, 3, 2, &le_bytes(&values));
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.format(), DxgiFormat::R16Float);
+        assert_eq!(decoder.color_type(), ColorType::Rgb32F);
+        assert_eq!(
+            decoder.output_size(),
+            decoded_len(DxgiFormat::R16Float, 3, 2, 3)
+        );
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        let expected: Vec<u32> = values
+            .iter()
+            .flat_map(|&value| [half_to_f32(value).to_bits(); 3])
+            .collect();
+        assert_eq!(f32s(&image), expected);
+        assert_eq!(expected[9], f32::INFINITY.to_bits());
+        assert!(f32::from_bits(expected[12]).is_nan());
+
+        // R16G16B16A16_FLOAT, and the equivalent D3DFMT_A16B16G16R16F
+        let values = [
+            0x3C00, 0x3800, 0x0000, 0x3C00, 0xFC00, 0x4000, 0x3555, 0x3800,
+        ];
+        for file in [
+            dx10_file(10, 2, 1, &le_bytes(&values)),
+            dds_file(d3dfmt_pixel_format(113), 2, 1, &le_bytes(&values)),
+        ] {
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.format(), DxgiFormat::R16G16B16A16Float);
+            assert_eq!(decoder.color_type(), ColorType::Rgba32F);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            let expected: Vec<u32> = values.map(|v| half_to_f32(v).to_bits()).to_vec();
+            assert_eq!(f32s(&image), expected);
+
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_alpha_handling(AlphaHandling::Premultiply);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            assert_eq!(
+                image.as_rgba32f().unwrap().as_raw()[..4],
+                [1.0, 0.5, 0.0, 1.0]
+            );
+            // Infinity is kept.
+            assert_eq!(
+                image.as_rgba32f().unwrap().as_raw()[4..6],
+                [f32::NEG_INFINITY, 1.0]
+            );
+        }
+    }
+
+    #[test]
+    fn floats() {
+        let le_bytes = |values: &[f32]| -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect()
+        };
+        let bits = |values: &[f32]| -> Vec<u32> { values.iter().map(|v| v.to_bits()).collect() };
+        let nan = f32::from_bits(0x7FC0_1234);
+        let decode = |file: &[u8]| {
+            let decoder = DdsDecoder::new(Cursor::new(file)).unwrap();
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            let raw = image.as_bytes().as_chunks::<4>().0;
+            let values: Vec<u32> = raw.iter().map(|&c| u32::from_ne_bytes(c)).collect();
+            (image.color(), values)
+        };
+
+        // R32_FLOAT with 4 bytes of padding after each row
+        let mut data = le_bytes(&[-1.5, nan]);
+        data.extend([0xAA; 4]);
+        data.extend(le_bytes(&[-0.0, f32::NEG_INFINITY]));
+        data.extend([0xAA; 4]);
+        let mut file = dx10_file(41, 2, 2, &data);
+        set_pitch(&mut file, 12);
+        let (color, values) = decode(&file);
+        assert_eq!(color, ColorType::Rgb32F);
+        let expected = [[-1.5; 3], [nan; 3], [-0.0; 3], [f32::NEG_INFINITY; 3]];
+        assert_eq!(values, bits(expected.as_flattened()));
+
+        // R32G32_FLOAT, with blue 0 like R16G16_UNORM
+        let file = dx10_file(16, 2, 1, &le_bytes(&[-2.0, nan, 3.0, 1e30]));
+        let (color, values) = decode(&file);
+        assert_eq!(color, ColorType::Rgb32F);
+        assert_eq!(values, bits(&[-2.0, nan, 0.0, 3.0, 1e30, 0.0]));
+
+        // R32G32B32_FLOAT, with an alpha of 1.0
+        let file = dx10_file(6, 2, 1, &le_bytes(&[-2.0, nan, 0.25, 4.0, -8.0, 16.0]));
+        let (color, values) = decode(&file);
+        assert_eq!(color, ColorType::Rgba32F);
+        assert_eq!(values, bits(&[-2.0, nan, 0.25, 1.0, 4.0, -8.0, 16.0, 1.0]));
+
+        // R32G32B32A32_FLOAT, and the equivalent D3DFMT_A32B32G32R32F
+        let rgba = [-2.0, nan, 0.25, 0.5, f32::INFINITY, -0.0, 1e-40, -1.0];
+        for file in [
+            dx10_file(2, 2, 1, &le_bytes(&rgba)),
+            dds_file(d3dfmt_pixel_format(116), 2, 1, &le_bytes(&rgba)),
+        ] {
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.format(), DxgiFormat::R32G32B32A32Float);
+            let (color, values) = decode(&file);
+            assert_eq!(color, ColorType::Rgba32F);
+            assert_eq!(values, bits(&rgba));
+        }
+    }
+
+    #[test]
+    fn packed() {
+        let le_bytes = |values: &[u32]| -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect()
+        };
+
+        // R10G10B10A2_UNORM
+        let values = [0, u32::MAX, 1023 | 512 << 10 | 1 << 20 | 1 << 30];
+        let file = dx10_file(24, 3, 1, &le_bytes(&values));
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.format(), DxgiFormat::R10G10B10A2Unorm);
+        assert_eq!(decoder.color_type(), ColorType::Rgba16);
+        assert_eq!(
+            decoder.output_size(),
+            decoded_len(DxgiFormat::R10G10B10A2Unorm, 3, 1, 4)
+        );
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(
+            image.as_rgba16().unwrap().as_raw(),
+            &[0, 0, 0, 0, 65535, 65535, 65535, 65535, 65535, 32800, 64, 21845]
+        );
+
+        // R9G9B9E5_SHAREDEXP, with values above 1.0
+        let values = [256 | 128 << 9 | 1 << 18 | 16 << 27, u32::MAX];
+        let file = dx10_file(67, 2, 1, &le_bytes(&values));
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.format(), DxgiFormat::R9G9B9E5SharedExp);
+        assert_eq!(decoder.color_type(), ColorType::Rgb32F);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(
+            image.as_rgb32f().unwrap().as_raw(),
+            &[1.0, 0.5, 1.0 / 256.0, 65408.0, 65408.0, 65408.0]
+        );
+    }
+
+    #[test]
+    fn unorm16() {
+        let le_bytes = |values: &[u16]| -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect()
+        };
+
+        // R16_UNORM with 2 bytes of padding after each row
+        let values = [0, 1, 256, 0x1234, 0xFFFF, 0x8000];
+        let mut data = le_bytes(&values[..3]);
+        data.extend([0xAA; 2]);
+        data.extend(le_bytes(&values[3..]));
+        data.extend([0xAA; 2]);
+        let mut file = dx10_file(56, 3, 2, &data);
+        set_pitch(&mut file, 8);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.format(), DxgiFormat::R16Unorm);
+        assert_eq!(decoder.color_type(), ColorType::L16);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_luma16().unwrap().as_raw(), &values);
+
+        // R16G16_UNORM, with blue 0 like BC5
+        let file = dx10_file(35, 2, 1, &le_bytes(&[1, 2, 0xFFFF, 0x0102]));
+        let image = DynamicImage::from_decoder(DdsDecoder::new(Cursor::new(&file)).unwrap());
+        let image = image.unwrap();
+        assert_eq!(
+            image.as_rgb16().unwrap().as_raw(),
+            &[1, 2, 0, 0xFFFF, 0x0102, 0]
+        );
+
+        // R16G16B16A16_UNORM, and the equivalent D3DFMT_A16B16G16R16
+        let values = [1, 2, 3, 4, 0xFFFF, 0x8000, 0, 0x8000];
+        for file in [
+            dx10_file(11, 2, 1, &le_bytes(&values)),
+            dds_file(d3dfmt_pixel_format(36), 2, 1, &le_bytes(&values)),
+        ] {
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.format(), DxgiFormat::R16G16B16A16Unorm);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            assert_eq!(image.as_rgba16().unwrap().as_raw(), &values);
+
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_alpha_handling(AlphaHandling::Premultiply);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            assert_eq!(
+                image.as_rgba16().unwrap().as_raw(),
+                &[0, 0, 0, 4, 0x8000, 0x4000, 0, 0x8000]
+            );
+        }
+    }
+
+    #[test]
+    fn unorm8() {
+        // R8_UNORM with 1 byte of padding after each row, and the equivalent D3DFMT_L8
+        let values = [0, 1, 0x80, 0x12, 0xFF, 0x7F];
+        let mut file = dx10_file(61, 3, 2, &[0, 1, 0x80, 0xAA, 0x12, 0xFF, 0x7F, 0xAA]);
+        set_pitch(&mut file, 4);
+        let mut legacy = dds_file(d3dfmt_pixel_format(50), 3, 2, &values);
+        set_pitch(&mut legacy, 3);
+        for file in [file, legacy] {
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.format(), DxgiFormat::R8Unorm);
+            assert_eq!(decoder.color_type(), ColorType::L8);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            assert_eq!(image.as_luma8().unwrap().as_raw(), &values);
+
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_high_precision(true);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            let expected = values.map(|v| u16::from(v) * 257);
+            assert_eq!(image.as_luma16().unwrap().as_raw(), &expected);
+
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_gray_to_rgba(true);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            let expected: Vec<u8> = values.iter().flat_map(|&v| [v, v, v, 255]).collect();
+            assert_eq!(image.as_rgba8().unwrap().as_raw(), &expected);
+
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_gray_to_rgba(true);
+            decoder.set_high_precision(true);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            let expected: Vec<u16> = expected.iter().map(|&v| u16::from(v) * 257).collect();
+            assert_eq!(image.as_rgba16().unwrap().as_raw(), &expected);
+        }
+
+        // R8G8_UNORM, with blue 0 like BC5
+        let file = dx10_file(49, 2, 1, &[1, 2, 0xFF, 0x80]);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.format(), DxgiFormat::R8G8Unorm);
+        assert_eq!(decoder.color_type(), ColorType::Rgb8);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_rgb8().unwrap().as_raw(), &[1, 2, 0, 0xFF, 0x80, 0]);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_high_precision(true);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(
+            image.as_rgb16().unwrap().as_raw(),
+            &[257, 514, 0, 0xFFFF, 0x8080, 0]
+        );
+    }
+
+    #[test]
+    fn legacy_unorm8() {
+        // (flags, bit count, red mask, green mask, alpha mask)
+        let pixel_format = |flags, rgb_bit_count, r_bit_mask, g_bit_mask, a_bit_mask| PixelFormat {
+            flags,
+            rgb_bit_count,
+            r_bit_mask,
+            g_bit_mask,
+            a_bit_mask,
+            ..Default::default()
+        };
+        let values = [0, 1, 0x80, 0xFF];
+
+        // 8-bit luminance as written by D3DX and nvtt, and the same as red
+        for pixel_format in [
+            pixel_format(DDPF_LUMINANCE, 8, 0xFF, 0, 0),
+            pixel_format(DDPF_RGB, 8, 0xFF, 0, 0),
+        ] {
+            let file = dds_file(pixel_format, 2, 2, &values);
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.format(), DxgiFormat::R8Unorm);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            assert_eq!(image.as_luma8().unwrap().as_raw(), &values);
+        }
+
+        // A8L8, with luminance in the low byte, and the equivalent D3DFMT_A8L8
+        let data = [10, 0xFF, 20, 0x80, 30, 0, 40, 0x40];
+        for pixel_format in [
+            pixel_format(DDPF_LUMINANCE | DDPF_ALPHAPIXELS, 16, 0x00FF, 0, 0xFF00),
+            d3dfmt_pixel_format(51),
+        ] {
+            let file = dds_file(pixel_format, 2, 2, &data);
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.color_type(), ColorType::La8);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            assert_eq!(image.as_luma_alpha8().unwrap().as_raw(), &data);
+
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_alpha_handling(AlphaHandling::Premultiply);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            assert_eq!(
+                image.as_luma_alpha8().unwrap().as_raw(),
+                &[10, 0xFF, 10, 0x80, 0, 0, 10, 0x40]
+            );
+        }
+
+        // 16-bit red and green, with blue 0 like R8G8_UNORM
+        let file = dds_file(
+            pixel_format(DDPF_RGB, 16, 0x00FF, 0xFF00, 0),
+            2,
+            1,
+            &[1, 2, 3, 4],
+        );
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.format(), DxgiFormat::R8G8Unorm);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_rgb8().unwrap().as_raw(), &[1, 2, 0, 3, 4, 0]);
+
+        // Other luminance masks aren't supported.
+        for pixel_format in [
+            pixel_format(DDPF_LUMINANCE | DDPF_ALPHAPIXELS, 8, 0x0F, 0, 0xF0),
+            pixel_format(DDPF_LUMINANCE, 16, 0xFFF0, 0, 0),
+        ] {
+            let file = dds_file(pixel_format, 2, 2, &data);
+            assert!(matches!(
+                DdsDecoder::new(Cursor::new(&file)),
+                Err(ImageError::Unsupported(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn padded_rows() {
+        // Each row of the main surface is followed by 8 bytes of padding, then comes a 1x1 mip.
+        let data = [
+            1, 2, 3, 4, 5, 6, 7, 8, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE, //
+            9, 10, 11, 12, 13, 14, 15, 16, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE, //
+            17, 18, 19, 20,
+        ];
+        let mut file = dx10_file(87, 2, 2, &data);
+        set_pitch(&mut file, 16);
+        set_mipmap_count(&mut file, 2);
+
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(
+            decoder
+                .mip_layout()
+                .map(|level| level.len)
+                .collect::<Vec<_>>(),
+            [32, 4]
+        );
+        let images: Vec<_> = decoder.into_mipmaps().map(Result::unwrap).collect();
+        assert_eq!(
+            images[0].as_bytes(),
+            [3, 2, 1, 4, 7, 6, 5, 8, 11, 10, 9, 12, 15, 14, 13, 16]
+        );
+        assert_eq!(images[1].as_bytes(), [19, 18, 17, 20]);
+
+        // Pitches smaller than a row are ignored.
+        let mut file = dds_file(d3dfmt_pixel_format(32), 2, 2, &DATA);
+        set_pitch(&mut file, 4);
+        assert_eq!(decode(&file).unwrap(), DATA);
+    }
+
+    fn four_cc_pixel_format(four_cc: &[u8; 4]) -> PixelFormat {
+        PixelFormat {
+            flags: DDPF_FOURCC,
+            four_cc: *four_cc,
+            ..Default::default()
+        }
+    }
+
+    /// BC1 blocks that only use `color0`, one color per block.
+    fn solid_bc1_blocks(count: usize) -> Vec<u8> {
+        (0..count as u16)
+            .flat_map(|i| {
+                let color0 = (i + 1) * 0x0841;
+                let [lo, hi] = color0.to_le_bytes();
+                [lo, hi, 0, 0, 0, 0, 0, 0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bc1_partial_blocks() {
+        let sizes = [
+            (1_u32, 1_u32),
+            (4, 4),
+            (5, 5),
+            (10, 6),
+            (3, 9),
+            (5, 3),
+            (13, 7),
+        ];
+        for (width, height) in sizes {
+            let blocks_per_row = width.div_ceil(4);
+            let blocks = (blocks_per_row * height.div_ceil(4)) as usize;
+            let mut data = solid_bc1_blocks(blocks);
+            // Each row of each block uses the 4 palette colors in a different order, so pixels
+            // from the wrong position within a block are noticed too.
+            for block in data.chunks_exact_mut(8) {
+                block[4..].copy_from_slice(&[0x1B, 0xE4, 0x4E, 0xB1]);
+            }
+            let file = dds_file(four_cc_pixel_format(b"DXT1"), width, height, &data);
+
+            let image = decode(&file).unwrap();
+            assert_eq!(image.len(), (width * height * 4) as usize);
+            for (i, pixel) in image.chunks_exact(4).enumerate() {
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                let block = (y / 4 * blocks_per_row + x / 4) as usize * 8;
+                let block = data[block..block + 8].try_into().unwrap();
+                assert_eq!(
+                    pixel,
+                    bc::decode_bc1_block(block)[(y % 4 * 4 + x % 4) as usize],
+                    "{width}x{height} ({x}, {y})"
+                );
+            }
+
+            // A missing block is an error, even if its pixels would be clipped.
+            assert!(matches!(
+                decode(&file[..file.len() - 8]),
+                Err(ImageError::IoError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn bc2_and_bc3() {
+        let block = [0xFF; 16];
+        let file = dds_file(four_cc_pixel_format(b"DXT3"), 3, 2, &block);
+        let expected = bc::decode_bc2_block(block);
+        let image = decode(&file).unwrap();
+        assert_eq!(image[..12], *expected[..3].as_flattened());
+        assert_eq!(image[12..], *expected[4..7].as_flattened());
+
+        let file = dds_file(four_cc_pixel_format(b"DXT5"), 3, 2, &block);
+        let expected = bc::decode_bc3_block(block);
+        let image = decode(&file).unwrap();
+        assert_eq!(image[..12], *expected[..3].as_flattened());
+        assert_eq!(image[12..], *expected[4..7].as_flattened());
+    }
+
+    /// Sets the mip count of a file built by `dds_file`.
+    fn set_mipmap_count(file: &mut [u8], count: u32) {
+        // DDSD_MIPMAPCOUNT
+        file[10] |= 0x2;
+        file[28..32].copy_from_slice(&count.to_le_bytes());
+    }
+
+    #[test]
+    fn bc3_mipmaps() {
+        // Every pixel of level `i` has an alpha of `i + 1`.
+        let mut data = Vec::new();
+        for level in 0..9 {
+            let size = (256 >> level).max(4) / 4;
+            let alpha = level as u8 + 1;
+            let block = [alpha, alpha, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+            data.extend(block.repeat(size * size));
+        }
+        let mut file = dds_file(four_cc_pixel_format(b"DXT5"), 256, 256, &data);
+        set_mipmap_count(&mut file, 9);
+
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.mipmap_count(), 9);
+        assert_eq!(decoder.mipmap_dimensions(0), (256, 256));
+        assert_eq!(decoder.mipmap_dimensions(3), (32, 32));
+        assert_eq!(decoder.mipmap_dimensions(8), (1, 1));
+        assert_eq!(decoder.mipmap_dimensions(9), (1, 1));
+
+        let levels: Vec<_> = decoder.into_mipmaps().map(Result::unwrap).collect();
+        assert_eq!(levels.len(), 9);
+        for (i, level) in levels.iter().enumerate() {
+            assert_eq!(level.dimensions(), (256 >> i, 256 >> i));
+            assert!(level
+                .to_rgba8()
+                .pixels()
+                .all(|pixel| pixel[3] == i as u8 + 1));
+        }
+    }
+
+    #[test]
+    fn mipmaps_of_non_square_surfaces() {
+        // 3x1, 1x1
+        let mut file = dds_file(d3dfmt_pixel_format(32), 3, 1, &[7; 16]);
+        set_mipmap_count(&mut file, 2);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.mipmap_dimensions(1), (1, 1));
+        let levels: Vec<_> = decoder.into_mipmaps().map(Result::unwrap).collect();
+        assert_eq!(levels[0].dimensions(), (3, 1));
+        assert_eq!(levels[1].dimensions(), (1, 1));
+        assert_eq!(levels[1].as_bytes(), &[7; 4]);
+    }
+
+    #[test]
+    fn random_access() {
+        // A 10x6 BC3 surface of 3x2 random blocks
+        let data: Vec<u8> = (0..16 * 6).map(|i| (i * 73 % 256) as u8).collect();
+        let file = dds_file(four_cc_pixel_format(b"DXT5"), 10, 6, &data);
+        let image = DynamicImage::from_decoder(DdsDecoder::new(Cursor::new(&file)).unwrap());
+        let image = image.unwrap().into_rgba8();
+
+        let scan = |decoder: &mut RandomAccessDecoder| {
+            for (x, y, pixel) in image.enumerate_pixels() {
+                assert_eq!(decoder.pixel(x, y), pixel.0, "({x}, {y})");
+            }
+        };
+        let mut decoder = DdsDecoder::new(Cursor::new(&file))
+            .unwrap()
+            .into_random_access()
+            .unwrap();
+        assert_eq!(decoder.dimensions(), (10, 6));
+        // Each block is decoded once, and not even scanning again decodes it again.
+        scan(&mut decoder);
+        assert_eq!(decoder.decoded_blocks(), 6);
+        scan(&mut decoder);
+        assert_eq!(decoder.decoded_blocks(), 6);
+
+        // Without the cache, every pixel decodes its block.
+        decoder.set_cache_size(0);
+        scan(&mut decoder);
+        assert_eq!(decoder.decoded_blocks(), 6 + 60);
+        // With a single cached block, each row of pixels decodes the 3 blocks it goes through.
+        decoder.set_cache_size(1);
+        scan(&mut decoder);
+        assert_eq!(decoder.decoded_blocks(), 6 + 60 + 6 * 3);
+
+        let bc6h = dx10_file(95, 4, 4, &[0; 16]);
+        let rgba = dds_file(d3dfmt_pixel_format(32), 1, 1, &[0; 4]);
+        for file in [bc6h, rgba] {
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert!(matches!(
+                decoder.into_random_access(),
+                Err(ImageError::Unsupported(_))
+            ));
+        }
+        // truncated
+        let decoder = DdsDecoder::new(Cursor::new(&file[..file.len() - 1])).unwrap();
+        assert!(decoder.into_random_access().is_err());
+    }
+
+    #[test]
+    fn decode_with_progress() {
+        // A 12x12 BC1 file with 4 levels filled with random blocks
+        let data: Vec<u8> = (0..8 * (9 + 4 + 1 + 1))
+            .map(|i| (i * 73 % 256) as u8)
+            .collect();
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 12, 12, &data);
+        set_mipmap_count(&mut file, 4);
+
+        let expected: Vec<_> = DdsDecoder::new(Cursor::new(&file))
+            .unwrap()
+            .into_mipmaps()
+            .map(Result::unwrap)
+            .collect();
+        let mut calls = Vec::new();
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let images = decoder
+            .decode_with_progress(|progress| {
+                calls.push(progress);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(images, expected);
+        let calls: Vec<_> = calls
+            .iter()
+            .map(|p| (p.level, p.level_count, p.block_row, p.block_rows))
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                (0, 4, 1, 3),
+                (0, 4, 2, 3),
+                (0, 4, 3, 3),
+                (1, 4, 1, 2),
+                (1, 4, 2, 2),
+                (2, 4, 1, 1),
+                (3, 4, 1, 1)
+            ]
+        );
+
+        // Cancelling stops before the next row of blocks is read.
+        let mut reader = Cursor::new(&file);
+        let mut calls = 0;
+        let decoder = DdsDecoder::new(&mut reader).unwrap();
+        let result = decoder.decode_with_progress(|progress| {
+            calls += 1;
+            if progress.block_row == 2 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        let Err(ImageError::IoError(err)) = result else {
+            panic!("{result:?}");
+        };
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(
+            err.get_ref().unwrap().downcast_ref::<Cancelled>(),
+            Some(&Cancelled)
+        );
+        assert_eq!(calls, 2);
+        assert_eq!(reader.position(), 128 + 2 * 3 * 8);
+
+        // Cancelling after the last row of a level only fails if another level follows.
+        let cancel_after_level = |cancelled_level| {
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.decode_with_progress(|progress| {
+                if progress.level == cancelled_level && progress.block_row == progress.block_rows {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+        };
+        assert_eq!(cancel_after_level(3).unwrap(), expected);
+        for level in 0..3 {
+            let Err(ImageError::IoError(err)) = cancel_after_level(level) else {
+                panic!("level {level} wasn't cancelled");
+            };
+            assert!(err.get_ref().unwrap().is::<Cancelled>());
+        }
+    }
+
+    #[test]
+    fn read_mipmap_into_buffer() {
+        // A 12x12 BC1 file with 4 levels filled with random blocks
+        let data: Vec<u8> = (0..8 * (9 + 4 + 1 + 1))
+            .map(|i| (i * 73 % 256) as u8)
+            .collect();
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 12, 12, &data);
+        set_mipmap_count(&mut file, 4);
+
+        let expected: Vec<_> = DdsDecoder::new(Cursor::new(&file))
+            .unwrap()
+            .into_mipmaps()
+            .map(|level| level.unwrap().into_bytes())
+            .collect();
+
+        // The same buffer is reused for all levels, and they can be read in any order.
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let mut buf = vec![0; 12 * 12 * 4];
+        for level in [2, 0, 3, 1] {
+            let (width, height) = decoder.mipmap_dimensions(level);
+            let buf = &mut buf[..(width * height * 4) as usize];
+            decoder.read_mipmap_into(level, buf).unwrap();
+            assert_eq!(buf, expected[level as usize], "{level}");
+        }
+
+        assert!(matches!(
+            decoder.read_mipmap_into(0, &mut buf[..12 * 12 * 4 - 1]),
+            Err(ImageError::Parameter(_))
+        ));
+        assert!(matches!(
+            decoder.read_mipmap_into(4, &mut buf[..4]),
+            Err(ImageError::Parameter(_))
+        ));
+        // The reader is still at the main surface.
+        decoder.read_image(&mut buf).unwrap();
+        assert_eq!(buf, expected[0]);
+    }
+
+    #[test]
+    fn decode_closest_mip() {
+        // A 12x12 BC1 file with 4 levels filled with random blocks
+        let data: Vec<u8> = (0..8 * (9 + 4 + 1 + 1))
+            .map(|i| (i * 73 % 256) as u8)
+            .collect();
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 12, 12, &data);
+        set_mipmap_count(&mut file, 4);
+
+        let expected: Vec<_> = DdsDecoder::new(Cursor::new(&file))
+            .unwrap()
+            .into_mipmaps()
+            .map(Result::unwrap)
+            .collect();
+
+        // The levels are 12x12, 6x6, 3x3 and 1x1.
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        for (max_dim, level) in [
+            (u32::MAX, 0),
+            (12, 0),
+            (11, 1),
+            (6, 1),
+            (5, 2),
+            (3, 2),
+            (2, 3),
+            (1, 3),
+            (0, 3),
+        ] {
+            assert_eq!(decoder.closest_mip_level(max_dim), level, "{max_dim}");
+            let image = decoder.decode_closest_mip(max_dim).unwrap();
+            assert_eq!(image, expected[level as usize], "{max_dim}");
+        }
+        // The reader is still at the main surface.
+        let mut buf = vec![0; 12 * 12 * 4];
+        decoder.read_image(&mut buf).unwrap();
+        assert_eq!(buf, expected[0].as_bytes());
+
+        // Both dimensions have to fit.
+        let mut file = dds_file(d3dfmt_pixel_format(32), 8, 2, &[7; 4 * (16 + 4 + 2 + 1)]);
+        set_mipmap_count(&mut file, 4);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.closest_mip_level(4), 1);
+        assert_eq!(decoder.closest_mip_level(2), 2);
+
+        // Without mip levels, the main surface is always used.
+        let file = dds_file(four_cc_pixel_format(b"DXT1"), 12, 12, &data[..8 * 9]);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        for max_dim in [0, 1, 12, 100] {
+            assert_eq!(decoder.closest_mip_level(max_dim), 0);
+        }
+        assert_eq!(decoder.decode_closest_mip(1).unwrap(), expected[0]);
+    }
+
+    #[test]
+    fn decoded_lengths() {
+        let cases = [
+            (DxgiFormat::Bc1Unorm, 4, 4, 4, 64),
+            (DxgiFormat::Bc7UnormSrgb, 13, 7, 4, 13 * 7 * 4),
+            (DxgiFormat::Bc4Unorm, 5, 3, 1, 15),
+            (DxgiFormat::Bc5Snorm, 5, 3, 3, 45),
+            (DxgiFormat::Bc6hUf16, 8, 2, 3, 8 * 2 * 3 * 4),
+            (DxgiFormat::Bc6hSf16, 1, 1, 4, 16),
+            (DxgiFormat::R32G32B32A32Float, 3, 3, 4, 144),
+            (DxgiFormat::R16Unorm, 3, 2, 1, 12),
+            (DxgiFormat::R10G10B10A2Unorm, 2, 2, 4, 32),
+            (DxgiFormat::R9G9B9E5SharedExp, 2, 2, 3, 48),
+            (DxgiFormat::B8G8R8A8Unorm, 0, 9, 4, 0),
+            (DxgiFormat::Bc3Unorm, u32::MAX, u32::MAX, 4, usize::MAX),
+        ];
+        for (format, width, height, channels, len) in cases {
+            assert_eq!(
+                decoded_len(format, width, height, channels),
+                len,
+                "{format:?} {width}x{height}"
+            );
+        }
+
+        // The decoder agrees with the default settings and accounts for the others.
+        for (dxgi_format, channels) in [(71, 4), (80, 1), (84, 3), (95, 3)] {
+            let file = dx10_file(dxgi_format, 6, 5, &[0; 2 * 2 * 16]);
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            let len = decoded_len(decoder.format(), 6, 5, channels);
+            assert_eq!(decoder.output_size(), len, "{dxgi_format}");
+            assert_eq!(decoder.total_bytes(), len as u64);
+        }
+        // BC4 decoded to 16-bit RGBA
+        let file = dx10_file(80, 6, 5, &[0; 2 * 2 * 8]);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_gray_to_rgba(true);
+        decoder.set_high_precision(true);
+        assert_eq!(decoder.output_size(), 6 * 5 * 8);
+    }
+
+    #[test]
+    fn read_image_into_buffer() {
+        let data: Vec<u8> = (0..8 * 6).map(|i| (i * 73 % 256) as u8).collect();
+        let file = dds_file(four_cc_pixel_format(b"DXT1"), 10, 7, &data);
+        let expected = DynamicImage::from_decoder(DdsDecoder::new(Cursor::new(&file)).unwrap())
+            .unwrap()
+            .into_bytes();
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.output_size(), 10 * 7 * 4);
+        let mut buf = vec![0; decoder.output_size()];
+        decoder.read_image_into(&mut buf).unwrap();
+        assert_eq!(buf, expected);
+
+        // Larger buffers are fine, and the bytes after the image aren't touched.
+        let mut buf = vec![1; decoder.output_size() + 5];
+        decoder.read_image_into(&mut buf).unwrap();
+        assert_eq!(buf[..expected.len()], expected);
+        assert_eq!(buf[expected.len()..], [1; 5]);
+
+        assert!(matches!(
+            decoder.read_image_into(&mut buf[..expected.len() - 1]),
+            Err(ImageError::Parameter(_))
+        ));
+    }
+
+    #[test]
+    fn mipmaps_stop_after_error() {
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 8, 8, &[0; 8 * 5]);
+        set_mipmap_count(&mut file, 0);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.mipmap_count(), 1);
+        assert_eq!(decoder.into_mipmaps().count(), 1);
+
+        // The second level is missing.
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 8, 8, &[0; 8 * 4]);
+        set_mipmap_count(&mut file, 4);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let levels: Vec<_> = decoder.into_mipmaps().collect();
+        assert_eq!(levels.len(), 2);
+        assert!(levels[0].is_ok());
+        assert!(matches!(levels[1], Err(ImageError::IoError(_))));
+    }
+
+    /// Sets the `caps2` field of a file built by `dds_file`.
+    fn set_caps2(file: &mut [u8], caps2: u32) {
+        file[112..116].copy_from_slice(&caps2.to_le_bytes());
+    }
+
+    /// All faces of a cubemap.
+    const DDSCAPS2_CUBEMAP_ALLFACES: u32 = 0xFC00;
+
+    #[test]
+    fn cubemap() {
+        let colors = [
+            [255, 0, 0, 255],
+            [0, 255, 255, 255],
+            [0, 255, 0, 255],
+            [255, 0, 255, 255],
+            [0, 0, 255, 255],
+            [255, 255, 0, 255],
+        ];
+        let data: Vec<u8> = colors.iter().flat_map(|color| color.repeat(4)).collect();
+        let mut file = dds_file(d3dfmt_pixel_format(32), 2, 2, &data);
+        set_caps2(&mut file, DDSCAPS2_CUBEMAP | DDSCAPS2_CUBEMAP_ALLFACES);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert!(decoder.is_cubemap());
+        // Read the faces out of order to check that the position is restored.
+        for face in CubeFace::ALL.into_iter().rev() {
+            let image = decoder.read_cubemap_face(face).unwrap().unwrap();
+            assert_eq!(image.dimensions(), (2, 2));
+            assert!(image
+                .to_rgba8()
+                .pixels()
+                .all(|pixel| pixel.0 == colors[face as usize]));
+        }
+
+        let mut buf = vec![0; decoder.total_bytes() as usize];
+        decoder.read_image(&mut buf).unwrap();
+        assert_eq!(buf, colors[0].repeat(4));
+    }
+
+    #[test]
+    fn partial_cubemap_with_mipmaps() {
+        // +Y and -Z, each with a 4x4 and a 2x2 level
+        let data: Vec<u8> = [[1; 4], [2; 4], [3; 4], [4; 4]]
+            .iter()
+            .zip([16, 4, 16, 4])
+            .flat_map(|(color, pixels)| color.repeat(pixels))
+            .collect();
+        let mut file = dds_file(d3dfmt_pixel_format(32), 4, 4, &data);
+        set_mipmap_count(&mut file, 2);
+        set_caps2(&mut file, DDSCAPS2_CUBEMAP | 0x1000 | 0x8000);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        for face in CubeFace::ALL {
+            let image = decoder.read_cubemap_face(face).map(Result::unwrap);
+            match face {
+                CubeFace::PositiveY => assert_eq!(image.unwrap().as_bytes(), &[1; 64]),
+                CubeFace::NegativeZ => assert_eq!(image.unwrap().as_bytes(), &[3; 64]),
+                _ => assert!(image.is_none()),
+            }
+        }
+
+        // Not a cubemap
+        let mut file = dds_file(d3dfmt_pixel_format(32), 2, 2, &DATA);
+        set_caps2(&mut file, DDSCAPS2_CUBEMAP_ALLFACES);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert!(!decoder.is_cubemap());
+        assert!(decoder.read_cubemap_face(CubeFace::PositiveX).is_none());
+    }
+
+    #[test]
+    fn partial_cubemap_mipmaps() {
+        // -X and +Z, each with an 8x8, a 4x4, a 2x2, and a 1x1 level
+        let faces = [CubeFace::NegativeX, CubeFace::PositiveZ];
+        let colors: [[u16; 4]; 2] = [
+            [0xF800, 0x07E0, 0x001F, 0xFFFF],
+            [0x0841, 0x1082, 0x18C3, 0x2104],
+        ];
+        let mut data = Vec::new();
+        for face_colors in colors {
+            for (color, blocks) in face_colors.into_iter().zip([4, 1, 1, 1]) {
+                let [lo, hi] = color.to_le_bytes();
+                data.extend([lo, hi, 0, 0, 0, 0, 0, 0].repeat(blocks));
+            }
+        }
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 8, 8, &data);
+        set_mipmap_count(&mut file, 4);
+        set_caps2(&mut file, DDSCAPS2_CUBEMAP | 0x800 | 0x4000);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.cubemap_faces().collect::<Vec<_>>(), faces);
+        assert!(!decoder.has_cubemap_face(CubeFace::PositiveX));
+
+        for (face, face_colors) in faces.into_iter().zip(colors).rev() {
+            for level in (0..4).rev() {
+                let image = decoder
+                    .read_cubemap_face_mipmap(face, level)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(image.dimensions(), (8 >> level, 8 >> level));
+                let [lo, hi] = face_colors[level as usize].to_le_bytes();
+                let expected = bc::decode_bc1_block([lo, hi, 0, 0, 0, 0, 0, 0])[0];
+                assert!(
+                    image.to_rgba8().pixels().all(|pixel| pixel.0 == expected),
+                    "{face:?} {level}"
+                );
+            }
+            assert!(decoder.read_cubemap_face_mipmap(face, 4).is_none());
+        }
+        assert!(decoder
+            .read_cubemap_face_mipmap(CubeFace::PositiveY, 0)
+            .is_none());
+    }
+
+    #[test]
+    fn truncated_cubemap() {
+        let mut file = dds_file(d3dfmt_pixel_format(32), 2, 2, &[0; 16 * 5]);
+        set_caps2(&mut file, DDSCAPS2_CUBEMAP | DDSCAPS2_CUBEMAP_ALLFACES);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert!(decoder
+            .read_cubemap_face(CubeFace::NegativeZ)
+            .unwrap()
+            .is_err());
+        assert!(decoder
+            .read_cubemap_face(CubeFace::PositiveZ)
+            .unwrap()
+            .is_ok());
+    }
+
+    /// Builds a DDS file with a DX10 header for a 2D texture.
+    fn dx10_file(dxgi_format: u32, width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+        let dx10 = Dx10Header {
+            dxgi_format,
+            resource_dimension: 3,
+            misc_flag: 0,
+            array_size: 1,
+            alpha_mode: 0,
+        };
+        dx10_file_with_header(dx10, width, height, data)
+    }
+
+    fn dx10_file_with_header(dx10: Dx10Header, width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for value in [
+            dx10.dxgi_format,
+            dx10.resource_dimension,
+            dx10.misc_flag,
+            dx10.array_size,
+            dx10.alpha_mode,
+        ] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(data);
+        dds_file(four_cc_pixel_format(b"DX10"), width, height, &bytes)
+    }
+
+    #[test]
+    fn dx10_header() {
+        let file = dds_file(d3dfmt_pixel_format(32), 2, 2, &DATA);
+        assert_eq!(
+            DdsDecoder::new(Cursor::new(&file)).unwrap().dx10_header(),
+            None
+        );
+
+        // R8G8B8A8_UNORM_SRGB
+        let file = dx10_file(29, 2, 2, &DATA);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(
+            decoder.dx10_header(),
+            Some(&Dx10Header {
+                dxgi_format: 29,
+                resource_dimension: 3,
+                misc_flag: 0,
+                array_size: 1,
+                alpha_mode: 0,
+            })
+        );
+        assert_eq!(decode(&file).unwrap(), DATA);
+
+        // BC1_UNORM and BC1_UNORM_SRGB
+        let block = solid_bc1_blocks(1);
+        let expected = bc::decode_bc1_block(block[..].try_into().unwrap());
+        for dxgi_format in [71, 72] {
+            let file = dx10_file(dxgi_format, 4, 4, &block);
+            assert_eq!(decode(&file).unwrap(), expected.as_flattened());
+        }
+
+        // B8G8R8X8_UNORM
+        let file = dx10_file(88, 2, 2, &DATA);
+        assert_eq!(decode(&file).unwrap()[..4], [3, 2, 1, 255]);
+
+        let file = dx10_file(0, 2, 2, &DATA);
+        assert!(matches!(decode(&file), Err(ImageError::Unsupported(_))));
+        let file = dx10_file(28, 2, 2, &DATA);
+        assert!(matches!(decode(&file[..140]), Err(ImageError::IoError(_))));
+    }
+
+    #[test]
+    fn read_header() {
+        use crate::codecs::dds::{read_header, DdsHeader, DdsPixelFormat};
+
+        let bc1 = DdsPixelFormat {
+            flags: DDPF_FOURCC,
+            four_cc: *b"DXT1",
+            rgb_bit_count: 0,
+            r_bit_mask: 0,
+            g_bit_mask: 0,
+            b_bit_mask: 0,
+            a_bit_mask: 0,
+        };
+        let expected = DdsHeader {
+            width: 8,
+            height: 4,
+            depth: 0,
+            mipmap_count: 0,
+            flags: 0x1007,
+            pixel_format: bc1,
+            caps: 0x1000,
+            caps2: 0,
+            dx10: None,
+        };
+        let file = dds_file(four_cc_pixel_format(b"DXT1"), 8, 4, &solid_bc1_blocks(2));
+        let mut reader = Cursor::new(&file);
+        assert_eq!(read_header(&mut reader).unwrap(), expected);
+        // The reader is left at the start of the surface data.
+        assert_eq!(reader.position(), 128);
+
+        let mut file = dds_file(four_cc_pixel_format(b"DXT5"), 16, 16, &[]);
+        set_mipmap_count(&mut file, 5);
+        let header = read_header(&mut Cursor::new(&file)).unwrap();
+        assert_eq!(header.pixel_format.four_cc, *b"DXT5");
+        assert_eq!((header.width, header.height), (16, 16));
+        assert_eq!(header.mipmap_count, 5);
+        assert_eq!(header.flags, 0x2_1007);
+
+        // BC7_UNORM. The format doesn't have to be supported.
+        for dxgi_format in [98, 0] {
+            let file = dx10_file(dxgi_format, 4, 4, &[]);
+            let mut reader = Cursor::new(&file);
+            let header = read_header(&mut reader).unwrap();
+            assert_eq!(header.pixel_format.four_cc, *b"DX10");
+            assert_eq!(header.dx10.unwrap().dxgi_format, dxgi_format);
+            assert_eq!(reader.position(), 148);
+        }
+
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 4, 4, &[]);
+        file[4] = 100;
+        assert!(matches!(
+            read_header(&mut Cursor::new(&file)),
+            Err(ImageError::Decoding(_))
+        ));
+        file[..4].copy_from_slice(b"DDS_");
+        assert!(matches!(
+            read_header(&mut Cursor::new(&file)),
+            Err(ImageError::Decoding(_))
+        ));
+    }
+
+    #[test]
+    fn dx10_block_formats() {
+        /// Decodes a 3x2 surface made of `block`, and compares it to the decoded block.
+        fn check<T: bytemuck::Pod>(
+            dxgi_format: u32,
+            color_type: ColorType,
+            block: &[u8],
+            decoded_block: [T; 16],
+        ) {
+            let file = dx10_file(dxgi_format, 3, 2, block);
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.color_type(), color_type, "{dxgi_format}");
+
+            let expected = [&decoded_block[..3], &decoded_block[4..7]].concat();
+            let expected: &[u8] = bytemuck::cast_slice(&expected);
+            assert_eq!(decode(&file).unwrap(), expected, "{dxgi_format}");
+        }
+
+        let block: [u8; 16] = std::array::from_fn(|i| (i * 37 + 11) as u8);
+        let bc4_block: [u8; 8] = block[..8].try_into().unwrap();
+
+        let decoded = bc::decode_bc4_unsigned_block(bc4_block);
+        check(80, ColorType::L8, &bc4_block, decoded);
+
+        let decoded = bc::decode_bc4_signed_block(bc4_block);
+        check(81, ColorType::L8, &bc4_block, decoded);
+        check(
+            83,
+            ColorType::Rgb8,
+            &block,
+            bc::decode_bc5_unsigned_block(block),
+        );
+        check(
+            84,
+            ColorType::Rgb8,
+            &block,
+            bc::decode_bc5_signed_block(block),
+        );
+        let decoded = bc::decode_bc6h_unsigned_block_f32(block);
+        check(95, ColorType::Rgb32F, &block, decoded);
+        let decoded = bc::decode_bc6h_signed_block_f32(block);
+        check(96, ColorType::Rgb32F, &block, decoded);
+        check(98, ColorType::Rgba8, &block, bc::decode_bc7_block(block));
+        check(99, ColorType::Rgba8, &block, bc::decode_bc7_block(block));
+    }
+
+    #[test]
+    fn dx10_cubemap() {
+        let data: Vec<u8> = (1..=6_u8).flat_map(|face| [face; 16]).collect();
+        let dx10 = Dx10Header {
+            dxgi_format: 28,
+            resource_dimension: 3,
+            misc_flag: D3D10_RESOURCE_MISC_TEXTURECUBE,
+            array_size: 1,
+            alpha_mode: 0,
+        };
+        let file = dx10_file_with_header(dx10, 2, 2, &data);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert!(decoder.is_cubemap());
+        for face in CubeFace::ALL {
+            let image = decoder.read_cubemap_face(face).unwrap().unwrap();
+            assert_eq!(image.as_bytes(), [face as u8 + 1; 16]);
+        }
+    }
+
+    #[test]
+    fn srgb_handling() {
+        let data = [0, 64, 128, 128, 188, 255, 10, 64];
+        let decode_with = |dxgi_format: u32, srgb_handling: SrgbHandling| {
+            let file = dx10_file(dxgi_format, 2, 1, &data);
+            let mut decoder = DdsDecoder::new(Cursor::new(file)).unwrap();
+            decoder.set_srgb_handling(srgb_handling);
+            DynamicImage::from_decoder(decoder).unwrap()
+        };
+
+        // R8G8B8A8_UNORM_SRGB
+        let image = decode_with(29, SrgbHandling::KeepEncoded);
+        assert_eq!(image.as_bytes(), data);
+        let image = decode_with(29, SrgbHandling::ToLinear8);
+        assert_eq!(image.as_bytes(), [0, 13, 55, 128, 128, 255, 1, 64]);
+        let image = decode_with(29, SrgbHandling::ToLinearF32);
+        let pixels = image.as_rgba32f().unwrap().as_raw();
+        let expected = [0.0, 0.051_269, 0.215_861, 128.0 / 255.0];
+        for (actual, expected) in pixels[..4].iter().zip(expected) {
+            assert!((actual - expected).abs() < 2e-6, "{actual} {expected}");
+        }
+        assert_eq!(pixels[5], 1.0);
+        assert_eq!(pixels[7], 64.0 / 255.0);
+
+        // B8G8R8A8_UNORM_SRGB is swizzled before it's converted.
+        let image = decode_with(91, SrgbHandling::ToLinear8);
+        assert_eq!(image.as_bytes(), [55, 13, 0, 128, 1, 255, 128, 64]);
+
+        // R8G8B8A8_UNORM isn't affected.
+        for srgb_handling in [SrgbHandling::ToLinear8, SrgbHandling::ToLinearF32] {
+            let image = decode_with(28, srgb_handling);
+            assert_eq!(image.as_bytes(), data);
+        }
+    }
+
+    #[test]
+    fn texture_array() {
+        // 4 elements, each with a 2x2 and a 1x1 level
+        let colors = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [9, 8, 7, 6],
+        ];
+        let data: Vec<u8> = colors
+            .iter()
+            .flat_map(|color| color.repeat(4 + 1))
+            .collect();
+        let dx10 = Dx10Header {
+            dxgi_format: 28,
+            resource_dimension: 3,
+            misc_flag: 0,
+            array_size: 4,
+            alpha_mode: 0,
+        };
+        let mut file = dx10_file_with_header(dx10, 2, 2, &data);
+        set_mipmap_count(&mut file, 2);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.array_len(), 4);
+        for index in [3, 1, 0, 2] {
+            let image = decoder.read_array_slice(index).unwrap().unwrap();
+            assert_eq!(image.as_bytes(), colors[index as usize].repeat(4));
+        }
+        assert!(decoder.read_array_slice(4).is_none());
+
+        let file = dx10_file(28, 2, 2, &DATA);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.array_len(), 1);
+        assert_eq!(
+            decoder.read_array_slice(0).unwrap().unwrap().as_bytes(),
+            DATA
+        );
+        assert!(decoder.read_array_slice(1).is_none());
+    }
+
+    #[test]
+    fn texture_array_mipmaps() {
+        // 3 elements, each with a 4x2, a 2x1, and a 1x1 level. Every pixel is
+        // `[element, level, 0, 0]`.
+        let mut data = Vec::new();
+        for element in 0..3 {
+            for (level, pixels) in [8, 2, 1].into_iter().enumerate() {
+                data.extend([element, level as u8, 0, 0].repeat(pixels));
+            }
+        }
+        let dx10 = Dx10Header {
+            dxgi_format: 28,
+            resource_dimension: 3,
+            misc_flag: 0,
+            array_size: 3,
+            alpha_mode: 0,
+        };
+        let mut file = dx10_file_with_header(dx10, 4, 2, &data);
+        set_mipmap_count(&mut file, 3);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        for element in (0..3).rev() {
+            for level in 0..3 {
+                let image = decoder
+                    .read_array_slice_mipmap(element, level)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(image.dimensions(), decoder.mipmap_dimensions(level));
+                assert!(image
+                    .to_rgba8()
+                    .pixels()
+                    .all(|pixel| pixel.0 == [element as u8, level as u8, 0, 0]));
+            }
+            assert!(decoder.read_array_slice_mipmap(element, 3).is_none());
+        }
+        assert!(decoder.read_array_slice_mipmap(3, 0).is_none());
+        assert!(decoder
+            .read_array_cubemap_face(0, CubeFace::PositiveX, 0)
+            .is_none());
+    }
+
+    #[test]
+    fn cubemap_array() {
+        // 2 cubes, each face with a 2x2 and a 1x1 level. Every pixel is
+        // `[cube, face, level, 0]`.
+        let mut data = Vec::new();
+        for cube in 0..2 {
+            for face in 0..6 {
+                for (level, pixels) in [4, 1].into_iter().enumerate() {
+                    data.extend([cube, face, level as u8, 0].repeat(pixels));
+                }
+            }
+        }
+        let dx10 = Dx10Header {
+            dxgi_format: 28,
+            resource_dimension: 3,
+            misc_flag: D3D10_RESOURCE_MISC_TEXTURECUBE,
+            array_size: 2,
+            alpha_mode: 0,
+        };
+        let mut file = dx10_file_with_header(dx10, 2, 2, &data);
+        set_mipmap_count(&mut file, 2);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.array_len(), 2);
+        for cube in 0..2 {
+            for face in CubeFace::ALL {
+                for level in 0..2 {
+                    let image = decoder
+                        .read_array_cubemap_face(cube, face, level)
+                        .unwrap()
+                        .unwrap();
+                    let expected = [cube as u8, face as u8, level as u8, 0];
+                    assert!(
+                        image.to_rgba8().pixels().all(|pixel| pixel.0 == expected),
+                        "{cube} {face:?} {level}"
+                    );
+                }
+            }
+        }
+        assert!(decoder
+            .read_array_cubemap_face(2, CubeFace::PositiveX, 0)
+            .is_none());
+
+        // Array slices of a cubemap array are the first face of each cube.
+        let image = decoder.read_array_slice_mipmap(1, 1).unwrap().unwrap();
+        assert_eq!(image.as_bytes(), [1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn raw_surface() {
+        // 2 elements of BC1_UNORM, each with a 10x6, a 5x3, and a 2x1 level. Every byte of a block
+        // is `element * 0x10 + level`.
+        let mut data = Vec::new();
+        for element in 0..2 {
+            for (level, blocks) in [6, 2, 1].into_iter().enumerate() {
+                data.extend(vec![element * 0x10 + level as u8; blocks * 8]);
+            }
+        }
+        let dx10 = Dx10Header {
+            dxgi_format: 71,
+            resource_dimension: 3,
+            misc_flag: 0,
+            array_size: 2,
+            alpha_mode: 0,
+        };
+        let mut file = dx10_file_with_header(dx10, 10, 6, &data);
+        set_mipmap_count(&mut file, 3);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        for element in 0..2 {
+            for (level, (width, height, blocks_x, blocks_y)) in
+                [(10, 6, 3, 2), (5, 3, 2, 1), (2, 1, 1, 1)]
+                    .into_iter()
+                    .enumerate()
+            {
+                let surface = decoder
+                    .read_raw_surface(element, None, level as u32)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!((surface.width, surface.height), (width, height));
+                assert_eq!(surface.format, DxgiFormat::Bc1Unorm);
+                assert_eq!((surface.blocks_x, surface.blocks_y), (blocks_x, blocks_y));
+                assert_eq!(surface.bytes_per_block, 8);
+                assert_eq!(surface.row_pitch, u64::from(blocks_x) * 8);
+                assert_eq!(
+                    surface.data.len() as u32,
+                    surface.blocks_x * surface.blocks_y * surface.bytes_per_block
+                );
+                let expected = element as u8 * 0x10 + level as u8;
+                assert!(surface.data.iter().all(|&byte| byte == expected));
+            }
+        }
+        assert!(decoder.read_raw_surface(2, None, 0).is_none());
+        assert!(decoder.read_raw_surface(0, None, 3).is_none());
+        assert!(decoder
+            .read_raw_surface(0, Some(CubeFace::PositiveX), 0)
+            .is_none());
+        // The raw surfaces don't change what is decoded.
+        assert_eq!(decoder.dimensions(), (10, 6));
+        let mut buf = vec![0; decoder.total_bytes() as usize];
+        decoder.read_image(&mut buf).unwrap();
+
+        // Faces of a cubemap with uncompressed 2x1 pixels, where every pixel is `[face, 0, 0, 0]`
+        let data: Vec<u8> = (0..6).flat_map(|face| [face, 0, 0, 0].repeat(2)).collect();
+        let dx10 = Dx10Header {
+            dxgi_format: 28,
+            resource_dimension: 3,
+            misc_flag: D3D10_RESOURCE_MISC_TEXTURECUBE,
+            array_size: 1,
+            alpha_mode: 0,
+        };
+        let file = dx10_file_with_header(dx10, 2, 1, &data);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        for face in CubeFace::ALL {
+            let surface = decoder.read_raw_surface(0, Some(face), 0).unwrap().unwrap();
+            assert_eq!((surface.blocks_x, surface.blocks_y), (2, 1));
+            assert_eq!(surface.bytes_per_block, 4);
+            assert_eq!(surface.data, [face as u8, 0, 0, 0].repeat(2));
+        }
+        let first = decoder.read_raw_surface(0, None, 0).unwrap().unwrap();
+        assert_eq!(first.data, [0; 8]);
+
+        // truncated
+        let file = &file[..file.len() - 1];
+        let mut decoder = DdsDecoder::new(Cursor::new(file)).unwrap();
+        assert!(decoder
+            .read_raw_surface(0, Some(CubeFace::NegativeZ), 0)
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn analyze_bc1() {
+        let block = |color0: u16, color1: u16, indexes: u32| {
+            let mut block = [0; 8];
+            block[..2].copy_from_slice(&color0.to_le_bytes());
+            block[2..4].copy_from_slice(&color1.to_le_bytes());
+            block[4..].copy_from_slice(&indexes.to_le_bytes());
+            block
+        };
+        // A 12x8 surface with 3x2 blocks: 3 4-color blocks, and 3 3-color blocks, of which 2 have
+        // transparent pixels. Equal endpoints are the 3-color mode.
+        let blocks = [
+            block(0xF800, 0x001F, 0),
+            block(0xF800, 0x001F, 0xFFFF_FFFF),
+            block(0x07E0, 0x0000, 0xE4E4_E4E4),
+            block(0x001F, 0xF800, 0x5555_5555),
+            block(0x001F, 0xF800, 0x0000_00C0),
+            block(0x1234, 0x1234, 0xAAAA_AAAB),
+        ];
+        let file = dds_file(four_cc_pixel_format(b"DXT1"), 12, 8, blocks.as_flattened());
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(
+            decoder.analyze_bc1().unwrap(),
+            Bc1Stats {
+                four_color_blocks: 3,
+                three_color_blocks: 3,
+                transparent_blocks: 2,
+                four_color_indexes: [16 + 4, 4, 4, 16 + 4],
+                three_color_indexes: [15, 16, 15, 1 + 1],
+            }
+        );
+        // The reader is still at the main surface.
+        let mut buf = vec![0; 12 * 8 * 4];
+        decoder.read_image(&mut buf).unwrap();
+
+        let file = dds_file(four_cc_pixel_format(b"DXT5"), 4, 4, &[0; 16]);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert!(matches!(
+            decoder.analyze_bc1(),
+            Err(ImageError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn subresource_layout() {
+        // A BC1 cubemap array of 2 elements with a 8x4, a 4x2, and a 2x1 level
+        let dx10 = Dx10Header {
+            dxgi_format: 71,
+            resource_dimension: 3,
+            misc_flag: D3D10_RESOURCE_MISC_TEXTURECUBE,
+            array_size: 2,
+            alpha_mode: 0,
+        };
+        let data: Vec<u8> = (0..2 * 6 * (2 + 1 + 1) * 8)
+            .map(|i| (i * 73 % 256) as u8)
+            .collect();
+        let mut file = dx10_file_with_header(dx10, 8, 4, &data);
+        set_mipmap_count(&mut file, 3);
+
+        // The subresources are stored one after another, starting after the headers and ending
+        // at the end of the file.
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let mut offset = 4 + 124 + 20;
+        for index in 0..2 {
+            for face in CubeFace::ALL {
+                for (level, (width, height, blocks)) in
+                    [(8, 4, 2), (4, 2, 1), (2, 1, 1)].into_iter().enumerate()
+                {
+                    let layout = decoder
+                        .subresource_layout(index, Some(face), level as u32)
+                        .unwrap();
+                    assert_eq!(
+                        layout,
+                        SubresourceInfo {
+                            offset,
+                            size: blocks * 8,
+                            row_pitch: blocks * 8,
+                            width,
+                            height,
+                            depth: 1,
+                        }
+                    );
+                    let start = offset as usize;
+                    let raw = decoder
+                        .read_raw_surface(index, Some(face), level as u32)
+                        .unwrap()
+                        .unwrap();
+                    assert_eq!(raw.data, file[start..start + layout.size as usize]);
+                    offset += layout.size;
+                }
+            }
+        }
+        assert_eq!(offset, file.len() as u64);
+        assert_eq!(
+            decoder.subresource_layout(1, None, 1),
+            decoder.subresource_layout(1, Some(CubeFace::PositiveX), 1)
+        );
+        assert_eq!(decoder.subresource_layout(2, None, 0), None);
+        assert_eq!(decoder.subresource_layout(0, None, 3), None);
+
+        // A 4x2x4 RGBA volume with padded rows, and a 2x1x2 and a 1x1x1 level. Only the main
+        // surface is padded.
+        let mut file = dds_file(d3dfmt_pixel_format(32), 4, 2, &[0; 20 * 2 * 4 + 8 * 2 + 4]);
+        set_caps2(&mut file, DDSCAPS2_VOLUME);
+        file[24..28].copy_from_slice(&4_u32.to_le_bytes()); // depth
+        set_mipmap_count(&mut file, 3);
+        set_pitch(&mut file, 20);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let layouts: Vec<_> = (0..3)
+            .map(|level| decoder.subresource_layout(0, None, level).unwrap())
+            .map(|layout| (layout.offset, layout.size, layout.row_pitch, layout.depth))
+            .collect();
+        assert_eq!(
+            layouts,
+            [
+                (128, 160, 20, 4),
+                (128 + 160, 16, 8, 2),
+                (128 + 176, 4, 4, 1)
+            ]
+        );
+        assert_eq!(
+            decoder.subresource_layout(0, Some(CubeFace::PositiveX), 0),
+            None
+        );
+    }
+
+    #[test]
+    fn raw_surface_matches_decoding() {
+        // A 12x12 BC1 file with 4 levels filled with random blocks
+        let data: Vec<u8> = (0..8 * (9 + 4 + 1 + 1))
+            .map(|i| (i * 73 % 256) as u8)
+            .collect();
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 12, 12, &data);
+        set_mipmap_count(&mut file, 4);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let raw: Vec<_> = (0..4)
+            .map(|level| decoder.read_raw_surface(0, None, level).unwrap().unwrap())
+            .collect();
+        // The levels are stored one after another without gaps.
+        assert_eq!(
+            raw.iter().flat_map(|s| s.data.clone()).collect::<Vec<_>>(),
+            data
+        );
+
+        // Decoding the blocks of each raw level gives the decoded level.
+        for (surface, expected) in raw.iter().zip(decoder.into_mipmaps()) {
+            let expected = expected.unwrap();
+            assert_eq!(surface.format, DxgiFormat::Bc1Unorm);
+            assert_eq!(expected.dimensions(), (surface.width, surface.height));
+            let blocks: Vec<_> = surface
+                .data
+                .as_chunks::<8>()
+                .0
+                .iter()
+                .map(|&block| bc::decode_bc1_block(block))
+                .collect();
+            let width = surface.width as usize;
+            let decoded: Vec<u8> = (0..surface.height as usize)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .flat_map(|(x, y)| {
+                    let block = &blocks[y / 4 * surface.blocks_x as usize + x / 4];
+                    block[y % 4 * 4 + x % 4]
+                })
+                .collect();
+            assert_eq!(decoded, expected.as_bytes());
+        }
+    }
+
+    #[test]
+    fn metadata() {
+        // Only the headers of a cubemap array, without any surface data
+        let dx10 = Dx10Header {
+            dxgi_format: 71,
+            resource_dimension: 3,
+            misc_flag: D3D10_RESOURCE_MISC_TEXTURECUBE,
+            array_size: 2,
+            alpha_mode: 0,
+        };
+        let mut file = dx10_file_with_header(dx10, 64, 32, &[]);
+        set_mipmap_count(&mut file, 3);
+        let headers_len = file.len() as u64;
+        file.extend(b"payload");
+
+        let mut reader = Cursor::new(&file);
+        let metadata = DdsMetadata::from_reader(&mut reader).unwrap();
+        assert_eq!(reader.position(), headers_len);
+        assert_eq!(
+            metadata,
+            DdsMetadata {
+                width: 64,
+                height: 32,
+                depth: 1,
+                mip_count: 3,
+                array_size: 2,
+                is_cubemap: true,
+                format: DxgiFormat::Bc1Unorm,
+                color_type: ColorType::Rgba8,
+                alpha_mode: AlphaMode::Unknown,
+                dx10_header: Some(dx10),
+            }
+        );
+
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.metadata(), metadata);
+
+        // Files without a DX10 header
+        let mut file = dds_file(four_cc_pixel_format(b"DXT5"), 16, 16, &[]);
+        set_caps2(&mut file, DDSCAPS2_VOLUME);
+        file[24..28].copy_from_slice(&4_u32.to_le_bytes()); // depth
+        let metadata = DdsMetadata::from_reader(&mut Cursor::new(&file)).unwrap();
+        assert_eq!((metadata.depth, metadata.mip_count), (4, 1));
+        assert_eq!((metadata.array_size, metadata.is_cubemap), (1, false));
+        assert_eq!(metadata.format, DxgiFormat::Bc3Unorm);
+        assert_eq!(metadata.dx10_header, None);
+    }
+
+    #[test]
+    fn format() {
+        for (dxgi_format, format) in [
+            (72, DxgiFormat::Bc1UnormSrgb),
+            (96, DxgiFormat::Bc6hSf16),
+            (115, DxgiFormat::B4G4R4A4Unorm),
+        ] {
+            let file = dx10_file(dxgi_format, 4, 4, &[0; 32]);
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.format(), format);
+            assert_eq!(decoder.is_srgb(), format.is_srgb());
+        }
+
+        let cases = [
+            (d3dfmt_pixel_format(21), DxgiFormat::B8G8R8A8Unorm),
+            (d3dfmt_pixel_format(23), DxgiFormat::B5G6R5Unorm),
+            // No DXGI format has 24-bit pixels or an unused 16th bit.
+            (d3dfmt_pixel_format(20), DxgiFormat::Unknown),
+            (d3dfmt_pixel_format(24), DxgiFormat::Unknown),
+        ];
+        for (pixel_format, format) in cases {
+            let file = dds_file(pixel_format, 4, 4, &[0; 64]);
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.format(), format);
+        }
+    }
+
+    #[test]
+    fn legacy_fourccs() {
+        // Each FourCC has to decode like its DXGI format.
+        let cases: [(&[u8; 4], u32, ColorType); 11] = [
+            (b"DXT1", 71, ColorType::Rgba8),
+            (b"DXT2", 74, ColorType::Rgba8),
+            (b"DXT3", 74, ColorType::Rgba8),
+            (b"DXT4", 77, ColorType::Rgba8),
+            (b"DXT5", 77, ColorType::Rgba8),
+            (b"ATI1", 80, ColorType::L8),
+            (b"BC4U", 80, ColorType::L8),
+            (b"BC4S", 81, ColorType::L8),
+            (b"ATI2", 83, ColorType::Rgb8),
+            (b"BC5U", 83, ColorType::Rgb8),
+            (b"BC5S", 84, ColorType::Rgb8),
+        ];
+        let data: Vec<u8> = (0..16 * 4).map(|i| (i * 73 % 256) as u8).collect();
+        for (four_cc, dxgi_format, color_type) in cases {
+            let format = Format::from_dxgi_format(dxgi_format).unwrap();
+            let data = &data[..format.surface_size(8, 8) as usize];
+
+            let file = dds_file(four_cc_pixel_format(four_cc), 8, 8, data);
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.color_type(), color_type, "{four_cc:?}");
+            assert_eq!(decoder.format().to_u32(), dxgi_format, "{four_cc:?}");
+            let expected = decode(&dx10_file(dxgi_format, 8, 8, data)).unwrap();
+            assert_eq!(decode(&file).unwrap(), expected, "{four_cc:?}");
+        }
+    }
+
+    #[test]
+    fn bc4_grayscale() {
+        // A horizontal gradient, e.g. of a height map
+        let mut block = [240, 16, 0, 0, 0, 0, 0, 0];
+        let indexes = [0_u64, 3, 5, 7];
+        let bits = (0..16).fold(0, |bits, i| bits | indexes[i % 4] << (i * 3));
+        block[2..].copy_from_slice(&bits.to_le_bytes()[..6]);
+        let data = block.repeat(4);
+        let file = dx10_file(80, 16, 4, &data);
+
+        // BC4 surfaces decode to gray images with one byte per pixel, not RGBA.
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.total_bytes(), 16 * 4);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        let DynamicImage::ImageLuma8(gray) = image else {
+            panic!("{:?}", image.color());
+        };
+        let row = [240, 176, 112, 48].repeat(4);
+        assert_eq!(gray.as_raw()[..], row.repeat(4));
+    }
+
+    #[test]
+    fn bc4_gray_to_rgba() {
+        let block: [u8; 8] = std::array::from_fn(|i| (i * 37 + 11) as u8);
+        for dxgi_format in [80, 81] {
+            let file = dx10_file(dxgi_format, 5, 3, &block.repeat(2));
+            let gray = decode(&file).unwrap();
+
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_gray_to_rgba(true);
+            assert_eq!(decoder.color_type(), ColorType::Rgba8);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            let expected: Vec<_> = gray.iter().flat_map(|&x| [x, x, x, 255]).collect();
+            assert_eq!(image.as_bytes(), expected);
+
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_high_precision(true);
+            let gray = DynamicImage::from_decoder(decoder).unwrap().into_luma16();
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_high_precision(true);
+            decoder.set_gray_to_rgba(true);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            let expected: Vec<_> = gray.iter().flat_map(|&x| [x, x, x, 65535]).collect();
+            assert_eq!(image.as_rgba16().unwrap().as_raw(), &expected);
+        }
+
+        // Other formats aren't affected.
+        let file = dx10_file(83, 4, 4, &[0; 16]);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_gray_to_rgba(true);
+        assert_eq!(decoder.color_type(), ColorType::Rgb8);
+    }
+
+    #[test]
+    fn bc5_channels() {
+        let block: [u8; 16] = std::array::from_fn(|i| (i * 37 + 11) as u8);
+        let data = [block, block.map(|x| x ^ 0x5A)].concat();
+        for dxgi_format in [83, 84] {
+            let file = dx10_file(dxgi_format, 8, 3, &data);
+            let rgb = decode(&file).unwrap();
+            let red: Vec<_> = rgb.iter().step_by(3).copied().collect();
+            let green: Vec<_> = rgb.iter().skip(1).step_by(3).copied().collect();
+
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            // Reconstructing blue doesn't affect red and green.
+            decoder.set_reconstruct_normal_z(true);
+            let (r, g) = decoder.read_bc5_channels().unwrap();
+            assert_eq!(r.as_luma8().unwrap().as_raw(), &red);
+            assert_eq!(g.as_luma8().unwrap().as_raw(), &green);
+
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_high_precision(true);
+            let (r, g) = decoder.read_bc5_channels().unwrap();
+            assert_eq!((r.color(), g.color()), (ColorType::L16, ColorType::L16));
+            // The interpolated values are more precise, so they can round differently.
+            let r = r.to_luma8();
+            assert!(r
+                .as_raw()
+                .iter()
+                .zip(&red)
+                .all(|(a, b)| a.abs_diff(*b) <= 1));
+        }
+
+        let decoder = DdsDecoder::new(Cursor::new(dx10_file(80, 4, 4, &[0; 8]))).unwrap();
+        assert!(matches!(
+            decoder.read_bc5_channels(),
+            Err(ImageError::Parameter(_))
+        ));
+    }
+
+    #[test]
+    fn bc5_normal_maps() {
+        // x = 0.72, y = 0.0
+        let mut block = [0; 16];
+        block[..2].copy_from_slice(&[219, 219]);
+        block[8..10].copy_from_slice(&[128, 128]);
+        let file = dx10_file(83, 4, 4, &block);
+
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_bytes(), [219, 128, 0].repeat(16));
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_reconstruct_normal_z(true);
+        assert_eq!(decoder.color_type(), ColorType::Rgb8);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_bytes(), [219, 128, 216].repeat(16));
+
+        // BC5_SNORM with x = y = 0.0
+        block[..2].copy_from_slice(&[0, 0]);
+        block[8..10].copy_from_slice(&[0, 0]);
+        let file = dx10_file(84, 4, 4, &block);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_reconstruct_normal_z(true);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_bytes(), [128, 128, 255].repeat(16));
+
+        // Legacy files identify BC5 with a FourCC.
+        block[..2].copy_from_slice(&[219, 219]);
+        block[8..10].copy_from_slice(&[128, 128]);
+        for four_cc in [b"ATI2", b"BC5U"] {
+            let file = dds_file(four_cc_pixel_format(four_cc), 4, 4, &block);
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_reconstruct_normal_z(true);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            assert_eq!(image.as_bytes(), [219, 128, 216].repeat(16));
+        }
+    }
+
+    #[test]
+    fn high_precision() {
+        // A ramp from 0 to 1/255
+        let mut block = [1, 0, 0b1000_1000, 0b1100_0110, 0b1111_1010, 0, 0, 0];
+        let file = dx10_file(80, 4, 1, &block);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_high_precision(true);
+        assert_eq!(decoder.color_type(), ColorType::L16);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_luma16().unwrap().as_raw(), &[257, 0, 220, 184]);
+
+        let image = DynamicImage::from_decoder(DdsDecoder::new(Cursor::new(&file)).unwrap());
+        assert_eq!(image.unwrap().as_bytes(), [1, 0, 1, 1]);
+
+        // BC5 normal maps
+        block = [0; 8];
+        let file = dx10_file(84, 4, 4, &[block, block].concat());
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_high_precision(true);
+        decoder.set_reconstruct_normal_z(true);
+        assert_eq!(decoder.color_type(), ColorType::Rgb16);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(
+            image.as_rgb16().unwrap().as_raw(),
+            &[32768, 32768, 65535].repeat(16)
+        );
+
+        // Other formats aren't affected.
+        let file = dx10_file(28, 2, 2, &DATA);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_high_precision(true);
+        assert_eq!(decoder.color_type(), ColorType::Rgba8);
+    }
+
+    #[test]
+    fn bc1_punch_through() {
+        // blue to red in the 3-color mode, where the pixels use index 2 and 3 in turn
+        let block = [0x1F, 0x00, 0x00, 0xF8, 0xEE, 0xEE, 0xEE, 0xEE];
+        let file = dds_file(four_cc_pixel_format(b"DXT1"), 4, 4, &block);
+        for (mode, transparent) in [
+            (Bc1PunchThrough::Transparent, [0, 0, 0, 0]),
+            (Bc1PunchThrough::BlackOpaque, [0, 0, 0, 255]),
+        ] {
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_bc1_punch_through(mode);
+            assert_eq!(decoder.color_type(), ColorType::Rgba8);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            assert_eq!(
+                image.as_bytes(),
+                [[128, 0, 128, 255], transparent].as_flattened().repeat(8),
+                "{mode:?}"
+            );
+        }
+
+        // Blocks in the 4-color mode aren't affected.
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0xFF, 0xFF, 0xFF, 0xFF];
+        let file = dds_file(four_cc_pixel_format(b"DXT1"), 4, 4, &block);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_bc1_punch_through(Bc1PunchThrough::BlackOpaque);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_bytes(), [85, 0, 170, 255].repeat(16));
+    }
+
+    #[test]
+    fn swizzle() {
+        use SwizzleSource::{One, Zero, A, B, G, R};
+
+        // red with an alpha of 64 in every pixel
+        let block = [64, 64, 0, 0, 0, 0, 0, 0, 0x00, 0xF8, 0x1F, 0x00, 0, 0, 0, 0];
+        let file = dds_file(four_cc_pixel_format(b"DXT5"), 4, 4, &block);
+        let decode = |swizzle: Swizzle, alpha_handling: AlphaHandling| {
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            decoder.set_swizzle(swizzle);
+            decoder.set_alpha_handling(alpha_handling);
+            assert_eq!(decoder.color_type(), ColorType::Rgba8);
+            DynamicImage::from_decoder(decoder).unwrap().into_bytes()
+        };
+        for (swizzle, expected) in [
+            (Swizzle::IDENTITY, [255, 0, 0, 64]),
+            (Swizzle::BC3N, [64, 0, 0, 255]),
+            (Swizzle([B, G, R, A]), [0, 0, 255, 64]),
+            (Swizzle([Zero, One, R, R]), [0, 255, 255, 255]),
+        ] {
+            let pixels = decode(swizzle, AlphaHandling::Keep);
+            assert_eq!(pixels, expected.repeat(16), "{swizzle:?}");
+        }
+        // Alpha is premultiplied after swizzling.
+        let pixels = decode(Swizzle([R, R, R, A]), AlphaHandling::Premultiply);
+        assert_eq!(pixels, [64; 4].repeat(16));
+        let pixels = decode(Swizzle([A, A, A, R]), AlphaHandling::Premultiply);
+        assert_eq!(pixels, [64, 64, 64, 255].repeat(16));
+
+        // 16-bit channels use 65535 for one.
+        let values: Vec<u8> = [1u16, 2, 3, 4]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let file = dx10_file(11, 1, 1, &values);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_swizzle(Swizzle([A, B, G, One]));
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_rgba16().unwrap().as_raw(), &[4, 3, 2, 0xFFFF]);
+
+        // Surfaces that don't decode to RGBA aren't affected.
+        let file = dds_file(
+            four_cc_pixel_format(b"ATI1"),
+            4,
+            4,
+            &[200, 200, 0, 0, 0, 0, 0, 0],
+        );
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_swizzle(Swizzle([Zero; 4]));
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_bytes(), [200; 16]);
+    }
+
+    #[test]
+    fn block_decoder() {
+        fn check(file: &[u8], configure: fn(&mut DdsDecoder<Cursor<&[u8]>>)) {
+            let mut decoder = DdsDecoder::new(Cursor::new(file)).unwrap();
+            configure(&mut decoder);
+            let mut expected = vec![0; decoder.total_bytes() as usize];
+            decoder.read_image(&mut expected).unwrap();
+
+            let mut decoder = DdsDecoder::new(Cursor::new(file)).unwrap();
+            configure(&mut decoder);
+            let (width, height) = decoder.dimensions();
+            let row_len = width as usize * decoder.color_type().bytes_per_pixel() as usize;
+            let bands: Vec<_> = decoder.into_block_decoder().map(Result::unwrap).collect();
+            assert_eq!(bands.len(), height.div_ceil(4) as usize);
+            for band in &bands[..bands.len() - 1] {
+                assert_eq!(band.len(), row_len * 4);
+            }
+            assert_eq!(bands.concat(), expected);
+        }
+
+        let data: Vec<u8> = (0..16 * 3 * 4).map(|i| (i * 37 % 251) as u8).collect();
+        check(&dx10_file(98, 10, 9, &data[..16 * 9]), |_| {});
+        check(&dx10_file(99, 12, 12, &data[..16 * 9]), |decoder| {
+            decoder.set_srgb_handling(SrgbHandling::ToLinearF32);
+            decoder.set_alpha_handling(AlphaHandling::Premultiply);
+        });
+        check(&dx10_file(71, 9, 10, &data[..8 * 9]), |_| {});
+        check(&dx10_file(95, 12, 12, &data[..16 * 9]), |_| {});
+        check(&dx10_file(80, 24, 3, &data[..8 * 6]), |decoder| {
+            decoder.set_high_precision(true)
+        });
+
+        // The whole surface is large enough to be decoded in parallel with the rayon feature, but
+        // the bands are decoded one at a time.
+        let mut state = 0x2545_F491_4F6C_DD1D_u64;
+        let large: Vec<u8> = (0..128 * 128 * 16)
+            .map(|_| {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        check(&dx10_file(98, 510, 512, &large), |_| {});
+
+        // Uncompressed with padded rows
+        let mut file = dx10_file(87, 5, 7, &data[..24 * 7]);
+        set_pitch(&mut file, 24);
+        check(&file, |_| {});
+
+        // Truncated files return an error, then stop.
+        let file = dx10_file(71, 8, 8, &data[..8 * 3]);
+        let mut bands = DdsDecoder::new(Cursor::new(&file[..]))
+            .unwrap()
+            .into_block_decoder();
+        assert!(bands.next().unwrap().is_ok());
+        assert!(bands.next().unwrap().is_err());
+        assert!(bands.next().is_none());
+    }
+
+    #[test]
+    fn premultiplied_alpha() {
+        let decode_with = |file: &[u8], srgb_handling: SrgbHandling| {
+            let mut decoder = DdsDecoder::new(Cursor::new(file)).unwrap();
+            decoder.set_srgb_handling(srgb_handling);
+            decoder.set_alpha_handling(AlphaHandling::Premultiply);
+            DynamicImage::from_decoder(decoder).unwrap()
+        };
+
+        // BC3 with white, alpha 0 to 255 in 6 interpolated steps
+        let mut block = [0xFF; 16];
+        block[..8].copy_from_slice(&[255, 0, 0b1000_1000, 0b1100_0110, 0b1111_1010, 0, 0, 0]);
+        block[12..].fill(0);
+        let file = dds_file(four_cc_pixel_format(b"DXT5"), 4, 1, &block);
+        let expected: Vec<u8> = bc::decode_bc3_block(block)[..4]
+            .iter()
+            .flat_map(|&[_, _, _, a]| [a, a, a, a])
+            .collect();
+        let image = decode_with(&file, SrgbHandling::KeepEncoded);
+        assert_eq!(image.as_bytes(), expected);
+        assert!(expected[..4] != expected[4..8]);
+
+        // BC2 with 4-bit alpha and a non-white color
+        let mut block = [0; 16];
+        block[..2].copy_from_slice(&[0x21, 0x00]);
+        block[8..10].copy_from_slice(&0xF800_u16.to_le_bytes());
+        let file = dds_file(four_cc_pixel_format(b"DXT3"), 2, 1, &block);
+        // red, alpha 1 / 15 = 17 and 2 / 15 = 34
+        let image = decode_with(&file, SrgbHandling::KeepEncoded);
+        assert_eq!(image.as_bytes(), [17, 0, 0, 17, 34, 0, 0, 34]);
+
+        // sRGB surfaces are premultiplied after linearization.
+        let file = dx10_file(29, 2, 1, &[128, 255, 0, 128, 188, 188, 188, 0]);
+        let image = decode_with(&file, SrgbHandling::ToLinear8);
+        assert_eq!(image.as_bytes(), [28, 128, 0, 128, 0, 0, 0, 0]);
+        let image = decode_with(&file, SrgbHandling::ToLinearF32);
+        let pixels = image.as_rgba32f().unwrap().as_raw();
+        let alpha = 128.0 / 255.0;
+        assert!((pixels[0] - 0.215_861 * alpha).abs() < 1e-6);
+        assert_eq!(pixels[1..4], [alpha, 0.0, alpha]);
+        assert_eq!(pixels[4..], [0.0; 4]);
+
+        // Formats without alpha aren't affected.
+        let file = dx10_file(83, 4, 4, &[0x80; 16]);
+        let image = decode_with(&file, SrgbHandling::KeepEncoded);
+        assert_eq!(image.as_bytes(), [128, 128, 0].repeat(16));
+    }
+
+    #[test]
+    fn alpha_modes() {
+        let file_with_mode = |dxgi_format: u32, alpha_mode: u32, data: &[u8]| {
+            let dx10 = Dx10Header {
+                dxgi_format,
+                resource_dimension: 3,
+                misc_flag: 0,
+                array_size: 1,
+                alpha_mode,
+            };
+            dx10_file_with_header(dx10, data.len() as u32 / 4, 1, data)
+        };
+        let modes = [
+            AlphaMode::Unknown,
+            AlphaMode::Straight,
+            AlphaMode::Premultiplied,
+            AlphaMode::Opaque,
+            AlphaMode::Custom,
+            AlphaMode::Unknown,
+        ];
+        for (value, mode) in modes.into_iter().enumerate() {
+            let file = file_with_mode(28, value as u32, &[0; 4]);
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.alpha_mode(), mode, "{value}");
+            assert_eq!(decoder.metadata().alpha_mode, mode, "{value}");
+        }
+        for (four_cc, mode) in [
+            (b"DXT2", AlphaMode::Premultiplied),
+            (b"DXT3", AlphaMode::Unknown),
+            (b"DXT4", AlphaMode::Premultiplied),
+            (b"DXT5", AlphaMode::Unknown),
+        ] {
+            let file = dds_file(four_cc_pixel_format(four_cc), 4, 4, &[0; 16]);
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.alpha_mode(), mode);
+        }
+
+        let decode_with = |file: &[u8], alpha_handling, srgb_handling| {
+            let mut decoder = DdsDecoder::new(Cursor::new(file)).unwrap();
+            decoder.set_alpha_handling(alpha_handling);
+            decoder.set_srgb_handling(srgb_handling);
+            DynamicImage::from_decoder(decoder).unwrap()
+        };
+        let data = [64, 32, 0, 128, 0, 0, 0, 0, 200, 100, 50, 255, 100, 0, 0, 50];
+        let premultiplied = file_with_mode(28, 2, &data);
+        let image = decode_with(
+            &premultiplied,
+            AlphaHandling::Unpremultiply,
+            SrgbHandling::KeepEncoded,
+        );
+        // Colors above alpha are clamped, and pixels with alpha 0 are kept.
+        let straight = [
+            128, 64, 0, 128, 0, 0, 0, 0, 200, 100, 50, 255, 255, 0, 0, 50,
+        ];
+        assert_eq!(image.as_bytes(), straight);
+
+        // Surfaces that are already premultiplied or straight are kept as they are.
+        let image = decode_with(
+            &premultiplied,
+            AlphaHandling::Premultiply,
+            SrgbHandling::KeepEncoded,
+        );
+        assert_eq!(image.as_bytes(), data);
+        for alpha_mode in [0, 1] {
+            let file = file_with_mode(28, alpha_mode, &data);
+            let image = decode_with(
+                &file,
+                AlphaHandling::Unpremultiply,
+                SrgbHandling::KeepEncoded,
+            );
+            assert_eq!(image.as_bytes(), data);
+        }
+
+        // sRGB surfaces are unpremultiplied after linearization.
+        let file = file_with_mode(29, 2, &data[..8]);
+        let image = decode_with(
+            &file,
+            AlphaHandling::Unpremultiply,
+            SrgbHandling::ToLinearF32,
+        );
+        let pixels = image.as_rgba32f().unwrap().as_raw();
+        let alpha = 128.0 / 255.0;
+        assert!((pixels[0] - 0.051_269 / alpha).abs() < 1e-5, "{pixels:?}");
+        assert_eq!(pixels[2..], [0.0, alpha, 0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn dxt2_dxt4_are_premultiplied() {
+        // Alpha 128, and a dark gray that's premultiplied
+        let mut block = [0; 16];
+        block[..2].copy_from_slice(&[128, 128]);
+        block[8..10].copy_from_slice(&0x4208_u16.to_le_bytes());
+        block[10..12].copy_from_slice(&0x4208_u16.to_le_bytes());
+        let premultiplied = [bc::decode_bc2_block(block), bc::decode_bc3_block(block)];
+        // The BC2 alpha is 0, so its colors are kept.
+        for (four_cc, pixels) in [(b"DXT2", premultiplied[0]), (b"DXT4", premultiplied[1])] {
+            let file = dds_file(four_cc_pixel_format(four_cc), 4, 4, &block);
+            let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.metadata().alpha_mode, AlphaMode::Premultiplied);
+            decoder.set_alpha_handling(AlphaHandling::Unpremultiply);
+            let image = DynamicImage::from_decoder(decoder).unwrap();
+            let expected = pixels.map(unpremultiply_alpha);
+            assert_eq!(image.as_bytes(), expected.as_flattened(), "{four_cc:?}");
+        }
+        assert_eq!(premultiplied[1][0], [66, 65, 66, 128]);
+        assert_eq!(
+            unpremultiply_alpha(premultiplied[1][0]),
+            [131, 129, 131, 128]
+        );
+
+        // DXT5 is decoded as it is.
+        let file = dds_file(four_cc_pixel_format(b"DXT5"), 4, 4, &block);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        decoder.set_alpha_handling(AlphaHandling::Unpremultiply);
+        let image = DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(image.as_bytes(), premultiplied[1].as_flattened());
+    }
+
+    #[test]
+    fn invalid_dx10_headers() {
+        let valid = Dx10Header {
+            dxgi_format: 28,
+            resource_dimension: 3,
+            misc_flag: 0,
+            array_size: 1,
+            alpha_mode: 0,
+        };
+        for dx10 in [
+            Dx10Header {
+                array_size: 0,
+                ..valid
+            },
+            // D3D10_RESOURCE_DIMENSION_UNKNOWN
+            Dx10Header {
+                resource_dimension: 0,
+                ..valid
+            },
+            // D3D10_RESOURCE_DIMENSION_BUFFER
+            Dx10Header {
+                resource_dimension: 1,
+                ..valid
+            },
+            Dx10Header {
+                resource_dimension: 5,
+                ..valid
+            },
+        ] {
+            let file = dx10_file_with_header(dx10, 2, 2, &DATA);
+            assert!(
+                matches!(decode(&file), Err(ImageError::Decoding(_))),
+                "{dx10:?}"
+            );
+        }
+
+        // 1D and 3D textures
+        for resource_dimension in [2, 4] {
+            let dx10 = Dx10Header {
+                resource_dimension,
+                ..valid
+            };
+            let file = dx10_file_with_header(dx10, 2, 2, &DATA);
+            assert_eq!(decode(&file).unwrap(), DATA);
+        }
+    }
+
+    #[test]
+    fn bc1_volume() {
+        // A 4x4x4 volume with a 2x2x2 and a 1x1x1 level. Every slice is a different color.
+        let blocks = solid_bc1_blocks(4 + 2 + 1);
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 4, 4, &blocks);
+        file[24..28].copy_from_slice(&4_u32.to_le_bytes()); // depth
+        set_mipmap_count(&mut file, 3);
+        set_caps2(&mut file, DDSCAPS2_VOLUME);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert!(decoder.is_volume());
+        assert_eq!(decoder.depth(), 4);
+        let decoded_block =
+            |i: usize| bc::decode_bc1_block(blocks[i * 8..][..8].try_into().unwrap());
+        for z in [2, 0, 3, 1] {
+            let image = decoder.read_volume_slice(z).unwrap().unwrap();
+            assert_eq!(image.as_bytes(), decoded_block(z as usize).as_flattened());
+        }
+        assert!(decoder.read_volume_slice(4).is_none());
+
+        // The first slice of each level
+        let levels: Vec<_> = decoder.into_mipmaps().map(Result::unwrap).collect();
+        assert_eq!(levels.len(), 3);
+        let level_1 = decoded_block(4);
+        assert_eq!(
+            levels[1].as_bytes(),
+            [level_1[0], level_1[1], level_1[4], level_1[5]].as_flattened()
+        );
+        assert_eq!(levels[2].as_bytes(), decoded_block(6)[0]);
+
+        // The depth is ignored for files that aren't volumes.
+        let mut file = dds_file(d3dfmt_pixel_format(32), 2, 2, &DATA);
+        file[24..28].copy_from_slice(&4_u32.to_le_bytes()); // depth
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert!(!decoder.is_volume());
+        assert_eq!(decoder.depth(), 1);
+        assert_eq!(
+            decoder.read_volume_slice(0).unwrap().unwrap().as_bytes(),
+            DATA
+        );
+        assert!(decoder.read_volume_slice(1).is_none());
+    }
+
+    #[test]
+    fn bc1_volume_mipmaps() {
+        // A 8x8x4 volume with a 4x4x2, a 2x2x1, and a 1x1x1 level. Every slice is a different
+        // color.
+        let slice_blocks = [4, 4, 4, 4, 1, 1, 1, 1];
+        let blocks = solid_bc1_blocks(slice_blocks.len());
+        let data: Vec<u8> = blocks
+            .chunks_exact(8)
+            .zip(slice_blocks)
+            .flat_map(|(block, count)| block.repeat(count))
+            .collect();
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 8, 8, &data);
+        file[24..28].copy_from_slice(&4_u32.to_le_bytes()); // depth
+        set_mipmap_count(&mut file, 4);
+        set_caps2(&mut file, DDSCAPS2_VOLUME);
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let depths: Vec<_> = (0..5).map(|level| decoder.mipmap_depth(level)).collect();
+        assert_eq!(depths, [4, 2, 1, 1, 1]);
+
+        let mut slice = 0;
+        for level in 0..4 {
+            for z in 0..decoder.mipmap_depth(level) {
+                let image = decoder.read_volume_slice_mipmap(z, level).unwrap().unwrap();
+                assert_eq!(image.dimensions(), decoder.mipmap_dimensions(level));
+                let block = blocks[slice * 8..][..8].try_into().unwrap();
+                let expected = bc::decode_bc1_block(block)[0];
+                assert!(
+                    image.to_rgba8().pixels().all(|pixel| pixel.0 == expected),
+                    "{level} {z}"
+                );
+                slice += 1;
+            }
+            let depth = decoder.mipmap_depth(level);
+            assert!(decoder.read_volume_slice_mipmap(depth, level).is_none());
+        }
+        assert!(decoder.read_volume_slice_mipmap(0, 4).is_none());
+
+        // DX10 volumes are identified by their resource dimension.
+        let dx10 = Dx10Header {
+            dxgi_format: 71,
+            resource_dimension: 4,
+            misc_flag: 0,
+            array_size: 1,
+            alpha_mode: 0,
+        };
+        let mut file = dx10_file_with_header(dx10, 8, 8, &data);
+        file[24..28].copy_from_slice(&4_u32.to_le_bytes()); // depth
+        set_mipmap_count(&mut file, 4);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert!(decoder.is_volume());
+        let image = decoder.read_volume_slice_mipmap(1, 1).unwrap().unwrap();
+        let expected = bc::decode_bc1_block(blocks[5 * 8..][..8].try_into().unwrap())[0];
+        assert!(image.to_rgba8().pixels().all(|pixel| pixel.0 == expected));
+    }
+
+    #[test]
+    fn mip_level_layout() {
+        let mut file = dds_file(four_cc_pixel_format(b"DXT5"), 256, 256, &[]);
+        set_mipmap_count(&mut file, 9);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let levels: Vec<_> = decoder.mip_layout().collect();
+        assert_eq!(levels.len(), 9);
+        assert_eq!(
+            levels[2],
+            MipLevel {
+                level: 2,
+                width: 64,
+                height: 64,
+                depth: 1,
+                offset: (64 * 64 + 32 * 32) * 16,
+                len: 16 * 16 * 16,
+            }
+        );
+        // 1x1 levels are stored as a whole block.
+        assert_eq!((levels[8].width, levels[8].len), (1, 16));
+
+        // The levels are stored back to back, and cover the whole surface data.
+        // (pixel format, width, height, depth, block size)
+        for (pixel_format, width, height, depth, block_size) in [
+            (four_cc_pixel_format(b"DXT1"), 10_u32, 6_u32, 1_u32, Some(8)),
+            (four_cc_pixel_format(b"DXT5"), 256, 256, 1, Some(16)),
+            (d3dfmt_pixel_format(21), 5, 3, 1, None),
+            (four_cc_pixel_format(b"DXT1"), 8, 8, 4, Some(8)),
+        ] {
+            let (mut w, mut h, mut d) = (width, height, depth);
+            let mut data_len = 0;
+            loop {
+                let level_size = match block_size {
+                    Some(block_size) => w.div_ceil(4) * h.div_ceil(4) * block_size,
+                    None => w * h * 4,
+                };
+                data_len += level_size * d;
+                if (w, h, d) == (1, 1, 1) {
+                    break;
+                }
+                (w, h, d) = ((w / 2).max(1), (h / 2).max(1), (d / 2).max(1));
+            }
+            let data = vec![0; data_len as usize];
+
+            let mut file = dds_file(pixel_format, width, height, &data);
+            let count = 32 - width.max(height).leading_zeros();
+            set_mipmap_count(&mut file, count);
+            if depth > 1 {
+                file[24..28].copy_from_slice(&depth.to_le_bytes()); // depth
+                set_caps2(&mut file, DDSCAPS2_VOLUME);
+            }
+
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            let mut offset = 0;
+            for level in decoder.mip_layout() {
+                assert_eq!(level.offset, offset);
+                offset += level.len;
+            }
+            assert_eq!(offset, data.len() as u64, "{width}x{height}x{depth}");
+        }
+    }
+
+    #[test]
+    fn mip_levels() {
+        let mut data = DATA.to_vec();
+        data.extend([17, 18, 19, 20]);
+        let mut file = dds_file(d3dfmt_pixel_format(32), 2, 2, &data);
+        set_mipmap_count(&mut file, 2);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let levels: Vec<_> = decoder.mip_levels().map(Result::unwrap).collect();
+        assert_eq!(
+            levels,
+            [(0, 2, 2, DATA.to_vec()), (1, 1, 1, vec![17, 18, 19, 20])]
+        );
+
+        // BC1 levels are decoded to RGBA like with `into_mipmaps`, without the padding of partial
+        // blocks.
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), 8, 6, &solid_bc1_blocks(7));
+        set_mipmap_count(&mut file, 4);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let images: Vec<_> = decoder.into_mipmaps().map(Result::unwrap).collect();
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let levels = decoder.mip_levels();
+        assert_eq!(levels.size_hint(), (0, Some(4)));
+        let levels: Vec<_> = levels.map(Result::unwrap).collect();
+        assert_eq!(levels.len(), 4);
+        for (i, ((level, width, height, pixels), image)) in
+            levels.into_iter().zip(images).enumerate()
+        {
+            assert_eq!(level, i as u32);
+            assert_eq!((width, height), image.dimensions());
+            assert_eq!(pixels, image.to_rgba8().into_raw());
+        }
+
+        // The iterator stops after a truncated level.
+        let mut file = dds_file(d3dfmt_pixel_format(32), 2, 2, &DATA);
+        set_mipmap_count(&mut file, 2);
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let levels: Vec<_> = decoder.mip_levels().collect();
+        assert_eq!(levels.len(), 2);
+        assert!(levels[1].is_err());
+    }
+
+    #[test]
+    fn hostile_headers() {
+        // The size of the surface overflows.
+        let pixel_format = rgb_pixel_format([0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000]);
+        let file = dds_file(pixel_format, u32::MAX, u32::MAX, &[]);
+        assert!(matches!(decode(&file), Err(ImageError::Limits(_))));
+
+        // The size of the array overflows.
+        let dx10 = Dx10Header {
+            dxgi_format: 28,
+            resource_dimension: 3,
+            misc_flag: 0,
+            array_size: u32::MAX,
+            alpha_mode: 0,
+        };
+        let file = dx10_file_with_header(dx10, 1 << 16, 1 << 16, &[]);
+        assert!(matches!(decode(&file), Err(ImageError::Limits(_))));
+
+        let mut file = dds_file(pixel_format, 2, 2, &DATA);
+        set_mipmap_count(&mut file, u32::MAX);
+        assert!(matches!(decode(&file), Err(ImageError::Decoding(_))));
+
+        // 2^63 bytes of BC1 blocks don't overflow, but don't fit into the default limits either.
+        let mut file = dds_file(four_cc_pixel_format(b"DXT1"), u32::MAX, u32::MAX, &[0; 8]);
+        set_mipmap_count(&mut file, 32);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let limits = Limits {
+            max_image_width: Some(1 << 16),
+            ..Limits::default()
+        };
+        assert!(matches!(
+            decoder.set_limits(limits),
+            Err(ImageError::Limits(_))
+        ));
+        decoder.set_limits(Limits::default()).unwrap();
+        // The file ends long before the level does.
+        assert!(matches!(
+            decoder.read_volume_slice_mipmap(0, 1),
+            Some(Err(ImageError::IoError(_)))
+        ));
+        let (width, height) = decoder.mipmap_dimensions(31);
+        assert_eq!((width, height), (1, 1));
+        let mut pixels = [0; 4];
+        // The last level is missing from the file.
+        assert!(matches!(
+            decoder.read_mipmap_into(31, &mut pixels),
+            Err(ImageError::IoError(_))
+        ));
+        let mut bands = decoder.into_block_decoder();
+        assert!(matches!(bands.next(), Some(Err(ImageError::Limits(_)))));
+        assert!(bands.next().is_none());
+    }
+
+    #[test]
+    fn truncated_surfaces() {
+        // A cubemap array of 2 cubes with 2 mip levels of 8x8 BC1
+        let dx10 = Dx10Header {
+            dxgi_format: 71,
+            resource_dimension: 3,
+            misc_flag: D3D10_RESOURCE_MISC_TEXTURECUBE,
+            array_size: 2,
+            alpha_mode: 0,
+        };
+        let chain_size = 8 * 4 + 8;
+        let data = vec![0; chain_size * 12];
+        let mut file = dx10_file_with_header(dx10, 8, 8, &data);
+        set_mipmap_count(&mut file, 2);
+
+        for len in [file.len(), file.len() - 8] {
+            let mut decoder = DdsDecoder::new(Cursor::new(&file[..len])).unwrap();
+            let result = |result: Option<ImageResult<DynamicImage>>| result.unwrap().map(drop);
+            assert!(result(decoder.read_array_cubemap_face(1, CubeFace::PositiveZ, 0)).is_ok());
+            assert!(result(decoder.read_array_slice_mipmap(1, 1)).is_ok());
+
+            // The last level of the last face needs the last 8 bytes.
+            let last_level = decoder.read_array_cubemap_face(1, CubeFace::NegativeZ, 1);
+            assert_eq!(result(last_level).is_ok(), len == file.len());
+            let last_face = decoder.read_array_cubemap_face(1, CubeFace::NegativeZ, 0);
+            assert!(result(last_face).is_ok());
+
+            // The position of the reader is restored after errors.
+            let mut buf = [0; 8 * 8 * 4];
+            decoder.read_mipmap_into(0, &mut buf).unwrap();
+        }
+
+        let mut decoder = DdsDecoder::new(Cursor::new(&file[..file.len() - chain_size])).unwrap();
+        let last_level = decoder.read_array_cubemap_face(1, CubeFace::NegativeZ, 1);
+        match last_level {
+            Some(Err(ImageError::IoError(e))) => {
+                assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof)
+            }
+            _ => panic!("expected an unexpected EOF"),
+        }
+        let last_face = decoder.read_array_cubemap_face(1, CubeFace::NegativeZ, 0);
+        assert!(matches!(last_face, Some(Err(ImageError::IoError(_)))));
+
+        // Files that don't even contain the main surface
+        let file = dds_file(four_cc_pixel_format(b"DXT1"), 8, 8, &[0; 8 * 3]);
+        let mut decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        let mut buf = [0; 8 * 8 * 4];
+        assert!(matches!(
+            decoder.read_mipmap_into(0, &mut buf),
+            Err(ImageError::IoError(_))
+        ));
+        assert!(matches!(decode(&file), Err(ImageError::IoError(_))));
+    }
+
+    #[test]
+    fn invalid_files() {
+        let pixel_format = rgb_pixel_format([0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000]);
+        let file = dds_file(pixel_format, 2, 2, &DATA);
+
+        let mut bad_magic = file.clone();
+        bad_magic[3] = b'X';
+        assert!(matches!(decode(&bad_magic), Err(ImageError::Decoding(_))));
+
+        let mut bad_size = file.clone();
+        bad_size[4] = 123;
+        assert!(matches!(decode(&bad_size), Err(ImageError::Decoding(_))));
+
+        for (width, height) in [(0, 2), (2, 0), (0, 0)] {
+            let empty = dds_file(pixel_format, width, height, &DATA);
+            assert!(matches!(decode(&empty), Err(ImageError::Decoding(_))));
+        }
+
+        assert!(matches!(decode(&file[..100]), Err(ImageError::IoError(_))));
+        assert!(matches!(
+            decode(&file[..file.len() - 1]),
+            Err(ImageError::IoError(_))
+        ));
+
+        let unsupported = rgb_pixel_format([0xFF, 0xFF00, 0xFF_0000, 0xFF]);
+        assert!(matches!(
+            decode(&dds_file(unsupported, 2, 2, &DATA)),
+            Err(ImageError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,235 @@
+//! The `DXGI_FORMAT` values DX10 headers use to describe the format of the surfaces.
+
+/// Defines [`DxgiFormat`] with the given variants and their `DXGI_FORMAT` values.
+macro_rules! dxgi_formats {
+    ($($(#[$attr:meta])* $name:ident = $value:literal,)*) => {
+        /// A `DXGI_FORMAT`, which describes the format of the surfaces of a DDS file.
+        ///
+        /// Only the formats that are commonly stored in DDS files are listed. The discriminant of
+        /// each variant is its `DXGI_FORMAT` value.
+        #[repr(u32)]
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+        #[non_exhaustive]
+        pub enum DxgiFormat {
+            $($(#[$attr])* $name = $value,)*
+        }
+
+        impl DxgiFormat {
+            /// Returns the format with the given `DXGI_FORMAT` value, or `None` if it isn't
+            /// listed.
+            pub fn from_u32(value: u32) -> Option<Self> {
+                match value {
+                    $($value => Some(DxgiFormat::$name),)*
+                    _ => None,
+                }
+            }
+
+            #[cfg(test)]
+            const ALL: &[DxgiFormat] = &[$(DxgiFormat::$name,)*];
+        }
+    };
+}
+
+dxgi_formats! {
+    /// `DXGI_FORMAT_UNKNOWN`, also used for legacy formats without a DXGI equivalent.
+    Unknown = 0,
+    /// `DXGI_FORMAT_R32G32B32A32_FLOAT`
+    R32G32B32A32Float = 2,
+    /// `DXGI_FORMAT_R32G32B32_FLOAT`
+    R32G32B32Float = 6,
+    /// `DXGI_FORMAT_R16G16B16A16_FLOAT`
+    R16G16B16A16Float = 10,
+    /// `DXGI_FORMAT_R16G16B16A16_UNORM`
+    R16G16B16A16Unorm = 11,
+    /// `DXGI_FORMAT_R32G32_FLOAT`
+    R32G32Float = 16,
+    /// `DXGI_FORMAT_R10G10B10A2_UNORM`
+    R10G10B10A2Unorm = 24,
+    /// `DXGI_FORMAT_R11G11B10_FLOAT`
+    R11G11B10Float = 26,
+    /// `DXGI_FORMAT_R8G8B8A8_TYPELESS`
+    R8G8B8A8Typeless = 27,
+    /// `DXGI_FORMAT_R8G8B8A8_UNORM`
+    R8G8B8A8Unorm = 28,
+    /// `DXGI_FORMAT_R8G8B8A8_UNORM_SRGB`
+    R8G8B8A8UnormSrgb = 29,
+    /// `DXGI_FORMAT_R8G8B8A8_SNORM`
+    R8G8B8A8Snorm = 31,
+    /// `DXGI_FORMAT_R16G16_FLOAT`
+    R16G16Float = 34,
+    /// `DXGI_FORMAT_R16G16_UNORM`
+    R16G16Unorm = 35,
+    /// `DXGI_FORMAT_R32_FLOAT`
+    R32Float = 41,
+    /// `DXGI_FORMAT_R8G8_UNORM`
+    R8G8Unorm = 49,
+    /// `DXGI_FORMAT_R16_FLOAT`
+    R16Float = 54,
+    /// `DXGI_FORMAT_R16_UNORM`
+    R16Unorm = 56,
+    /// `DXGI_FORMAT_R8_UNORM`
+    R8Unorm = 61,
+    /// `DXGI_FORMAT_A8_UNORM`
+    A8Unorm = 65,
+    /// `DXGI_FORMAT_R9G9B9E5_SHAREDEXP`
+    R9G9B9E5SharedExp = 67,
+    /// `DXGI_FORMAT_BC1_TYPELESS`
+    Bc1Typeless = 70,
+    /// `DXGI_FORMAT_BC1_UNORM`
+    Bc1Unorm = 71,
+    /// `DXGI_FORMAT_BC1_UNORM_SRGB`
+    Bc1UnormSrgb = 72,
+    /// `DXGI_FORMAT_BC2_TYPELESS`
+    Bc2Typeless = 73,
+    /// `DXGI_FORMAT_BC2_UNORM`
+    Bc2Unorm = 74,
+    /// `DXGI_FORMAT_BC2_UNORM_SRGB`
+    Bc2UnormSrgb = 75,
+    /// `DXGI_FORMAT_BC3_TYPELESS`
+    Bc3Typeless = 76,
+    /// `DXGI_FORMAT_BC3_UNORM`
+    Bc3Unorm = 77,
+    /// `DXGI_FORMAT_BC3_UNORM_SRGB`
+    Bc3UnormSrgb = 78,
+    /// `DXGI_FORMAT_BC4_TYPELESS`
+    Bc4Typeless = 79,
+    /// `DXGI_FORMAT_BC4_UNORM`
+    Bc4Unorm = 80,
+    /// `DXGI_FORMAT_BC4_SNORM`
+    Bc4Snorm = 81,
+    /// `DXGI_FORMAT_BC5_TYPELESS`
+    Bc5Typeless = 82,
+    /// `DXGI_FORMAT_BC5_UNORM`
+    Bc5Unorm = 83,
+    /// `DXGI_FORMAT_BC5_SNORM`
+    Bc5Snorm = 84,
+    /// `DXGI_FORMAT_B5G6R5_UNORM`
+    B5G6R5Unorm = 85,
+    /// `DXGI_FORMAT_B5G5R5A1_UNORM`
+    B5G5R5A1Unorm = 86,
+    /// `DXGI_FORMAT_B8G8R8A8_UNORM`
+    B8G8R8A8Unorm = 87,
+    /// `DXGI_FORMAT_B8G8R8X8_UNORM`
+    B8G8R8X8Unorm = 88,
+    /// `DXGI_FORMAT_B8G8R8A8_TYPELESS`
+    B8G8R8A8Typeless = 90,
+    /// `DXGI_FORMAT_B8G8R8A8_UNORM_SRGB`
+    B8G8R8A8UnormSrgb = 91,
+    /// `DXGI_FORMAT_B8G8R8X8_TYPELESS`
+    B8G8R8X8Typeless = 92,
+    /// `DXGI_FORMAT_B8G8R8X8_UNORM_SRGB`
+    B8G8R8X8UnormSrgb = 93,
+    /// `DXGI_FORMAT_BC6H_TYPELESS`
+    Bc6hTypeless = 94,
+    /// `DXGI_FORMAT_BC6H_UF16`
+    Bc6hUf16 = 95,
+    /// `DXGI_FORMAT_BC6H_SF16`
+    Bc6hSf16 = 96,
+    /// `DXGI_FORMAT_BC7_TYPELESS`
+    Bc7Typeless = 97,
+    /// `DXGI_FORMAT_BC7_UNORM`
+    Bc7Unorm = 98,
+    /// `DXGI_FORMAT_BC7_UNORM_SRGB`
+    Bc7UnormSrgb = 99,
+    /// `DXGI_FORMAT_B4G4R4A4_UNORM`
+    B4G4R4A4Unorm = 115,
+}
+
+impl DxgiFormat {
+    /// The `DXGI_FORMAT` value of the format.
+    pub fn to_u32(self) -> u32 {
+        self as u32
+    }
+
+    /// Whether the format is one of the block compressed formats BC1 to BC7.
+    pub fn is_block_compressed(self) -> bool {
+        self.bytes_per_block().is_some()
+    }
+
+    /// The number of bytes in each 4x4 block, or `None` if the format isn't block compressed.
+    pub fn bytes_per_block(self) -> Option<u32> {
+        use DxgiFormat::*;
+        match self {
+            Bc1Typeless | Bc1Unorm | Bc1UnormSrgb | Bc4Typeless | Bc4Unorm | Bc4Snorm => Some(8),
+            Bc2Typeless | Bc2Unorm | Bc2UnormSrgb | Bc3Typeless | Bc3Unorm | Bc3UnormSrgb
+            | Bc5Typeless | Bc5Unorm | Bc5Snorm | Bc6hTypeless | Bc6hUf16 | Bc6hSf16
+            | Bc7Typeless | Bc7Unorm | Bc7UnormSrgb => Some(16),
+            _ => None,
+        }
+    }
+
+    /// Whether the format stores sRGB-encoded colors.
+    pub fn is_srgb(self) -> bool {
+        use DxgiFormat::*;
+        matches!(
+            self,
+            R8G8B8A8UnormSrgb
+                | Bc1UnormSrgb
+                | Bc2UnormSrgb
+                | Bc3UnormSrgb
+                | B8G8R8A8UnormSrgb
+                | B8G8R8X8UnormSrgb
+                | Bc7UnormSrgb
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_u32() {
+        let cases = [
+            (0, DxgiFormat::Unknown),
+            (28, DxgiFormat::R8G8B8A8Unorm),
+            (29, DxgiFormat::R8G8B8A8UnormSrgb),
+            (70, DxgiFormat::Bc1Typeless),
+            (71, DxgiFormat::Bc1Unorm),
+            (72, DxgiFormat::Bc1UnormSrgb),
+            (81, DxgiFormat::Bc4Snorm),
+            (84, DxgiFormat::Bc5Snorm),
+            (87, DxgiFormat::B8G8R8A8Unorm),
+            (95, DxgiFormat::Bc6hUf16),
+            (96, DxgiFormat::Bc6hSf16),
+            (99, DxgiFormat::Bc7UnormSrgb),
+            (115, DxgiFormat::B4G4R4A4Unorm),
+        ];
+        for (value, format) in cases {
+            assert_eq!(DxgiFormat::from_u32(value), Some(format), "{value}");
+        }
+        for value in [1, 89, 100, 116, u32::MAX] {
+            assert_eq!(DxgiFormat::from_u32(value), None, "{value}");
+        }
+
+        for &format in DxgiFormat::ALL {
+            assert_eq!(DxgiFormat::from_u32(format.to_u32()), Some(format));
+        }
+    }
+
+    #[test]
+    fn helpers() {
+        let block_compressed: Vec<_> = DxgiFormat::ALL
+            .iter()
+            .filter(|format| format.is_block_compressed())
+            .collect();
+        assert_eq!(block_compressed.len(), 7 * 3);
+        assert!(block_compressed.iter().all(|format| {
+            let value = format.to_u32();
+            (70..=84).contains(&value) || (94..=99).contains(&value)
+        }));
+
+        assert_eq!(DxgiFormat::Bc1UnormSrgb.bytes_per_block(), Some(8));
+        assert_eq!(DxgiFormat::Bc4Snorm.bytes_per_block(), Some(8));
+        assert_eq!(DxgiFormat::Bc3Typeless.bytes_per_block(), Some(16));
+        assert_eq!(DxgiFormat::Bc6hSf16.bytes_per_block(), Some(16));
+        assert_eq!(DxgiFormat::R8G8B8A8Unorm.bytes_per_block(), None);
+
+        let srgb: Vec<_> = DxgiFormat::ALL
+            .iter()
+            .filter(|format| format.is_srgb())
+            .map(|format| format.to_u32())
+            .collect();
+        assert_eq!(srgb, [29, 72, 75, 78, 91, 93, 99]);
+    }
+}
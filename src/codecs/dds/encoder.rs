@@ -0,0 +1,450 @@
+use std::io::Write;
+
+use crate::error::{LimitError, LimitErrorKind, UnsupportedError, UnsupportedErrorKind};
+use crate::{ExtendedColorType, ImageEncoder, ImageError, ImageFormat, ImageResult};
+
+use super::bc_encoder::{self, EncodeQuality};
+use super::convert::{linear_f32_to_srgb8, SRGB_TO_LINEAR_F32};
+use super::dxgi::DxgiFormat;
+use super::header::{
+    Dx10Header, Header, PixelFormat, D3D10_RESOURCE_DIMENSION_TEXTURE2D, DDPF_FOURCC,
+    DDSCAPS_COMPLEX, DDSCAPS_MIPMAP, DDSCAPS_TEXTURE, DDSD_CAPS, DDSD_HEIGHT, DDSD_LINEARSIZE,
+    DDSD_MIPMAPCOUNT, DDSD_PIXELFORMAT, DDSD_WIDTH,
+};
+use super::surface::encode_blocks;
+
+/// How [`DdsEncoder`] generates the mip levels of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MipFilter {
+    /// Writes only the image itself.
+    #[default]
+    None,
+    /// Writes all mip levels down to 1x1. Each pixel of a level is the average of 2x2 pixels of
+    /// the previous level.
+    ///
+    /// The size of each level is half the size of the previous one, rounded down, so the last
+    /// row or column of a level with an odd size doesn't contribute to the next level. For sRGB
+    /// formats, the colors are averaged in linear space.
+    Box,
+}
+
+/// A DDS encoder that writes a block compressed surface and optionally its mip levels.
+///
+/// BC1 files are written with the FourCC `DXT1` and BC3 files with `DXT5`, which every DDS reader
+/// supports. Their sRGB variants need a DX10 header. If the width or height isn't a multiple of 4,
+/// the blocks at the edges are padded by repeating the last column and row of the image.
+pub struct DdsEncoder<W> {
+    writer: W,
+    format: DxgiFormat,
+    quality: EncodeQuality,
+    mip_filter: MipFilter,
+}
+
+impl<W: Write> DdsEncoder<W> {
+    /// Creates an encoder that writes BC3 (DXT5) files.
+    pub fn new(writer: W) -> Self {
+        Self::new_with_format(writer, DxgiFormat::Bc3Unorm)
+    }
+
+    /// Creates an encoder that writes files in the given format.
+    ///
+    /// Only [`DxgiFormat::Bc1Unorm`], [`DxgiFormat::Bc3Unorm`], and their sRGB variants are
+    /// supported. Writing an image in any other format returns an error.
+    pub fn new_with_format(writer: W, format: DxgiFormat) -> Self {
+        Self {
+            writer,
+            format,
+            quality: EncodeQuality::default(),
+            mip_filter: MipFilter::default(),
+        }
+    }
+
+    /// Sets the tradeoff between speed and quality of encoding the blocks.
+    ///
+    /// Defaults to [`EncodeQuality::Balanced`].
+    pub fn set_quality(&mut self, quality: EncodeQuality) {
+        self.quality = quality;
+    }
+
+    /// Sets how the mip levels are generated. See [`MipFilter`].
+    ///
+    /// Defaults to [`MipFilter::None`].
+    pub fn set_mip_filter(&mut self, mip_filter: MipFilter) {
+        self.mip_filter = mip_filter;
+    }
+
+    /// Returns the FourCC of the format, or `None` if it isn't supported.
+    fn four_cc(&self) -> Option<[u8; 4]> {
+        match self.format {
+            DxgiFormat::Bc1Unorm => Some(*b"DXT1"),
+            DxgiFormat::Bc3Unorm => Some(*b"DXT5"),
+            DxgiFormat::Bc1UnormSrgb | DxgiFormat::Bc3UnormSrgb => Some(*b"DX10"),
+            _ => None,
+        }
+    }
+
+    /// Encodes RGBA pixels into the blocks of the format.
+    fn encode_surface(&self, pixels: &[[u8; 4]], width: u32, height: u32) -> Vec<u8> {
+        let quality = self.quality;
+        match self.format {
+            DxgiFormat::Bc1Unorm | DxgiFormat::Bc1UnormSrgb => {
+                encode_blocks(pixels, width, height, |block| {
+                    bc_encoder::encode_bc1_block(block, quality)
+                })
+            }
+            DxgiFormat::Bc3Unorm | DxgiFormat::Bc3UnormSrgb => {
+                encode_blocks(pixels, width, height, |block| {
+                    bc_encoder::encode_bc3_block(block, quality)
+                })
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Halves the size of an image with a box filter, rounding down to at least 1.
+///
+/// If `srgb` is set, the colors are averaged in linear space. Alpha is always averaged as it is.
+fn downsample(pixels: &[[u8; 4]], width: u32, height: u32, srgb: bool) -> Vec<[u8; 4]> {
+    let (width, height) = (width as usize, height as usize);
+    let (new_width, new_height) = ((width / 2).max(1), (height / 2).max(1));
+    (0..new_height)
+        .flat_map(|y| {
+            // Clamping only affects a width or height of 1, where the single pixel is used twice.
+            let rows = [2 * y, (2 * y + 1).min(height - 1)].map(|y| &pixels[y * width..][..width]);
+            (0..new_width).map(move |x| {
+                let columns = [2 * x, (2 * x + 1).min(width - 1)];
+                let samples = rows.map(|row| columns.map(|x| row[x]));
+                let samples = samples.as_flattened();
+                std::array::from_fn(|c| {
+                    if srgb && c < 3 {
+                        let sum: f32 = samples
+                            .iter()
+                            .map(|p| SRGB_TO_LINEAR_F32[p[c] as usize])
+                            .sum();
+                        linear_f32_to_srgb8(sum / 4.0)
+                    } else {
+                        let sum: u32 = samples.iter().map(|p| u32::from(p[c])).sum();
+                        ((sum + 2) / 4) as u8
+                    }
+                })
+            })
+        })
+        .collect()
+}
+
+/// Converts pixels of the given color type to RGBA, or returns `None` if the color type isn't
+/// supported.
+fn to_rgba8(buf: &[u8], color_type: ExtendedColorType) -> Option<Vec<[u8; 4]>> {
+    Some(match color_type {
+        ExtendedColorType::Rgba8 => buf.as_chunks::<4>().0.to_vec(),
+        ExtendedColorType::Rgb8 => buf
+            .as_chunks::<3>()
+            .0
+            .iter()
+            .map(|&[r, g, b]| [r, g, b, 255])
+            .collect(),
+        ExtendedColorType::La8 => buf
+            .as_chunks::<2>()
+            .0
+            .iter()
+            .map(|&[l, a]| [l, l, l, a])
+            .collect(),
+        ExtendedColorType::L8 => buf.iter().map(|&l| [l, l, l, 255]).collect(),
+        _ => return None,
+    })
+}
+
+impl<W: Write> ImageEncoder for DdsEncoder<W> {
+    #[track_caller]
+    fn write_image(
+        mut self,
+        buf: &[u8],
+        width: u32,
+        height: u32,
+        color_type: ExtendedColorType,
+    ) -> ImageResult<()> {
+        let expected_buffer_len = color_type.buffer_size(width, height);
+        assert_eq!(
+            expected_buffer_len,
+            buf.len() as u64,
+            "Invalid buffer length: expected {expected_buffer_len} got {} for {width}x{height} image",
+            buf.len(),
+        );
+
+        let Some(four_cc) = self.four_cc() else {
+            return Err(ImageError::Unsupported(
+                UnsupportedError::from_format_and_kind(
+                    ImageFormat::Dds.into(),
+                    UnsupportedErrorKind::GenericFeature(format!("encoding {:?}", self.format)),
+                ),
+            ));
+        };
+        let Some(pixels) = to_rgba8(buf, color_type) else {
+            return Err(ImageError::Unsupported(
+                UnsupportedError::from_format_and_kind(
+                    ImageFormat::Dds.into(),
+                    UnsupportedErrorKind::Color(color_type),
+                ),
+            ));
+        };
+
+        // DDS files can't be empty, and the size of the surface has to fit into the header.
+        let block_size = self.format.bytes_per_block().unwrap();
+        let linear_size =
+            u64::from(width.div_ceil(4)) * u64::from(height.div_ceil(4)) * u64::from(block_size);
+        let linear_size = match u32::try_from(linear_size) {
+            Ok(size) if width > 0 && height > 0 => size,
+            _ => {
+                return Err(ImageError::Limits(LimitError::from_kind(
+                    LimitErrorKind::DimensionError,
+                )))
+            }
+        };
+
+        let mipmap_count = match self.mip_filter {
+            MipFilter::None => 1,
+            MipFilter::Box => u32::BITS - width.max(height).leading_zeros(),
+        };
+        let mut header = Header {
+            flags: DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT | DDSD_LINEARSIZE,
+            height,
+            width,
+            pitch_or_linear_size: linear_size,
+            pixel_format: PixelFormat {
+                flags: DDPF_FOURCC,
+                four_cc,
+                ..PixelFormat::default()
+            },
+            caps: DDSCAPS_TEXTURE,
+            dx10: (four_cc == *b"DX10").then_some(Dx10Header {
+                dxgi_format: self.format.to_u32(),
+                resource_dimension: D3D10_RESOURCE_DIMENSION_TEXTURE2D,
+                misc_flag: 0,
+                array_size: 1,
+                alpha_mode: 0,
+            }),
+            ..Header::default()
+        };
+        if mipmap_count > 1 {
+            header.flags |= DDSD_MIPMAPCOUNT;
+            header.mipmap_count = mipmap_count;
+            header.caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+        }
+        header.write_to(&mut self.writer)?;
+
+        let srgb = self.format.is_srgb();
+        let (mut pixels, mut width, mut height) = (pixels, width, height);
+        for level in 0..mipmap_count {
+            if level > 0 {
+                pixels = downsample(&pixels, width, height, srgb);
+                width = (width / 2).max(1);
+                height = (height / 2).max(1);
+            }
+            let data = self.encode_surface(&pixels, width, height);
+            self.writer.write_all(&data)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codecs::dds::DdsDecoder;
+    use crate::{ImageDecoder, RgbaImage};
+    use std::io::Cursor;
+
+    fn encode(image: &RgbaImage, format: DxgiFormat) -> Vec<u8> {
+        let mut file = Vec::new();
+        DdsEncoder::new_with_format(&mut file, format)
+            .write_image(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                ExtendedColorType::Rgba8,
+            )
+            .unwrap();
+        file
+    }
+
+    fn decode(file: &[u8]) -> RgbaImage {
+        let decoder = DdsDecoder::new(Cursor::new(file)).unwrap();
+        let (width, height) = decoder.dimensions();
+        let mut buf = vec![0; decoder.total_bytes() as usize];
+        decoder.read_image(&mut buf).unwrap();
+        RgbaImage::from_raw(width, height, buf).unwrap()
+    }
+
+    #[test]
+    fn header() {
+        let image = RgbaImage::new(10, 6);
+        let file = encode(&image, DxgiFormat::Bc1Unorm);
+        let u32_at = |offset: usize| u32::from_le_bytes(file[offset..][..4].try_into().unwrap());
+        assert_eq!(file[..4], *b"DDS ");
+        assert_eq!(u32_at(4), 124);
+        assert_eq!(u32_at(8), 0x8_1007, "flags");
+        assert_eq!((u32_at(16), u32_at(12)), (10, 6));
+        assert_eq!(u32_at(20), 3 * 2 * 8, "linear size");
+        assert_eq!(u32_at(80), DDPF_FOURCC);
+        assert_eq!(file[84..88], *b"DXT1");
+        assert_eq!(u32_at(108), DDSCAPS_TEXTURE);
+        assert_eq!(file.len(), 128 + 3 * 2 * 8);
+
+        let file = encode(&image, DxgiFormat::Bc3Unorm);
+        assert_eq!(file[84..88], *b"DXT5");
+        assert_eq!(file.len(), 128 + 3 * 2 * 16);
+
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.format(), DxgiFormat::Bc3Unorm);
+        assert_eq!(decoder.dimensions(), (10, 6));
+    }
+
+    #[test]
+    fn round_trip() {
+        // The colors of each block are on a line, which the palettes of BC1 and BC3 can follow
+        // closely.
+        let image = RgbaImage::from_fn(13, 7, |x, y| {
+            let x = x as u8;
+            crate::Rgba([x * 19, 255 - x * 15, 100 + x * 5, 255 - y as u8 * 20])
+        });
+        for (format, alpha_error) in [
+            (DxgiFormat::Bc1Unorm, None),
+            (DxgiFormat::Bc3Unorm, Some(9)),
+        ] {
+            let decoded = decode(&encode(&image, format));
+            assert_eq!(decoded.dimensions(), image.dimensions());
+            for (pixel, decoded) in image.pixels().zip(decoded.pixels()) {
+                for c in 0..3 {
+                    assert!(pixel[c].abs_diff(decoded[c]) <= 16, "{format:?}");
+                }
+                match alpha_error {
+                    Some(error) => assert!(pixel[3].abs_diff(decoded[3]) <= error),
+                    // All alphas are at least 128, so BC1 encodes them as opaque.
+                    None => assert_eq!(decoded[3], 255),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn color_types() {
+        // gray levels that are in the palette of a block from black to white
+        let gray = [0, 85, 170, 255];
+        let cases = [
+            (ExtendedColorType::L8, gray.to_vec()),
+            (
+                ExtendedColorType::La8,
+                gray.iter().flat_map(|&l| [l, 255]).collect(),
+            ),
+            (
+                ExtendedColorType::Rgb8,
+                gray.iter().flat_map(|&l| [l; 3]).collect(),
+            ),
+        ];
+        for (color_type, buf) in cases {
+            let mut file = Vec::new();
+            DdsEncoder::new(&mut file)
+                .write_image(&buf, 2, 2, color_type)
+                .unwrap();
+            let decoded = decode(&file);
+            for (&l, pixel) in gray.iter().zip(decoded.pixels()) {
+                assert!(pixel[0].abs_diff(l) <= 4, "{color_type:?}");
+                assert_eq!(pixel[3], 255);
+            }
+        }
+
+        let mut file = Vec::new();
+        let result =
+            DdsEncoder::new(&mut file).write_image(&[0; 8], 1, 1, ExtendedColorType::Rgba16);
+        assert!(matches!(result, Err(ImageError::Unsupported(_))));
+    }
+
+    #[test]
+    fn mip_chain() {
+        let color = crate::Rgba([0, 255, 0, 255]);
+        let image = RgbaImage::from_pixel(5, 3, color);
+        let mut file = Vec::new();
+        let mut encoder = DdsEncoder::new_with_format(&mut file, DxgiFormat::Bc1Unorm);
+        encoder.set_mip_filter(MipFilter::Box);
+        encoder
+            .write_image(image.as_raw(), 5, 3, ExtendedColorType::Rgba8)
+            .unwrap();
+        let u32_at = |offset: usize| u32::from_le_bytes(file[offset..][..4].try_into().unwrap());
+        assert_eq!(u32_at(8), 0xA_1007, "flags");
+        assert_eq!(u32_at(28), 3, "mipmap count");
+        assert_eq!(
+            u32_at(108),
+            DDSCAPS_COMPLEX | DDSCAPS_TEXTURE | DDSCAPS_MIPMAP
+        );
+        // 5x3, 2x1, and 1x1
+        assert_eq!(file.len(), 128 + (2 + 1 + 1) * 8);
+
+        let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+        assert_eq!(decoder.mipmap_count(), 3);
+        let levels: Vec<_> = decoder.into_mipmaps().map(Result::unwrap).collect();
+        let dimensions: Vec<_> = levels
+            .iter()
+            .map(|level| (level.width(), level.height()))
+            .collect();
+        assert_eq!(dimensions, [(5, 3), (2, 1), (1, 1)]);
+        for level in levels {
+            assert!(level.to_rgba8().pixels().all(|&pixel| pixel == color));
+        }
+    }
+
+    #[test]
+    fn downsample_box() {
+        let checker = [[0, 0, 0, 0], [255; 4], [255; 4], [0, 0, 0, 0]];
+        assert_eq!(downsample(&checker, 2, 2, false), [[128; 4]]);
+        // Half of the light is 188 in sRGB.
+        assert_eq!(downsample(&checker, 2, 2, true), [[188, 188, 188, 128]]);
+
+        // The last column of an odd width is dropped, and single rows are kept.
+        let row = [[10; 4], [20; 4], [90; 4]];
+        assert_eq!(downsample(&row, 3, 1, false), [[15; 4]]);
+        assert_eq!(downsample(&row, 1, 3, false), [[15; 4]]);
+    }
+
+    #[test]
+    fn srgb() {
+        let image = RgbaImage::from_fn(4, 4, |x, y| crate::Rgba([((x + y) % 2 * 255) as u8; 4]));
+        for format in [DxgiFormat::Bc1UnormSrgb, DxgiFormat::Bc3UnormSrgb] {
+            let mut file = Vec::new();
+            let mut encoder = DdsEncoder::new_with_format(&mut file, format);
+            encoder.set_mip_filter(MipFilter::Box);
+            encoder
+                .write_image(image.as_raw(), 4, 4, ExtendedColorType::Rgba8)
+                .unwrap();
+            assert_eq!(file[84..88], *b"DX10");
+
+            let decoder = DdsDecoder::new(Cursor::new(&file)).unwrap();
+            assert_eq!(decoder.format(), format);
+            let levels: Vec<_> = decoder.into_mipmaps().map(Result::unwrap).collect();
+            assert_eq!(levels.len(), 3);
+            // The checkerboard averages to half the light, not to half the sRGB value.
+            for level in &levels[1..] {
+                for pixel in level.to_rgba8().pixels() {
+                    assert!(pixel[0].abs_diff(188) <= 4, "{format:?} {pixel:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported() {
+        let mut file = Vec::new();
+        let result = DdsEncoder::new_with_format(&mut file, DxgiFormat::Bc7Unorm).write_image(
+            &[0; 4],
+            1,
+            1,
+            ExtendedColorType::Rgba8,
+        );
+        assert!(matches!(result, Err(ImageError::Unsupported(_))));
+
+        let result = DdsEncoder::new(&mut file).write_image(&[], 0, 5, ExtendedColorType::Rgba8);
+        assert!(matches!(result, Err(ImageError::Limits(_))));
+        assert!(file.is_empty());
+    }
+}
@@ -0,0 +1,441 @@
+use super::dxgi::DxgiFormat;
+use super::header::{PixelFormat, DDPF_ALPHAPIXELS, DDPF_FOURCC, DDPF_LUMINANCE, DDPF_RGB};
+use crate::ColorType;
+
+/// The format of the surfaces in a DDS file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    R8G8B8A8,
+    /// Like `R8G8B8A8`, but the alpha channel is unused.
+    R8G8B8X8,
+    B8G8R8A8,
+    /// Like `B8G8R8A8`, but the alpha channel is unused.
+    B8G8R8X8,
+    /// 24-bit RGB without alpha.
+    R8G8B8,
+    /// 16-bit RGB with 5 bits of red and blue and 6 bits of green.
+    B5G6R5,
+    /// 16-bit RGBA with 5 bits of red, green, and blue and 1 bit of alpha.
+    B5G5R5A1,
+    /// Like `B5G5R5A1`, but the alpha bit is unused.
+    B5G5R5X1,
+    /// 16-bit RGBA with 4 bits per channel.
+    B4G4R4A4,
+    /// 24-bit BGR without alpha. This is `D3DFMT_R8G8B8`, which names the channels from the most
+    /// significant byte.
+    B8G8R8,
+    /// A single 8-bit channel.
+    R8,
+    /// Two 8-bit channels, decoded to RGB with blue 0.
+    R8G8,
+    /// 8 bits of luminance followed by 8 bits of alpha, decoded to gray with alpha.
+    L8A8,
+    /// A single 16-bit channel.
+    R16,
+    /// Two 16-bit channels, decoded to RGB with blue 0.
+    R16G16,
+    /// 64-bit RGBA with 16 bits per channel.
+    R16G16B16A16,
+    /// A single half float channel, decoded to RGB floats with the value in all three channels.
+    R16Float,
+    /// 64-bit RGBA with a half float per channel.
+    R16G16B16A16Float,
+    /// A single float channel, decoded to RGB with the value in all three channels.
+    R32Float,
+    /// Two float channels, decoded to RGB with blue 0.
+    R32G32Float,
+    /// 96-bit RGB with a float per channel, decoded to RGBA with an alpha of 1.0.
+    R32G32B32Float,
+    /// 128-bit RGBA with a float per channel.
+    R32G32B32A32Float,
+    /// 32-bit RGBA with 10 bits of red, green, and blue and 2 bits of alpha, decoded to 16 bits
+    /// per channel.
+    R10G10B10A2,
+    /// 32-bit RGB with 9-bit mantissas and a shared 5-bit exponent, decoded to floats.
+    R9G9B9E5,
+    /// Block compressed with BC1, also known as DXT1.
+    Bc1,
+    /// Block compressed with BC2, also known as DXT3.
+    Bc2,
+    /// Block compressed with BC3, also known as DXT5.
+    Bc3,
+    Bc4Unsigned,
+    /// Decoded to unorm.
+    Bc4Signed,
+    Bc5Unsigned,
+    /// Decoded to unorm.
+    Bc5Signed,
+    Bc6hUnsigned,
+    Bc6hSigned,
+    Bc7,
+}
+
+impl Format {
+    /// Determines the format from the pixel format of the DDS header.
+    ///
+    /// Returns `None` if the format isn't supported.
+    pub(crate) fn from_pixel_format(pixel_format: &PixelFormat) -> Option<Self> {
+        if pixel_format.flags & DDPF_FOURCC != 0 {
+            return Self::from_fourcc(pixel_format.four_cc);
+        }
+
+        // Luminance is stored in the red mask.
+        if pixel_format.flags & DDPF_LUMINANCE != 0 {
+            let has_alpha = pixel_format.flags & DDPF_ALPHAPIXELS != 0;
+            let masks = (
+                pixel_format.r_bit_mask,
+                has_alpha.then_some(pixel_format.a_bit_mask),
+            );
+            return match (pixel_format.rgb_bit_count, masks) {
+                (8, (0xFF, None)) => Some(Format::R8),
+                (16, (0x00FF, Some(0xFF00))) => Some(Format::L8A8),
+                _ => None,
+            };
+        }
+
+        if pixel_format.flags & DDPF_RGB != 0 && pixel_format.rgb_bit_count == 8 {
+            if pixel_format.flags & DDPF_ALPHAPIXELS != 0 {
+                return None;
+            }
+            let masks = (
+                pixel_format.r_bit_mask,
+                pixel_format.g_bit_mask,
+                pixel_format.b_bit_mask,
+            );
+            return match masks {
+                (0xFF, 0, 0) => Some(Format::R8),
+                _ => None,
+            };
+        }
+
+        if pixel_format.flags & DDPF_RGB != 0 && pixel_format.rgb_bit_count == 16 {
+            let a_bit_mask = if pixel_format.flags & DDPF_ALPHAPIXELS != 0 {
+                pixel_format.a_bit_mask
+            } else {
+                0
+            };
+            let masks = (
+                pixel_format.r_bit_mask,
+                pixel_format.g_bit_mask,
+                pixel_format.b_bit_mask,
+                a_bit_mask,
+            );
+            return match masks {
+                (0xF800, 0x07E0, 0x001F, 0) => Some(Format::B5G6R5),
+                (0x7C00, 0x03E0, 0x001F, 0x8000) => Some(Format::B5G5R5A1),
+                (0x7C00, 0x03E0, 0x001F, 0) => Some(Format::B5G5R5X1),
+                (0x0F00, 0x00F0, 0x000F, 0xF000) => Some(Format::B4G4R4A4),
+                (0x00FF, 0xFF00, 0, 0) => Some(Format::R8G8),
+                _ => None,
+            };
+        }
+
+        if pixel_format.flags & DDPF_RGB != 0 && pixel_format.rgb_bit_count == 24 {
+            // There is no room for alpha.
+            if pixel_format.flags & DDPF_ALPHAPIXELS != 0 {
+                return None;
+            }
+            let masks = (
+                pixel_format.r_bit_mask,
+                pixel_format.g_bit_mask,
+                pixel_format.b_bit_mask,
+            );
+            return match masks {
+                (0x0000_00FF, 0x0000_FF00, 0x00FF_0000) => Some(Format::R8G8B8),
+                (0x00FF_0000, 0x0000_FF00, 0x0000_00FF) => Some(Format::B8G8R8),
+                _ => None,
+            };
+        }
+
+        if pixel_format.flags & DDPF_RGB != 0 && pixel_format.rgb_bit_count == 32 {
+            let has_alpha = match pixel_format.a_bit_mask {
+                _ if pixel_format.flags & DDPF_ALPHAPIXELS == 0 => false,
+                0xFF00_0000 => true,
+                _ => return None,
+            };
+            let masks = (
+                pixel_format.r_bit_mask,
+                pixel_format.g_bit_mask,
+                pixel_format.b_bit_mask,
+            );
+            return match (masks, has_alpha) {
+                ((0x0000_00FF, 0x0000_FF00, 0x00FF_0000), true) => Some(Format::R8G8B8A8),
+                ((0x0000_00FF, 0x0000_FF00, 0x00FF_0000), false) => Some(Format::R8G8B8X8),
+                ((0x00FF_0000, 0x0000_FF00, 0x0000_00FF), true) => Some(Format::B8G8R8A8),
+                ((0x00FF_0000, 0x0000_FF00, 0x0000_00FF), false) => Some(Format::B8G8R8X8),
+                _ => None,
+            };
+        }
+
+        None
+    }
+
+    /// Determines the format from the FourCC of a pixel format.
+    ///
+    /// Besides FourCC strings, this also accepts the numeric `D3DFMT` values some writers store
+    /// instead. DXT2 and DXT4 are the premultiplied variants of DXT3 and DXT5, which only differ
+    /// in their [alpha mode](super::DdsDecoder::alpha_mode). Returns `None` if the code is unknown
+    /// or the format isn't supported.
+    pub(crate) fn from_fourcc(code: [u8; 4]) -> Option<Self> {
+        match &code {
+            b"DXT1" => return Some(Format::Bc1),
+            b"DXT2" | b"DXT3" => return Some(Format::Bc2),
+            b"DXT4" | b"DXT5" => return Some(Format::Bc3),
+            b"ATI1" | b"BC4U" => return Some(Format::Bc4Unsigned),
+            b"BC4S" => return Some(Format::Bc4Signed),
+            b"ATI2" | b"BC5U" => return Some(Format::Bc5Unsigned),
+            b"BC5S" => return Some(Format::Bc5Signed),
+            _ => {}
+        }
+
+        match u32::from_le_bytes(code) {
+            20 => Some(Format::B8G8R8),             // D3DFMT_R8G8B8
+            21 => Some(Format::B8G8R8A8),           // D3DFMT_A8R8G8B8
+            22 => Some(Format::B8G8R8X8),           // D3DFMT_X8R8G8B8
+            23 => Some(Format::B5G6R5),             // D3DFMT_R5G6B5
+            24 => Some(Format::B5G5R5X1),           // D3DFMT_X1R5G5B5
+            25 => Some(Format::B5G5R5A1),           // D3DFMT_A1R5G5B5
+            26 => Some(Format::B4G4R4A4),           // D3DFMT_A4R4G4B4
+            32 => Some(Format::R8G8B8A8),           // D3DFMT_A8B8G8R8
+            33 => Some(Format::R8G8B8X8),           // D3DFMT_X8B8G8R8
+            34 => Some(Format::R16G16),             // D3DFMT_G16R16
+            36 => Some(Format::R16G16B16A16),       // D3DFMT_A16B16G16R16
+            50 => Some(Format::R8),                 // D3DFMT_L8
+            51 => Some(Format::L8A8),               // D3DFMT_A8L8
+            81 => Some(Format::R16),                // D3DFMT_L16
+            111 => Some(Format::R16Float),          // D3DFMT_R16F
+            113 => Some(Format::R16G16B16A16Float), // D3DFMT_A16B16G16R16F
+            114 => Some(Format::R32Float),          // D3DFMT_R32F
+            115 => Some(Format::R32G32Float),       // D3DFMT_G32R32F
+            116 => Some(Format::R32G32B32A32Float), // D3DFMT_A32B32G32R32F
+            _ => None,
+        }
+    }
+
+    /// Determines the format from the `DXGI_FORMAT` of the DX10 header.
+    ///
+    /// sRGB formats map to the same format as their linear counterparts, because the decoded
+    /// values are the same. Returns `None` if the format isn't supported.
+    pub(crate) fn from_dxgi_format(dxgi_format: u32) -> Option<Self> {
+        match dxgi_format {
+            2 => Some(Format::R32G32B32A32Float),  // R32G32B32A32_FLOAT
+            6 => Some(Format::R32G32B32Float),     // R32G32B32_FLOAT
+            10 => Some(Format::R16G16B16A16Float), // R16G16B16A16_FLOAT
+            11 => Some(Format::R16G16B16A16),      // R16G16B16A16_UNORM
+            16 => Some(Format::R32G32Float),       // R32G32_FLOAT
+            24 => Some(Format::R10G10B10A2),       // R10G10B10A2_UNORM
+            28 | 29 => Some(Format::R8G8B8A8),     // R8G8B8A8_UNORM(_SRGB)
+            35 => Some(Format::R16G16),            // R16G16_UNORM
+            41 => Some(Format::R32Float),          // R32_FLOAT
+            49 => Some(Format::R8G8),              // R8G8_UNORM
+            54 => Some(Format::R16Float),          // R16_FLOAT
+            56 => Some(Format::R16),               // R16_UNORM
+            61 => Some(Format::R8),                // R8_UNORM
+            67 => Some(Format::R9G9B9E5),          // R9G9B9E5_SHAREDEXP
+            71 | 72 => Some(Format::Bc1),          // BC1_UNORM(_SRGB)
+            74 | 75 => Some(Format::Bc2),          // BC2_UNORM(_SRGB)
+            77 | 78 => Some(Format::Bc3),          // BC3_UNORM(_SRGB)
+            80 => Some(Format::Bc4Unsigned),       // BC4_UNORM
+            81 => Some(Format::Bc4Signed),         // BC4_SNORM
+            83 => Some(Format::Bc5Unsigned),       // BC5_UNORM
+            84 => Some(Format::Bc5Signed),         // BC5_SNORM
+            85 => Some(Format::B5G6R5),            // B5G6R5_UNORM
+            86 => Some(Format::B5G5R5A1),          // B5G5R5A1_UNORM
+            87 | 91 => Some(Format::B8G8R8A8),     // B8G8R8A8_UNORM(_SRGB)
+            88 | 93 => Some(Format::B8G8R8X8),     // B8G8R8X8_UNORM(_SRGB)
+            95 => Some(Format::Bc6hUnsigned),      // BC6H_UF16
+            96 => Some(Format::Bc6hSigned),        // BC6H_SF16
+            98 | 99 => Some(Format::Bc7),          // BC7_UNORM(_SRGB)
+            115 => Some(Format::B4G4R4A4),         // B4G4R4A4_UNORM
+            _ => None,
+        }
+    }
+
+    /// The DXGI format equivalent to the format of a file without a DX10 header.
+    ///
+    /// Formats without an exact DXGI equivalent, e.g. 24-bit RGB, are [`DxgiFormat::Unknown`].
+    pub(crate) fn legacy_dxgi_format(self) -> DxgiFormat {
+        match self {
+            Format::R8G8B8A8 => DxgiFormat::R8G8B8A8Unorm,
+            Format::B8G8R8A8 => DxgiFormat::B8G8R8A8Unorm,
+            Format::B8G8R8X8 => DxgiFormat::B8G8R8X8Unorm,
+            Format::B5G6R5 => DxgiFormat::B5G6R5Unorm,
+            Format::B5G5R5A1 => DxgiFormat::B5G5R5A1Unorm,
+            Format::B4G4R4A4 => DxgiFormat::B4G4R4A4Unorm,
+            Format::R8 => DxgiFormat::R8Unorm,
+            Format::R8G8 => DxgiFormat::R8G8Unorm,
+            Format::R16 => DxgiFormat::R16Unorm,
+            Format::R16G16 => DxgiFormat::R16G16Unorm,
+            Format::R16G16B16A16 => DxgiFormat::R16G16B16A16Unorm,
+            Format::R16Float => DxgiFormat::R16Float,
+            Format::R16G16B16A16Float => DxgiFormat::R16G16B16A16Float,
+            Format::R32Float => DxgiFormat::R32Float,
+            Format::R32G32Float => DxgiFormat::R32G32Float,
+            Format::R32G32B32Float => DxgiFormat::R32G32B32Float,
+            Format::R32G32B32A32Float => DxgiFormat::R32G32B32A32Float,
+            Format::R10G10B10A2 => DxgiFormat::R10G10B10A2Unorm,
+            Format::R9G9B9E5 => DxgiFormat::R9G9B9E5SharedExp,
+            Format::Bc1 => DxgiFormat::Bc1Unorm,
+            Format::Bc2 => DxgiFormat::Bc2Unorm,
+            Format::Bc3 => DxgiFormat::Bc3Unorm,
+            Format::Bc4Unsigned => DxgiFormat::Bc4Unorm,
+            Format::Bc4Signed => DxgiFormat::Bc4Snorm,
+            Format::Bc5Unsigned => DxgiFormat::Bc5Unorm,
+            Format::Bc5Signed => DxgiFormat::Bc5Snorm,
+            Format::Bc6hUnsigned => DxgiFormat::Bc6hUf16,
+            Format::Bc6hSigned => DxgiFormat::Bc6hSf16,
+            Format::Bc7 => DxgiFormat::Bc7Unorm,
+            Format::R8G8B8X8
+            | Format::B5G5R5X1
+            | Format::R8G8B8
+            | Format::B8G8R8
+            | Format::L8A8 => DxgiFormat::Unknown,
+        }
+    }
+
+    /// The number of bytes in each 4x4 block, or `None` if the format isn't block compressed.
+    pub(crate) fn block_size(self) -> Option<usize> {
+        match self {
+            Format::Bc1 | Format::Bc4Unsigned | Format::Bc4Signed => Some(8),
+            Format::Bc2
+            | Format::Bc3
+            | Format::Bc5Unsigned
+            | Format::Bc5Signed
+            | Format::Bc6hUnsigned
+            | Format::Bc6hSigned
+            | Format::Bc7 => Some(16),
+            Format::R8G8B8A8
+            | Format::R8G8B8X8
+            | Format::B8G8R8A8
+            | Format::B8G8R8X8
+            | Format::B5G6R5
+            | Format::B5G5R5A1
+            | Format::B5G5R5X1
+            | Format::B4G4R4A4
+            | Format::R8G8B8
+            | Format::B8G8R8
+            | Format::R8
+            | Format::R8G8
+            | Format::L8A8
+            | Format::R16
+            | Format::R16G16
+            | Format::R16G16B16A16
+            | Format::R16Float
+            | Format::R16G16B16A16Float
+            | Format::R32Float
+            | Format::R32G32Float
+            | Format::R32G32B32Float
+            | Format::R32G32B32A32Float
+            | Format::R10G10B10A2
+            | Format::R9G9B9E5 => None,
+        }
+    }
+
+    /// The number of bytes of each pixel, or `None` if the format is block compressed.
+    pub(crate) fn bytes_per_pixel(self) -> Option<usize> {
+        match self {
+            Format::R32G32B32A32Float => Some(16),
+            Format::R32G32B32Float => Some(12),
+            Format::R16G16B16A16 | Format::R16G16B16A16Float | Format::R32G32Float => Some(8),
+            Format::R8G8B8A8
+            | Format::R8G8B8X8
+            | Format::B8G8R8A8
+            | Format::B8G8R8X8
+            | Format::R16G16
+            | Format::R32Float
+            | Format::R10G10B10A2
+            | Format::R9G9B9E5 => Some(4),
+            Format::R8G8B8 | Format::B8G8R8 => Some(3),
+            Format::B5G6R5
+            | Format::B5G5R5A1
+            | Format::B5G5R5X1
+            | Format::B4G4R4A4
+            | Format::R8G8
+            | Format::L8A8
+            | Format::R16
+            | Format::R16Float => Some(2),
+            Format::R8 => Some(1),
+            _ => None,
+        }
+    }
+
+    /// The color type the format is decoded to.
+    pub(crate) fn color_type(self) -> ColorType {
+        match self {
+            Format::Bc4Unsigned | Format::Bc4Signed | Format::R8 => ColorType::L8,
+            Format::Bc5Unsigned | Format::Bc5Signed | Format::R8G8 => ColorType::Rgb8,
+            Format::Bc6hUnsigned
+            | Format::Bc6hSigned
+            | Format::R16Float
+            | Format::R32Float
+            | Format::R32G32Float
+            | Format::R9G9B9E5 => ColorType::Rgb32F,
+            Format::R16G16B16A16Float | Format::R32G32B32Float | Format::R32G32B32A32Float => {
+                ColorType::Rgba32F
+            }
+            Format::L8A8 => ColorType::La8,
+            Format::R16 => ColorType::L16,
+            Format::R16G16 => ColorType::Rgb16,
+            Format::R16G16B16A16 | Format::R10G10B10A2 => ColorType::Rgba16,
+            _ => ColorType::Rgba8,
+        }
+    }
+
+    /// The number of bytes a surface with the given dimensions takes up in the file.
+    pub(crate) fn surface_size(self, width: u32, height: u32) -> u64 {
+        match self.block_size() {
+            Some(block_size) => {
+                let blocks = u64::from(width.div_ceil(4)) * u64::from(height.div_ceil(4));
+                blocks * block_size as u64
+            }
+            None => {
+                let bytes_per_pixel = self.bytes_per_pixel().unwrap() as u64;
+                u64::from(width) * u64::from(height) * bytes_per_pixel
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fourcc_aliases() {
+        let cases = [
+            (*b"DXT1", Format::Bc1),
+            (*b"DXT2", Format::Bc2),
+            (*b"DXT3", Format::Bc2),
+            (*b"DXT4", Format::Bc3),
+            (*b"DXT5", Format::Bc3),
+            (*b"ATI1", Format::Bc4Unsigned),
+            (*b"BC4U", Format::Bc4Unsigned),
+            (*b"BC4S", Format::Bc4Signed),
+            (*b"ATI2", Format::Bc5Unsigned),
+            (*b"BC5U", Format::Bc5Unsigned),
+            (*b"BC5S", Format::Bc5Signed),
+            (20_u32.to_le_bytes(), Format::B8G8R8),
+            (21_u32.to_le_bytes(), Format::B8G8R8A8),
+            (22_u32.to_le_bytes(), Format::B8G8R8X8),
+            (32_u32.to_le_bytes(), Format::R8G8B8A8),
+            (33_u32.to_le_bytes(), Format::R8G8B8X8),
+            (23_u32.to_le_bytes(), Format::B5G6R5),
+            (24_u32.to_le_bytes(), Format::B5G5R5X1),
+            (25_u32.to_le_bytes(), Format::B5G5R5A1),
+            (26_u32.to_le_bytes(), Format::B4G4R4A4),
+            (50_u32.to_le_bytes(), Format::R8),
+            (111_u32.to_le_bytes(), Format::R16Float),
+            (113_u32.to_le_bytes(), Format::R16G16B16A16Float),
+            (114_u32.to_le_bytes(), Format::R32Float),
+            (115_u32.to_le_bytes(), Format::R32G32Float),
+            (116_u32.to_le_bytes(), Format::R32G32B32A32Float),
+        ];
+        for (code, format) in cases {
+            assert_eq!(Format::from_fourcc(code), Some(format), "{code:?}");
+        }
+
+        for code in [*b"DX10", *b"dxt1", *b"ATI3", [0; 4], 19_u32.to_le_bytes()] {
+            assert_eq!(Format::from_fourcc(code), None, "{code:?}");
+        }
+    }
+}
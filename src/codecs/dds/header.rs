@@ -0,0 +1,450 @@
+use crate::error::DecodingError;
+use crate::{ImageError, ImageFormat, ImageResult};
+use byteorder_lite::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+/// The magic number at the start of every DDS file.
+pub(crate) const MAGIC: [u8; 4] = *b"DDS ";
+
+/// The header contains `caps`. Required in every file.
+pub(crate) const DDSD_CAPS: u32 = 0x1;
+/// The header contains the height. Required in every file.
+pub(crate) const DDSD_HEIGHT: u32 = 0x2;
+/// The header contains the width. Required in every file.
+pub(crate) const DDSD_WIDTH: u32 = 0x4;
+/// The header contains the pitch of uncompressed surfaces in `pitch_or_linear_size`.
+pub(crate) const DDSD_PITCH: u32 = 0x8;
+/// The header contains the pixel format. Required in every file.
+pub(crate) const DDSD_PIXELFORMAT: u32 = 0x1000;
+/// The header contains the number of mip levels.
+pub(crate) const DDSD_MIPMAPCOUNT: u32 = 0x2_0000;
+/// The header contains the size of a compressed main surface in `pitch_or_linear_size`.
+pub(crate) const DDSD_LINEARSIZE: u32 = 0x8_0000;
+
+/// The pixel format contains alpha data in `a_bit_mask`.
+pub(crate) const DDPF_ALPHAPIXELS: u32 = 0x1;
+/// The pixel format is identified by `four_cc`.
+pub(crate) const DDPF_FOURCC: u32 = 0x4;
+/// The pixel format contains uncompressed RGB data described by the bit masks.
+pub(crate) const DDPF_RGB: u32 = 0x40;
+/// The pixel format contains uncompressed luminance data described by `r_bit_mask`, and alpha if
+/// `DDPF_ALPHAPIXELS` is set.
+pub(crate) const DDPF_LUMINANCE: u32 = 0x2_0000;
+
+/// The file contains more than one surface, e.g. mip levels.
+pub(crate) const DDSCAPS_COMPLEX: u32 = 0x8;
+/// The file contains a texture. Required in every file.
+pub(crate) const DDSCAPS_TEXTURE: u32 = 0x1000;
+/// The file contains mip levels.
+pub(crate) const DDSCAPS_MIPMAP: u32 = 0x40_0000;
+/// The file contains a cubemap.
+pub(crate) const DDSCAPS2_CUBEMAP: u32 = 0x200;
+/// The file contains a volume texture.
+pub(crate) const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+/// The resource dimension of 2D textures in the DX10 header.
+pub(crate) const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+/// The resource dimension of volume textures in the DX10 header.
+pub(crate) const D3D10_RESOURCE_DIMENSION_TEXTURE3D: u32 = 4;
+/// The DX10 header describes a cubemap.
+pub(crate) const D3D10_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/// The `DDS_PIXELFORMAT` structure.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PixelFormat {
+    pub(crate) flags: u32,
+    pub(crate) four_cc: [u8; 4],
+    pub(crate) rgb_bit_count: u32,
+    pub(crate) r_bit_mask: u32,
+    pub(crate) g_bit_mask: u32,
+    pub(crate) b_bit_mask: u32,
+    pub(crate) a_bit_mask: u32,
+}
+
+/// The header of a DDS file, as returned by [`read_header`].
+///
+/// The values are the ones stored in the file. Most of them are only meaningful if the matching
+/// flag is set, e.g. `depth` for volume textures and `mipmap_count` if the file has mip levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DdsHeader {
+    /// The width of the main surface in pixels.
+    pub width: u32,
+    /// The height of the main surface in pixels.
+    pub height: u32,
+    /// The depth of volume textures.
+    pub depth: u32,
+    /// The number of mip levels, including the main surface. Some writers store 0 for a single
+    /// level.
+    pub mipmap_count: u32,
+    /// The `DDSD_*` flags, which tell which fields are set.
+    pub flags: u32,
+    /// The pixel format of the surfaces.
+    pub pixel_format: DdsPixelFormat,
+    /// The `DDSCAPS_*` flags, e.g. 0x40_0000 if the file contains mip levels.
+    pub caps: u32,
+    /// The `DDSCAPS2_*` flags, e.g. 0x200 for cubemaps and 0x20_0000 for volume textures.
+    pub caps2: u32,
+    /// The DX10 header, if the FourCC of the pixel format is `"DX10"`.
+    pub dx10: Option<Dx10Header>,
+}
+
+/// The `DDS_PIXELFORMAT` structure of a [`DdsHeader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DdsPixelFormat {
+    /// The `DDPF_*` flags, e.g. 0x4 if the format is identified by `four_cc` and 0x40 for
+    /// uncompressed RGB data described by the bit masks.
+    pub flags: u32,
+    /// The FourCC, e.g. `*b"DXT1"`. Some files store a `D3DFORMAT` here instead, e.g. 36 for
+    /// `D3DFMT_A16B16G16R16`.
+    pub four_cc: [u8; 4],
+    /// The number of bits per pixel of uncompressed data.
+    pub rgb_bit_count: u32,
+    /// The bits of red in a pixel of uncompressed data.
+    pub r_bit_mask: u32,
+    /// The bits of green in a pixel of uncompressed data.
+    pub g_bit_mask: u32,
+    /// The bits of blue in a pixel of uncompressed data.
+    pub b_bit_mask: u32,
+    /// The bits of alpha in a pixel of uncompressed data.
+    pub a_bit_mask: u32,
+}
+
+/// Reads the header of a DDS file without decoding any surfaces.
+///
+/// This reads the magic number, the header, and the DX10 header if present, so `r` is left at the
+/// start of the surface data. Unlike [`DdsDecoder::new`](super::DdsDecoder::new), this doesn't
+/// check whether the format is supported, so it can be used to classify any DDS file.
+pub fn read_header<R: Read>(r: &mut R) -> ImageResult<DdsHeader> {
+    let header = Header::from_reader(r)?;
+    let pixel_format = header.pixel_format;
+    Ok(DdsHeader {
+        width: header.width,
+        height: header.height,
+        depth: header.depth,
+        mipmap_count: header.mipmap_count,
+        flags: header.flags,
+        pixel_format: DdsPixelFormat {
+            flags: pixel_format.flags,
+            four_cc: pixel_format.four_cc,
+            rgb_bit_count: pixel_format.rgb_bit_count,
+            r_bit_mask: pixel_format.r_bit_mask,
+            g_bit_mask: pixel_format.g_bit_mask,
+            b_bit_mask: pixel_format.b_bit_mask,
+            a_bit_mask: pixel_format.a_bit_mask,
+        },
+        caps: header.caps,
+        caps2: header.caps2,
+        dx10: header.dx10,
+    })
+}
+
+/// The `DDS_HEADER_DXT10` structure that follows the header if the FourCC is `"DX10"`.
+///
+/// Files with this header describe their format with a `DXGI_FORMAT` instead of a pixel format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dx10Header {
+    /// The `DXGI_FORMAT` of the surfaces, e.g. 71 for `DXGI_FORMAT_BC1_UNORM` and 72 for
+    /// `DXGI_FORMAT_BC1_UNORM_SRGB`.
+    pub dxgi_format: u32,
+    /// The `D3D10_RESOURCE_DIMENSION`, e.g. 3 for 2D textures.
+    pub resource_dimension: u32,
+    /// The `D3D10_RESOURCE_MISC_FLAG` flags, e.g. 0x4 for cubemaps.
+    pub misc_flag: u32,
+    /// The number of textures in the array. For cubemaps, this is the number of cubes.
+    pub array_size: u32,
+    /// The `DDS_ALPHA_MODE`, e.g. 2 for premultiplied alpha.
+    pub alpha_mode: u32,
+}
+
+impl Dx10Header {
+    /// The size of `DDS_HEADER_DXT10` in bytes.
+    pub(crate) const SIZE: u32 = 20;
+
+    fn from_reader(r: &mut dyn Read) -> ImageResult<Self> {
+        let dxgi_format = r.read_u32::<LittleEndian>()?;
+        let resource_dimension = r.read_u32::<LittleEndian>()?;
+        // Only 1D, 2D, and 3D textures are valid. 0 is unknown and 1 is a buffer.
+        if !(2..=4).contains(&resource_dimension) {
+            return Err(HeaderError::InvalidResourceDimension(resource_dimension).into());
+        }
+        let misc_flag = r.read_u32::<LittleEndian>()?;
+        let array_size = r.read_u32::<LittleEndian>()?;
+        if array_size == 0 {
+            return Err(HeaderError::ZeroArraySize.into());
+        }
+        // Only the lower 3 bits of the second misc flags are defined.
+        let alpha_mode = r.read_u32::<LittleEndian>()? & 0x7;
+
+        Ok(Self {
+            dxgi_format,
+            resource_dimension,
+            misc_flag,
+            array_size,
+            alpha_mode,
+        })
+    }
+
+    fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        for value in [
+            self.dxgi_format,
+            self.resource_dimension,
+            self.misc_flag,
+            self.array_size,
+            self.alpha_mode,
+        ] {
+            w.write_u32::<LittleEndian>(value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `DDS_HEADER` structure that follows the magic number.
+#[derive(Debug, Default, Clone, Copy)]
+#[allow(dead_code)] // not all fields are used yet
+pub(crate) struct Header {
+    pub(crate) flags: u32,
+    pub(crate) height: u32,
+    pub(crate) width: u32,
+    pub(crate) pitch_or_linear_size: u32,
+    pub(crate) depth: u32,
+    pub(crate) mipmap_count: u32,
+    pub(crate) pixel_format: PixelFormat,
+    pub(crate) caps: u32,
+    pub(crate) caps2: u32,
+    pub(crate) dx10: Option<Dx10Header>,
+}
+
+impl Header {
+    /// The size of `DDS_HEADER` in bytes.
+    pub(crate) const SIZE: u32 = 124;
+
+    /// The offset of the surface data from the start of the file, after the magic number, the
+    /// header, and the DX10 header if present.
+    pub(crate) fn data_offset(&self) -> u64 {
+        let dx10_size = if self.dx10.is_some() {
+            Dx10Header::SIZE
+        } else {
+            0
+        };
+        u64::from(MAGIC.len() as u32 + Self::SIZE + dx10_size)
+    }
+
+    /// Reads the magic number, the header, and the DX10 header if present.
+    pub(crate) fn from_reader(r: &mut dyn Read) -> ImageResult<Self> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(HeaderError::InvalidMagic(magic).into());
+        }
+
+        let size = r.read_u32::<LittleEndian>()?;
+        if size != Self::SIZE {
+            return Err(HeaderError::InvalidHeaderSize(size).into());
+        }
+
+        let flags = r.read_u32::<LittleEndian>()?;
+        let height = r.read_u32::<LittleEndian>()?;
+        let width = r.read_u32::<LittleEndian>()?;
+        if width == 0 || height == 0 {
+            return Err(HeaderError::ZeroDimensions(width, height).into());
+        }
+        let pitch_or_linear_size = r.read_u32::<LittleEndian>()?;
+        let depth = r.read_u32::<LittleEndian>()?;
+        let mipmap_count = r.read_u32::<LittleEndian>()?;
+        // A full mip chain of the largest possible surface has 32 levels.
+        if mipmap_count > 32 {
+            return Err(HeaderError::InvalidMipmapCount(mipmap_count).into());
+        }
+        r.read_exact(&mut [0; 44])?; // reserved
+
+        // The size of the pixel format isn't validated, because some writers get it wrong.
+        let _pixel_format_size = r.read_u32::<LittleEndian>()?;
+        let pixel_format = PixelFormat {
+            flags: r.read_u32::<LittleEndian>()?,
+            four_cc: r.read_u32::<LittleEndian>()?.to_le_bytes(),
+            rgb_bit_count: r.read_u32::<LittleEndian>()?,
+            r_bit_mask: r.read_u32::<LittleEndian>()?,
+            g_bit_mask: r.read_u32::<LittleEndian>()?,
+            b_bit_mask: r.read_u32::<LittleEndian>()?,
+            a_bit_mask: r.read_u32::<LittleEndian>()?,
+        };
+
+        let caps = r.read_u32::<LittleEndian>()?;
+        let caps2 = r.read_u32::<LittleEndian>()?;
+        r.read_exact(&mut [0; 12])?; // caps3, caps4, and reserved
+
+        let dx10 = if pixel_format.flags & DDPF_FOURCC != 0 && pixel_format.four_cc == *b"DX10" {
+            Some(Dx10Header::from_reader(r)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            flags,
+            height,
+            width,
+            pitch_or_linear_size,
+            depth,
+            mipmap_count,
+            pixel_format,
+            caps,
+            caps2,
+            dx10,
+        })
+    }
+
+    /// Writes the magic number, the header, and the DX10 header if present.
+    ///
+    /// The flags are written as they are, so they have to match the fields that are set.
+    pub(crate) fn write_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        w.write_all(&MAGIC)?;
+        for value in [
+            Self::SIZE,
+            self.flags,
+            self.height,
+            self.width,
+            self.pitch_or_linear_size,
+            self.depth,
+            self.mipmap_count,
+        ] {
+            w.write_u32::<LittleEndian>(value)?;
+        }
+        w.write_all(&[0; 44])?; // reserved
+
+        let pixel_format = &self.pixel_format;
+        for value in [
+            32, // the size of the pixel format
+            pixel_format.flags,
+            u32::from_le_bytes(pixel_format.four_cc),
+            pixel_format.rgb_bit_count,
+            pixel_format.r_bit_mask,
+            pixel_format.g_bit_mask,
+            pixel_format.b_bit_mask,
+            pixel_format.a_bit_mask,
+        ] {
+            w.write_u32::<LittleEndian>(value)?;
+        }
+
+        w.write_u32::<LittleEndian>(self.caps)?;
+        w.write_u32::<LittleEndian>(self.caps2)?;
+        w.write_all(&[0; 12])?; // caps3, caps4, and reserved
+
+        if let Some(dx10) = &self.dx10 {
+            dx10.write_to(w)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors that can occur while reading the header of a DDS file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HeaderError {
+    /// The file doesn't start with `"DDS "`.
+    InvalidMagic([u8; 4]),
+    /// The size field of `DDS_HEADER` isn't 124.
+    InvalidHeaderSize(u32),
+    /// The width or height of the main surface is 0.
+    ZeroDimensions(u32, u32),
+    /// The header claims more mip levels than a full mip chain has.
+    InvalidMipmapCount(u32),
+    /// The resource dimension of the DX10 header isn't a texture.
+    InvalidResourceDimension(u32),
+    /// The array size of the DX10 header is 0.
+    ZeroArraySize,
+}
+
+impl std::fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderError::InvalidMagic(magic) => {
+                f.write_fmt(format_args!("Invalid DDS signature {magic:?}"))
+            }
+            HeaderError::InvalidHeaderSize(size) => {
+                f.write_fmt(format_args!("Invalid DDS header size {size}"))
+            }
+            HeaderError::ZeroDimensions(width, height) => {
+                f.write_fmt(format_args!("Invalid DDS dimensions {width}x{height}"))
+            }
+            HeaderError::InvalidMipmapCount(count) => {
+                f.write_fmt(format_args!("Invalid DDS mip count {count}"))
+            }
+            HeaderError::InvalidResourceDimension(dimension) => {
+                f.write_fmt(format_args!("Invalid DX10 resource dimension {dimension}"))
+            }
+            HeaderError::ZeroArraySize => f.write_str("DX10 array size is 0"),
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+impl From<HeaderError> for ImageError {
+    fn from(e: HeaderError) -> ImageError {
+        ImageError::Decoding(DecodingError::new(ImageFormat::Dds.into(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_little_endian() {
+        // Each field's bytes are written out, so the expected values don't depend on the
+        // endianness of the host.
+        let mut file = Vec::new();
+        file.extend(b"DDS ");
+        file.extend([124, 0, 0, 0]); // size
+        file.extend([0x07, 0x10, 0x0A, 0x00]); // flags
+        file.extend([0x02, 0x01, 0x00, 0x00]); // height
+        file.extend([0x04, 0x03, 0x02, 0x00]); // width
+        file.extend([0x08, 0x07, 0x06, 0x05]); // pitch_or_linear_size
+        file.extend([0x01, 0x00, 0x00, 0x00]); // depth
+        file.extend([0x0B, 0x00, 0x00, 0x00]); // mipmap_count
+        file.extend([0; 44]);
+        file.extend([32, 0, 0, 0]); // pixel format size
+        file.extend([0x04, 0x00, 0x00, 0x00]); // pixel format flags
+        file.extend(b"DX10");
+        file.extend([0x20, 0x00, 0x00, 0x00]); // rgb_bit_count
+        file.extend([0x00, 0x00, 0xFF, 0x00]); // r_bit_mask
+        file.extend([0x00, 0xFF, 0x00, 0x00]); // g_bit_mask
+        file.extend([0xFF, 0x00, 0x00, 0x00]); // b_bit_mask
+        file.extend([0x00, 0x00, 0x00, 0xFF]); // a_bit_mask
+        file.extend([0x08, 0x10, 0x40, 0x00]); // caps
+        file.extend([0x00, 0xFE, 0x00, 0x00]); // caps2
+        file.extend([0; 12]);
+        file.extend([0x62, 0x00, 0x00, 0x00]); // dxgi_format
+        file.extend([0x03, 0x00, 0x00, 0x00]); // resource_dimension
+        file.extend([0x04, 0x00, 0x00, 0x00]); // misc_flag
+        file.extend([0x06, 0x01, 0x00, 0x00]); // array_size
+        file.extend([0x02, 0x00, 0x00, 0x00]); // alpha_mode
+
+        let header = Header::from_reader(&mut file.as_slice()).unwrap();
+        assert_eq!(header.flags, 0x000A_1007);
+        assert_eq!(header.height, 0x0102);
+        assert_eq!(header.width, 0x02_0304);
+        assert_eq!(header.pitch_or_linear_size, 0x0506_0708);
+        assert_eq!(header.depth, 1);
+        assert_eq!(header.mipmap_count, 11);
+        let pixel_format = header.pixel_format;
+        assert_eq!(pixel_format.flags, DDPF_FOURCC);
+        assert_eq!(pixel_format.four_cc, *b"DX10");
+        assert_eq!(pixel_format.rgb_bit_count, 32);
+        assert_eq!(pixel_format.r_bit_mask, 0x00FF_0000);
+        assert_eq!(pixel_format.g_bit_mask, 0x0000_FF00);
+        assert_eq!(pixel_format.b_bit_mask, 0x0000_00FF);
+        assert_eq!(pixel_format.a_bit_mask, 0xFF00_0000);
+        assert_eq!(header.caps, 0x0040_1008);
+        assert_eq!(header.caps2, 0xFE00);
+        let dx10 = header.dx10.unwrap();
+        assert_eq!(dx10.dxgi_format, 98);
+        assert_eq!(dx10.resource_dimension, D3D10_RESOURCE_DIMENSION_TEXTURE2D);
+        assert_eq!(dx10.misc_flag, D3D10_RESOURCE_MISC_TEXTURECUBE);
+        assert_eq!(dx10.array_size, 0x0106);
+        assert_eq!(dx10.alpha_mode, 2);
+
+        // Writing the header gives the same bytes back.
+        let mut written = Vec::new();
+        header.write_to(&mut written).unwrap();
+        assert_eq!(written, file);
+    }
+}
@@ -0,0 +1,43 @@
+//! Decoding and encoding of DDS images
+//!
+//! DDS (DirectDraw Surface) is a container format for GPU textures. Most DDS files store their
+//! surfaces in one of the block compressed (BC) formats, where each 4x4 block of pixels is encoded
+//! into a fixed number of bytes.
+//!
+//! All multi-byte values in DDS files, from header fields to the channels of uncompressed pixels,
+//! are little endian. They are read and written as such on every host, so decoding gives the same
+//! results on big-endian targets. Decoded pixels with more than 8 bits per channel are returned in
+//! native endianness, like for all other [`ImageDecoder`](crate::ImageDecoder)s.
+//!
+//! # Related Links
+//! * <https://learn.microsoft.com/en-us/windows/win32/direct3ddds/dx-graphics-dds-pguide>
+//! * <https://learn.microsoft.com/en-us/windows/win32/direct3d11/texture-block-compression-in-direct3d-11>
+
+pub use self::bc_encoder::EncodeQuality;
+pub use self::decoder::{
+    decoded_len, AlphaHandling, AlphaMode, Bc1PunchThrough, Bc1Stats, BlockDecoder, Cancelled,
+    CubeFace, DdsDecoder, DdsMetadata, MipLevel, MipLevels, Mipmaps, Progress, RawSurface,
+    SrgbHandling, SubresourceInfo, Swizzle, SwizzleSource,
+};
+pub use self::dxgi::DxgiFormat;
+pub use self::encoder::{DdsEncoder, MipFilter};
+pub use self::header::{read_header, DdsHeader, DdsPixelFormat, Dx10Header};
+pub use self::random_access::RandomAccessDecoder;
+
+mod bc;
+#[cfg(feature = "simd")]
+mod bc1_batch;
+#[cfg(feature = "simd")]
+mod bc4_batch;
+mod bc7_tables;
+mod bc_encoder;
+pub mod blocks;
+mod convert;
+mod decoder;
+mod dxgi;
+mod encoder;
+mod format;
+mod header;
+mod random_access;
+mod surface;
+pub mod tonemap;
@@ -0,0 +1,262 @@
+//! Random access to the pixels of block compressed surfaces.
+
+use std::collections::HashMap;
+
+use super::bc;
+use super::convert::bc4_to_rgba;
+use super::format::Format;
+
+/// The number of blocks [`RandomAccessDecoder`] caches by default.
+const DEFAULT_CACHE_SIZE: usize = 256;
+
+/// Decodes the bytes of one block into its pixels.
+pub(crate) type DecodeBlock = fn(&[u8]) -> [[u8; 4]; 16];
+
+/// Decodes single pixels of a block compressed surface, e.g. for sampling a large texture.
+///
+/// Created by [`DdsDecoder::into_random_access`](super::DdsDecoder::into_random_access). The
+/// compressed surface is kept in memory, and the most recently used blocks are cached after
+/// decoding them, so looking up pixels close to each other only decodes each block once.
+pub struct RandomAccessDecoder {
+    width: u32,
+    height: u32,
+    blocks_x: usize,
+    block_size: usize,
+    data: Vec<u8>,
+    decode_block: DecodeBlock,
+    cache: BlockCache,
+    decoded_blocks: u64,
+}
+
+/// Returns the function that decodes a block of the format to RGBA, or `None` if the format isn't
+/// block compressed or doesn't decode to 8 bits per channel.
+pub(crate) fn rgba8_block_decoder(format: Format) -> Option<DecodeBlock> {
+    let decode_block: DecodeBlock = match format {
+        Format::Bc1 => |block| bc::decode_bc1_block(block.try_into().unwrap()),
+        Format::Bc2 => |block| bc::decode_bc2_block(block.try_into().unwrap()),
+        Format::Bc3 => |block| bc::decode_bc3_block(block.try_into().unwrap()),
+        Format::Bc4Unsigned => {
+            |block| bc4_to_rgba(bc::decode_bc4_unsigned_block(block.try_into().unwrap()))
+        }
+        Format::Bc4Signed => {
+            |block| bc4_to_rgba(bc::decode_bc4_signed_block(block.try_into().unwrap()))
+        }
+        Format::Bc5Unsigned => |block| {
+            bc::decode_bc5_unsigned_block(block.try_into().unwrap()).map(|[r, g, b]| [r, g, b, 255])
+        },
+        Format::Bc5Signed => |block| {
+            bc::decode_bc5_signed_block(block.try_into().unwrap()).map(|[r, g, b]| [r, g, b, 255])
+        },
+        Format::Bc7 => |block| bc::decode_bc7_block(block.try_into().unwrap()),
+        _ => return None,
+    };
+    Some(decode_block)
+}
+
+impl RandomAccessDecoder {
+    /// `decode_block` has to be the [block decoder](rgba8_block_decoder) of the format.
+    pub(crate) fn new(
+        format: Format,
+        decode_block: DecodeBlock,
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            blocks_x: width.div_ceil(4) as usize,
+            block_size: format.block_size().unwrap(),
+            data,
+            decode_block,
+            cache: BlockCache::new(DEFAULT_CACHE_SIZE),
+            decoded_blocks: 0,
+        }
+    }
+
+    /// The dimensions of the surface.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Sets the number of decoded blocks to cache. 0 disables the cache.
+    ///
+    /// Each cached block takes up 64 bytes. The default is 256 blocks. Changing the size clears
+    /// the cache.
+    pub fn set_cache_size(&mut self, blocks: usize) {
+        self.cache = BlockCache::new(blocks);
+    }
+
+    /// The number of blocks decoded so far.
+    ///
+    /// Blocks that were evicted from the cache and are looked up again are decoded again, so this
+    /// shows how well the cache size fits the access pattern.
+    pub fn decoded_blocks(&self) -> u64 {
+        self.decoded_blocks
+    }
+
+    /// Returns the RGBA pixel at `(x, y)`.
+    ///
+    /// BC4 is returned as gray, and BC5 with blue 0 for unsigned and 128 for signed surfaces, like
+    /// [`ImageDecoder::read_image`](crate::ImageDecoder::read_image) with the default settings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is out of bounds.
+    pub fn pixel(&mut self, x: u32, y: u32) -> [u8; 4] {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({x}, {y}) is out of bounds of a {}x{} surface",
+            self.width,
+            self.height
+        );
+        let block = (y / 4) as usize * self.blocks_x + (x / 4) as usize;
+        let pixels = match self.cache.get(block) {
+            Some(pixels) => pixels,
+            None => {
+                let start = block * self.block_size;
+                let pixels = (self.decode_block)(&self.data[start..start + self.block_size]);
+                self.decoded_blocks += 1;
+                self.cache.insert(block, pixels);
+                pixels
+            }
+        };
+        pixels[(y % 4 * 4 + x % 4) as usize]
+    }
+}
+
+/// Marks the end of the list of entries of a [`BlockCache`].
+const NONE: usize = usize::MAX;
+
+/// A least recently used cache of decoded blocks, keyed by the index of the block.
+///
+/// The entries form a doubly linked list from the most to the least recently used one, so both
+/// lookups and evictions take constant time.
+struct BlockCache {
+    capacity: usize,
+    /// The index of the entry of each cached block.
+    map: HashMap<usize, usize>,
+    entries: Vec<Entry>,
+    head: usize,
+    tail: usize,
+}
+
+struct Entry {
+    block: usize,
+    pixels: [[u8; 4]; 16],
+    prev: usize,
+    next: usize,
+}
+
+impl BlockCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            map: HashMap::new(),
+            entries: Vec::new(),
+            head: NONE,
+            tail: NONE,
+        }
+    }
+
+    /// Returns the pixels of the block and marks it as the most recently used one.
+    fn get(&mut self, block: usize) -> Option<[[u8; 4]; 16]> {
+        let &entry = self.map.get(&block)?;
+        if entry != self.head {
+            self.unlink(entry);
+            self.push_front(entry);
+        }
+        Some(self.entries[entry].pixels)
+    }
+
+    /// Adds a block that isn't cached yet, evicting the least recently used one if the cache is
+    /// full.
+    fn insert(&mut self, block: usize, pixels: [[u8; 4]; 16]) {
+        if self.capacity == 0 {
+            return;
+        }
+        let entry = if self.entries.len() < self.capacity {
+            self.entries.push(Entry {
+                block,
+                pixels,
+                prev: NONE,
+                next: NONE,
+            });
+            self.entries.len() - 1
+        } else {
+            let entry = self.tail;
+            self.unlink(entry);
+            self.map.remove(&self.entries[entry].block);
+            self.entries[entry].block = block;
+            self.entries[entry].pixels = pixels;
+            entry
+        };
+        self.map.insert(block, entry);
+        self.push_front(entry);
+    }
+
+    fn unlink(&mut self, entry: usize) {
+        let Entry { prev, next, .. } = self.entries[entry];
+        match prev {
+            NONE => self.head = next,
+            prev => self.entries[prev].next = next,
+        }
+        match next {
+            NONE => self.tail = prev,
+            next => self.entries[next].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, entry: usize) {
+        self.entries[entry].prev = NONE;
+        self.entries[entry].next = self.head;
+        match self.head {
+            NONE => self.tail = entry,
+            head => self.entries[head].prev = entry,
+        }
+        self.head = entry;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(cache: &mut BlockCache, block: usize) -> bool {
+        cache.get(block).is_some()
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let pixels = |block: usize| [[block as u8; 4]; 16];
+        let mut cache = BlockCache::new(3);
+        for block in [0, 1, 2] {
+            cache.insert(block, pixels(block));
+        }
+        // 0 is used again, so 1 is the least recently used block.
+        assert_eq!(cache.get(0), Some(pixels(0)));
+        cache.insert(3, pixels(3));
+        assert!(!cached(&mut cache, 1));
+        // The order is now 3, 0, 2, and looking up 2 makes 0 the least recently used block.
+        assert!(cached(&mut cache, 2));
+        cache.insert(4, pixels(4));
+        assert!(!cached(&mut cache, 0));
+        for block in [2, 3, 4] {
+            assert_eq!(cache.get(block), Some(pixels(block)));
+        }
+        assert_eq!(cache.entries.len(), 3);
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut cache = BlockCache::new(0);
+        cache.insert(0, [[0; 4]; 16]);
+        assert!(!cached(&mut cache, 0));
+
+        let mut cache = BlockCache::new(1);
+        cache.insert(0, [[0; 4]; 16]);
+        cache.insert(1, [[1; 4]; 16]);
+        assert!(!cached(&mut cache, 0));
+        assert_eq!(cache.get(1), Some([[1; 4]; 16]));
+    }
+}
@@ -0,0 +1,390 @@
+//! Assembling decoded blocks into surfaces, and splitting surfaces into blocks to encode them.
+
+use bytemuck::Pod;
+
+/// Decodes the blocks of a block compressed surface into `buf`.
+///
+/// `data` holds the `N`-byte blocks of the surface in row-major order. Each decoded pixel is
+/// written as `C` channels of `T` in native endianness, so `buf` has to be
+/// `width * height * size_of::<[T; C]>()` bytes long. If the width or height
+/// isn't a multiple of 4, the blocks at the right and bottom edges extend past the surface, and
+/// their extra pixels are discarded.
+///
+/// With the `rayon` feature, large surfaces are decoded in parallel.
+pub(crate) fn decode_blocks<const N: usize, T: Pod, const C: usize>(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    buf: &mut [u8],
+    decode_block: fn([u8; N]) -> [[T; C]; 16],
+) {
+    let width = width as usize;
+    let height = height as usize;
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let blocks_per_row = width.div_ceil(4);
+    debug_assert_eq!(data.len(), blocks_per_row * height.div_ceil(4) * N);
+    let pixel_bytes = size_of::<[T; C]>();
+    debug_assert_eq!(buf.len(), width * height * pixel_bytes);
+
+    let row_bytes = width * pixel_bytes;
+    for_each_block_row(
+        data,
+        blocks_per_row * N,
+        buf,
+        row_bytes * 4,
+        |blocks, rows| {
+            for (block_x, &block) in blocks.as_chunks::<N>().0.iter().enumerate() {
+                let pixels = decode_block(block);
+                write_block(rows, width, block_x, &pixels);
+            }
+        },
+    );
+}
+
+/// Surfaces with at least this many bytes of blocks are decoded in parallel. Smaller surfaces,
+/// like most mip levels, aren't worth distributing across threads.
+#[cfg(feature = "rayon")]
+const PARALLEL_THRESHOLD: usize = 1 << 16;
+
+/// Calls `decode_row` with each row of blocks and the rows of pixels it covers.
+///
+/// `buf` is split into chunks of `pixel_rows_len` bytes. The last chunk is shorter if the height
+/// isn't a multiple of 4. The chunks don't overlap, so with the `rayon` feature, the rows of
+/// large surfaces are decoded in parallel without any synchronization.
+fn for_each_block_row(
+    data: &[u8],
+    block_row_len: usize,
+    buf: &mut [u8],
+    pixel_rows_len: usize,
+    decode_row: impl Fn(&[u8], &mut [u8]) + Sync,
+) {
+    #[cfg(feature = "rayon")]
+    if data.len() >= PARALLEL_THRESHOLD {
+        use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+        use rayon::slice::{ParallelSlice, ParallelSliceMut};
+
+        data.par_chunks_exact(block_row_len)
+            .zip(buf.par_chunks_mut(pixel_rows_len))
+            .for_each(|(blocks, rows)| decode_row(blocks, rows));
+        return;
+    }
+
+    for (blocks, rows) in data
+        .chunks_exact(block_row_len)
+        .zip(buf.chunks_mut(pixel_rows_len))
+    {
+        decode_row(blocks, rows);
+    }
+}
+
+/// The decoded pixels of a block.
+#[cfg(feature = "simd")]
+type BlockPixels<T, const C: usize> = [[T; C]; 16];
+
+/// Like [`decode_blocks`], but decodes `B` blocks of each block row at once with `decode_batch`.
+///
+/// The remaining blocks of each row are decoded with `decode_block`, which has to produce the
+/// same pixels.
+#[cfg(feature = "simd")]
+pub(crate) fn decode_blocks_batched<const N: usize, T: Pod, const C: usize, const B: usize>(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    buf: &mut [u8],
+    decode_batch: fn([[u8; N]; B]) -> [BlockPixels<T, C>; B],
+    decode_block: fn([u8; N]) -> [[T; C]; 16],
+) {
+    let width = width as usize;
+    let height = height as usize;
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let blocks_per_row = width.div_ceil(4);
+    debug_assert_eq!(data.len(), blocks_per_row * height.div_ceil(4) * N);
+    let row_bytes = width * size_of::<[T; C]>();
+    debug_assert_eq!(buf.len(), height * row_bytes);
+
+    for_each_block_row(
+        data,
+        blocks_per_row * N,
+        buf,
+        row_bytes * 4,
+        |blocks, rows| {
+            let (batches, remainder) = blocks.as_chunks::<N>().0.as_chunks::<B>();
+            for (batch_index, &batch) in batches.iter().enumerate() {
+                for (i, pixels) in decode_batch(batch).iter().enumerate() {
+                    write_block(rows, width, batch_index * B + i, pixels);
+                }
+            }
+            let first = blocks_per_row - remainder.len();
+            for (i, &block) in remainder.iter().enumerate() {
+                let pixels = decode_block(block);
+                write_block(rows, width, first + i, &pixels);
+            }
+        },
+    );
+}
+
+/// Writes the pixels of the block in column `block_x` into up to 4 rows of a surface.
+///
+/// Pixels past the right or bottom edge of the surface are discarded.
+fn write_block<T: Pod, const C: usize>(
+    rows: &mut [u8],
+    width: usize,
+    block_x: usize,
+    pixels: &[[T; C]; 16],
+) {
+    let pixel_bytes = size_of::<[T; C]>();
+    let row_bytes = width * pixel_bytes;
+    let x = block_x * 4;
+    let block_width = (width - x).min(4);
+    for (y, row) in rows.chunks_exact_mut(row_bytes).enumerate() {
+        let row = &mut row[x * pixel_bytes..(x + block_width) * pixel_bytes];
+        for (pixel, decoded) in row.chunks_exact_mut(pixel_bytes).zip(&pixels[y * 4..]) {
+            pixel.copy_from_slice(bytemuck::cast_slice(decoded));
+        }
+    }
+}
+
+/// Encodes a surface of `C`-channel pixels into `N`-byte blocks in row-major order.
+///
+/// `pixels` holds the `width * height` pixels of the surface in row-major order. If the width or
+/// height isn't a multiple of 4, the blocks at the right and bottom edges are filled by repeating
+/// the last column and row of the surface, so they don't waste precision on pixels no one sees.
+///
+/// With the `rayon` feature, large surfaces are encoded in parallel.
+pub(crate) fn encode_blocks<const N: usize, const C: usize>(
+    pixels: &[[u8; C]],
+    width: u32,
+    height: u32,
+    encode_block: impl Fn([[u8; C]; 16]) -> [u8; N] + Sync,
+) -> Vec<u8> {
+    let width = width as usize;
+    let height = height as usize;
+    debug_assert_eq!(pixels.len(), width * height);
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    let blocks_per_row = width.div_ceil(4);
+    let mut data = vec![0; blocks_per_row * height.div_ceil(4) * N];
+    let encode_row = |block_y: usize, row: &mut [u8]| {
+        for (block_x, block) in row.as_chunks_mut::<N>().0.iter_mut().enumerate() {
+            *block = encode_block(std::array::from_fn(|i| {
+                let x = (block_x * 4 + i % 4).min(width - 1);
+                let y = (block_y * 4 + i / 4).min(height - 1);
+                pixels[y * width + x]
+            }));
+        }
+    };
+
+    #[cfg(feature = "rayon")]
+    if pixels.len() * C >= PARALLEL_THRESHOLD {
+        use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+        use rayon::slice::ParallelSliceMut;
+
+        data.par_chunks_mut(blocks_per_row * N)
+            .enumerate()
+            .for_each(|(block_y, row)| encode_row(block_y, row));
+        return data;
+    }
+
+    for (block_y, row) in data.chunks_mut(blocks_per_row * N).enumerate() {
+        encode_row(block_y, row);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A block is a single byte, and each pixel is the block byte and the index of the pixel
+    /// within the block.
+    fn decode_test_block([id]: [u8; 1]) -> [[u8; 2]; 16] {
+        std::array::from_fn(|i| [id, i as u8])
+    }
+
+    fn check_surface(width: u32, height: u32) {
+        let blocks_per_row = width.div_ceil(4);
+        let blocks = blocks_per_row * height.div_ceil(4);
+        let data: Vec<u8> = (0..blocks as u8).collect();
+        let mut buf = vec![0xFF; (width * height * 2) as usize];
+        decode_blocks(&data, width, height, &mut buf, decode_test_block);
+
+        for y in 0..height {
+            for x in 0..width {
+                let i = ((y * width + x) * 2) as usize;
+                let id = (y / 4 * blocks_per_row + x / 4) as u8;
+                let index = (y % 4 * 4 + x % 4) as u8;
+                assert_eq!(buf[i..i + 2], [id, index], "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn encode_partial_blocks() {
+        // Each pixel is its position, and each block is encoded as its 16 pixels.
+        for (width, height) in [(4, 4), (1, 1), (5, 3), (10, 6), (3, 9)] {
+            let pixels: Vec<[u8; 2]> = (0..height)
+                .flat_map(|y| (0..width).map(move |x| [x as u8, y as u8]))
+                .collect();
+            let data = encode_blocks(&pixels, width, height, |block: [[u8; 2]; 16]| {
+                bytemuck::cast::<_, [u8; 32]>(block)
+            });
+
+            let blocks_per_row = width.div_ceil(4);
+            assert_eq!(data.len() as u32, blocks_per_row * height.div_ceil(4) * 32);
+            for (i, block) in data.chunks_exact(32).enumerate() {
+                let (block_x, block_y) = (i as u32 % blocks_per_row, i as u32 / blocks_per_row);
+                for (j, pixel) in block.chunks_exact(2).enumerate() {
+                    let x = (block_x * 4 + j as u32 % 4).min(width - 1);
+                    let y = (block_y * 4 + j as u32 / 4).min(height - 1);
+                    assert_eq!(pixel, [x as u8, y as u8], "{width}x{height} block {i}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn multiple_of_4() {
+        check_surface(4, 4);
+        check_surface(8, 4);
+        check_surface(12, 16);
+    }
+
+    #[test]
+    fn partial_blocks() {
+        check_surface(1, 1);
+        check_surface(2, 3);
+        check_surface(5, 5);
+        check_surface(10, 6);
+        check_surface(3, 9);
+    }
+
+    #[test]
+    fn wide_channels() {
+        fn decode_f32_block([id]: [u8; 1]) -> [[f32; 2]; 16] {
+            std::array::from_fn(|i| [f32::from(id), i as f32 / 16.0])
+        }
+
+        let mut pixels = [0.0_f32; 5 * 2 * 2];
+        let buf = bytemuck::cast_slice_mut(&mut pixels);
+        decode_blocks(&[1, 2], 5, 2, buf, decode_f32_block);
+        assert_eq!(
+            pixels[..8],
+            [1.0, 0.0, 1.0, 1.0 / 16.0, 1.0, 2.0 / 16.0, 1.0, 3.0 / 16.0]
+        );
+        assert_eq!(pixels[8..10], [2.0, 0.0]);
+        assert_eq!(pixels[10..12], [1.0, 4.0 / 16.0]);
+        assert_eq!(pixels[18..], [2.0, 4.0 / 16.0]);
+    }
+
+    #[test]
+    #[cfg(feature = "simd")]
+    fn batched() {
+        // Decodes 3 blocks at once and marks the pixels of batches.
+        fn decode_test_batch(blocks: [[u8; 1]; 3]) -> [[[u8; 2]; 16]; 3] {
+            blocks.map(|block| decode_test_block(block).map(|[id, i]| [id, i | 0x80]))
+        }
+
+        for (width, height) in [(1_u32, 1_u32), (12, 4), (13, 5), (30, 9), (44, 8)] {
+            let blocks = width.div_ceil(4) * height.div_ceil(4);
+            let data: Vec<u8> = (0..blocks as u8).collect();
+            let size = (width * height * 2) as usize;
+            let (mut expected, mut buf) = (vec![0xFF; size], vec![0xFF; size]);
+            decode_blocks(&data, width, height, &mut expected, decode_test_block);
+            decode_blocks_batched(
+                &data,
+                width,
+                height,
+                &mut buf,
+                decode_test_batch,
+                decode_test_block,
+            );
+
+            let blocks_per_row = width.div_ceil(4);
+            for (i, (&pixel, &expected)) in buf.iter().zip(&expected).enumerate() {
+                let x = (i as u32 / 2) % width;
+                let batched = x / 4 < blocks_per_row / 3 * 3;
+                let expected = if batched && i % 2 == 1 {
+                    expected | 0x80
+                } else {
+                    expected
+                };
+                assert_eq!(pixel, expected, "{width}x{height} byte {i}");
+            }
+        }
+    }
+
+    /// Decodes the surface one block at a time, without any of the row handling of
+    /// [`decode_blocks`].
+    fn decode_one_by_one<const N: usize, T: Pod, const C: usize>(
+        data: &[u8],
+        width: usize,
+        height: usize,
+        decode_block: fn([u8; N]) -> [[T; C]; 16],
+    ) -> Vec<u8> {
+        let pixel_bytes = size_of::<[T; C]>();
+        let mut buf = vec![0; width * height * pixel_bytes];
+        let blocks_per_row = width.div_ceil(4);
+        for (i, block) in data.chunks_exact(N).enumerate() {
+            let pixels = decode_block(block.try_into().unwrap());
+            let (block_x, block_y) = (i % blocks_per_row * 4, i / blocks_per_row * 4);
+            for (j, pixel) in pixels.iter().enumerate() {
+                let (x, y) = (block_x + j % 4, block_y + j / 4);
+                if x < width && y < height {
+                    let start = (y * width + x) * pixel_bytes;
+                    buf[start..start + pixel_bytes].copy_from_slice(bytemuck::cast_slice(pixel));
+                }
+            }
+        }
+        buf
+    }
+
+    #[test]
+    fn large_surfaces() {
+        // Large enough to be decoded in parallel with the rayon feature
+        fn check<const N: usize, T: Pod, const C: usize>(
+            width: usize,
+            height: usize,
+            decode_block: fn([u8; N]) -> [[T; C]; 16],
+        ) {
+            let blocks = width.div_ceil(4) * height.div_ceil(4);
+            let mut state = 0x2545_F491_4F6C_DD1D_u64;
+            let data: Vec<u8> = (0..blocks * N)
+                .map(|_| {
+                    // xorshift64
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect();
+
+            let mut buf = vec![0; width * height * size_of::<[T; C]>()];
+            decode_blocks(&data, width as u32, height as u32, &mut buf, decode_block);
+            let expected = decode_one_by_one(&data, width, height, decode_block);
+            assert!(buf == expected, "{width}x{height}");
+        }
+
+        use crate::codecs::dds::bc;
+        check(1024, 512, bc::decode_bc1_block);
+        check(1001, 333, bc::decode_bc1_block);
+        check(517, 301, bc::decode_bc3_block);
+        check(300, 443, bc::decode_bc5_unsigned_block);
+        check(257, 258, bc::decode_bc6h_unsigned_block_f32);
+        check(599, 129, bc::decode_bc7_block);
+    }
+
+    #[test]
+    fn empty() {
+        decode_blocks(&[], 0, 0, &mut [], decode_test_block);
+        decode_blocks(&[], 0, 5, &mut [], decode_test_block);
+        decode_blocks(&[], 5, 0, &mut [], decode_test_block);
+    }
+}
@@ -0,0 +1,113 @@
+//! Tone mapping of HDR colors to 8-bit sRGB for display.
+//!
+//! BC6H blocks decode to linear RGB floats, which can be far greater than 1.0. These functions
+//! compress such colors into the displayable range and encode the result as sRGB. Each takes an
+//! `exposure` that the color is multiplied by first, so 1.0 leaves it unchanged and 2.0 is one
+//! stop brighter. Negative and NaN components are treated as 0.
+//!
+//! # Examples
+//! ```rust
+//! use image::codecs::dds::{blocks, tonemap};
+//!
+//! // A BC6H_UF16 block of mode 1 with black endpoints
+//! let hdr = blocks::decode_bc6h_unsigned_f32([0; 16]);
+//! let display = hdr.map(|rgb| tonemap::aces_filmic(rgb, 1.0));
+//! assert_eq!(display, [[0; 3]; 16]);
+//!
+//! // Reinhard maps 1.0 to 0.5 and 3.0 to 0.75 before encoding them as sRGB.
+//! assert_eq!(tonemap::reinhard([1.0, 3.0, 0.0], 1.0), [188, 225, 0]);
+//! ```
+
+use super::convert::linear_f32_to_srgb8;
+
+/// Components above this are clamped before tone mapping, so infinity doesn't turn into NaN. Both
+/// curves are within rounding of 1.0 long before this.
+const MAX_INPUT: f32 = 65504.0;
+
+/// Scales a component by the exposure and clamps it to `0.0..=MAX_INPUT`. NaN becomes 0.
+fn expose(x: f32, exposure: f32) -> f32 {
+    let x = x * exposure;
+    if x.is_nan() {
+        0.0
+    } else {
+        x.clamp(0.0, MAX_INPUT)
+    }
+}
+
+/// Tone maps a linear RGB color with the Reinhard operator `x / (1 + x)` per component.
+///
+/// The curve is gentle, so it keeps the colors of bright areas but makes the image look flat.
+pub fn reinhard(rgb: [f32; 3], exposure: f32) -> [u8; 3] {
+    rgb.map(|x| {
+        let x = expose(x, exposure);
+        linear_f32_to_srgb8(x / (1.0 + x))
+    })
+}
+
+/// Tone maps a linear RGB color with Krzysztof Narkowicz's fit of the ACES filmic curve per
+/// component.
+///
+/// Compared to [`reinhard`], the curve has more contrast and saturates bright colors to white,
+/// like film does.
+pub fn aces_filmic(rgb: [f32; 3], exposure: f32) -> [u8; 3] {
+    const A: f32 = 2.51;
+    const B: f32 = 0.03;
+    const C: f32 = 2.43;
+    const D: f32 = 0.59;
+    const E: f32 = 0.14;
+    rgb.map(|x| {
+        let x = expose(x, exposure);
+        linear_f32_to_srgb8((x * (A * x + B)) / (x * (C * x + D) + E))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Luminance values from black over middle gray and white to very bright highlights.
+    const LUMINANCES: [f32; 12] = [
+        0.0, 0.001, 0.01, 0.05, 0.18, 0.5, 1.0, 2.0, 4.0, 16.0, 1000.0, 65504.0,
+    ];
+
+    #[test]
+    fn monotonic_and_bounded() {
+        for tonemap in [reinhard, aces_filmic] {
+            let outputs = LUMINANCES.map(|x| tonemap([x; 3], 1.0));
+            assert_eq!(outputs[0], [0; 3]);
+            for pair in outputs.windows(2) {
+                assert!(pair[0][0] <= pair[1][0], "{outputs:?}");
+            }
+            for [r, g, b] in outputs {
+                assert!(r == g && g == b);
+            }
+            assert!(outputs[6][0] < 255, "white isn't clipped");
+            assert!(outputs[11][0] >= 254, "highlights approach white");
+        }
+
+        // x / (1 + x) is 0.5 for 1.0, which is 188 in sRGB.
+        assert_eq!(reinhard([1.0; 3], 1.0), [188; 3]);
+        // The ACES curve saturates to white.
+        assert_eq!(aces_filmic([16.0; 3], 1.0), [255; 3]);
+    }
+
+    #[test]
+    fn exposure() {
+        for x in LUMINANCES {
+            assert_eq!(reinhard([x; 3], 2.0), reinhard([x * 2.0; 3], 1.0));
+            assert_eq!(aces_filmic([x; 3], 0.5), aces_filmic([x * 0.5; 3], 1.0));
+        }
+        assert_eq!(reinhard([4.0, 1.0, 0.25], 0.0), [0; 3]);
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        for tonemap in [reinhard, aces_filmic] {
+            assert_eq!(tonemap([-1.0, f32::NAN, f32::NEG_INFINITY], 1.0), [0; 3]);
+            assert_eq!(
+                tonemap([f32::INFINITY; 3], 1.0),
+                tonemap([MAX_INPUT; 3], 1.0)
+            );
+        }
+    }
+}
@@ -50,6 +50,9 @@ pub enum ImageFormat {
 
     /// An Image in QOI Format
     Qoi,
+
+    /// An Image in DDS Format
+    Dds,
 }
 
 impl ImageFormat {
@@ -87,6 +90,7 @@ impl ImageFormat {
                 "pbm" | "pam" | "ppm" | "pgm" | "pnm" => ImageFormat::Pnm,
                 "ff" => ImageFormat::Farbfeld,
                 "qoi" => ImageFormat::Qoi,
+                "dds" => ImageFormat::Dds,
                 _ => return None,
             })
         }
@@ -161,6 +165,8 @@ impl ImageFormat {
             // Qoi's MIME type is being worked on.
             // See: https://github.com/phoboslab/qoi/issues/167
             "image/x-qoi" => Some(ImageFormat::Qoi),
+            // The registered type, and a misspelling some tools use
+            "image/vnd.ms-dds" | "image/vnd-ms.dds" => Some(ImageFormat::Dds),
             _ => None,
         }
     }
@@ -205,6 +211,7 @@ impl ImageFormat {
             // Qoi's MIME type is being worked on.
             // See: https://github.com/phoboslab/qoi/issues/167
             ImageFormat::Qoi => "image/x-qoi",
+            ImageFormat::Dds => "image/vnd.ms-dds",
             // farbfeld's MIME type taken from https://www.wikidata.org/wiki/Q28206109
             ImageFormat::Farbfeld => "application/octet-stream",
         }
@@ -230,6 +237,7 @@ impl ImageFormat {
             ImageFormat::Farbfeld => true,
             ImageFormat::Avif => true,
             ImageFormat::Qoi => true,
+            ImageFormat::Dds => true,
         }
     }
 
@@ -253,6 +261,7 @@ impl ImageFormat {
             ImageFormat::Hdr => true,
             ImageFormat::OpenExr => true,
             ImageFormat::Qoi => true,
+            ImageFormat::Dds => true,
         }
     }
 
@@ -284,6 +293,7 @@ impl ImageFormat {
             // According to: https://aomediacodec.github.io/av1-avif/#mime-registration
             ImageFormat::Avif => &["avif"],
             ImageFormat::Qoi => &["qoi"],
+            ImageFormat::Dds => &["dds"],
         }
     }
 
@@ -309,6 +319,7 @@ impl ImageFormat {
             ImageFormat::Farbfeld => cfg!(feature = "ff"),
             ImageFormat::Avif => cfg!(feature = "avif-native"),
             ImageFormat::Qoi => cfg!(feature = "qoi"),
+            ImageFormat::Dds => cfg!(feature = "dds"),
         }
     }
 
@@ -334,6 +345,7 @@ impl ImageFormat {
             ImageFormat::OpenExr => cfg!(feature = "exr"),
             ImageFormat::Qoi => cfg!(feature = "qoi"),
             ImageFormat::Hdr => cfg!(feature = "hdr"),
+            ImageFormat::Dds => cfg!(feature = "dds"),
         }
     }
 
@@ -354,6 +366,7 @@ impl ImageFormat {
             ImageFormat::OpenExr,
             ImageFormat::Qoi,
             ImageFormat::Hdr,
+            ImageFormat::Dds,
         ]
         .iter()
         .copied()
@@ -390,6 +403,7 @@ mod tests {
         assert_eq!(from_path("./a.Ppm").unwrap(), ImageFormat::Pnm);
         assert_eq!(from_path("./a.pgm").unwrap(), ImageFormat::Pnm);
         assert_eq!(from_path("./a.AViF").unwrap(), ImageFormat::Avif);
+        assert_eq!(from_path("./a.DDS").unwrap(), ImageFormat::Dds);
         assert!(from_path("./a.txt").is_err());
         assert!(from_path("./a").is_err());
     }
@@ -398,7 +412,7 @@ mod tests {
     fn image_formats_are_recognized() {
         use ImageFormat::*;
         const ALL_FORMATS: &[ImageFormat] = &[
-            Avif, Png, Jpeg, Gif, WebP, Pnm, Tiff, Tga, Bmp, Ico, Hdr, Farbfeld, OpenExr,
+            Avif, Png, Jpeg, Gif, WebP, Pnm, Tiff, Tga, Bmp, Ico, Hdr, Farbfeld, OpenExr, Dds,
         ];
         for &format in ALL_FORMATS {
             let mut file = Path::new("file.nothing").to_owned();
@@ -412,6 +426,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn mime_types() {
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Dds] {
+            assert_eq!(
+                ImageFormat::from_mime_type(format.to_mime_type()),
+                Some(format)
+            );
+        }
+        assert_eq!(ImageFormat::Dds.to_mime_type(), "image/vnd.ms-dds");
+        assert_eq!(
+            ImageFormat::from_mime_type("image/vnd.ms-dds"),
+            Some(ImageFormat::Dds)
+        );
+        assert_eq!(
+            ImageFormat::from_mime_type("image/vnd-ms.dds"),
+            Some(ImageFormat::Dds)
+        );
+    }
+
     #[test]
     fn all() {
         let all_formats: HashSet<ImageFormat> = ImageFormat::all().collect();
@@ -90,6 +90,8 @@ pub(crate) fn encoder_for_format<'a, W: Write + Seek>(
         ImageFormat::WebP => Box::new(webp::WebPEncoder::new_lossless(buffered_write)),
         #[cfg(feature = "hdr")]
         ImageFormat::Hdr => Box::new(hdr::HdrEncoder::new(buffered_write)),
+        #[cfg(feature = "dds")]
+        ImageFormat::Dds => Box::new(dds::DdsEncoder::new(buffered_write)),
         #[allow(unreachable_patterns)] // unreachable if all formats are enabled
         _ => {
             return Err(ImageError::Unsupported(
@@ -102,7 +104,7 @@ pub(crate) fn encoder_for_format<'a, W: Write + Seek>(
     })
 }
 
-static MAGIC_BYTES: [(&[u8], &[u8], ImageFormat); 21] = [
+static MAGIC_BYTES: [(&[u8], &[u8], ImageFormat); 22] = [
     (b"\x89PNG\r\n\x1a\n", b"", ImageFormat::Png),
     (&[0xff, 0xd8, 0xff], b"", ImageFormat::Jpeg),
     (b"GIF89a", b"", ImageFormat::Gif),
@@ -128,6 +130,7 @@ static MAGIC_BYTES: [(&[u8], &[u8], ImageFormat); 21] = [
     (b"P6", b"", ImageFormat::Pnm),
     (b"P7", b"", ImageFormat::Pnm),
     (b"farbfeld", b"", ImageFormat::Farbfeld),
+    (b"DDS ", b"", ImageFormat::Dds),
 ];
 
 /// Guess image format from memory block
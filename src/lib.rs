@@ -203,6 +203,7 @@ pub use crate::images::flat;
 /// | ------- | -------- | -----
 /// | `avif`  | AVIF     | Decoding requires the `avif-native` feature, uses the libdav1d C library.
 /// | `bmp`   | BMP      |
+/// | `dds`   | DDS      | Encoding writes BC1 or BC3.
 /// | `exr`   | OpenEXR  |
 /// | `ff`    | Farbfeld |
 /// | `gif`   | GIF      |
@@ -242,6 +243,8 @@ pub mod codecs {
     pub mod avif;
     #[cfg(feature = "bmp")]
     pub mod bmp;
+    #[cfg(feature = "dds")]
+    pub mod dds;
     #[cfg(feature = "ff")]
     pub mod farbfeld;
     #[cfg(feature = "gif")]
@@ -0,0 +1,95 @@
+//! Test loading DDS files through the format-agnostic APIs.
+#![cfg(feature = "dds")]
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat, ImageReader, RgbaImage};
+
+/// Builds a DDS file with a DX10 header and a single surface.
+fn dds_file(dxgi_format: u32, width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+    let mut file = b"DDS ".to_vec();
+    // DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT
+    for value in [124, 0x1007, height, width, 0, 0, 0] {
+        file.extend(u32::to_le_bytes(value));
+    }
+    file.extend([0; 44]);
+    // DDPF_FOURCC with DX10
+    for value in [32, 0x4, u32::from_le_bytes(*b"DX10"), 0, 0, 0, 0, 0] {
+        file.extend(u32::to_le_bytes(value));
+    }
+    // DDSCAPS_TEXTURE
+    for value in [0x1000, 0, 0, 0, 0] {
+        file.extend(u32::to_le_bytes(value));
+    }
+    // A 2D texture
+    for value in [dxgi_format, 3, 0, 1, 0] {
+        file.extend(u32::to_le_bytes(value));
+    }
+    file.extend(data);
+    file
+}
+
+#[test]
+fn round_trip_rgba8() {
+    let image = RgbaImage::from_fn(7, 5, |x, y| {
+        image::Rgba([x as u8 * 30, y as u8 * 50, 99, 200])
+    });
+    // R8G8B8A8_UNORM
+    let file = dds_file(28, 7, 5, image.as_raw());
+
+    assert_eq!(image::guess_format(&file).unwrap(), ImageFormat::Dds);
+    let loaded = image::load_from_memory(&file).unwrap();
+    assert_eq!(loaded, DynamicImage::ImageRgba8(image.clone()));
+
+    let loaded = ImageReader::with_format(Cursor::new(&file), ImageFormat::Dds)
+        .decode()
+        .unwrap();
+    assert_eq!(loaded.into_rgba8(), image);
+}
+
+#[test]
+fn color_types() {
+    // One block of each format, decoded to a 4x4 image
+    let cases = [
+        (71, image::ColorType::Rgba8, 8),   // BC1_UNORM
+        (74, image::ColorType::Rgba8, 16),  // BC2_UNORM
+        (77, image::ColorType::Rgba8, 16),  // BC3_UNORM
+        (80, image::ColorType::L8, 8),      // BC4_UNORM
+        (83, image::ColorType::Rgb8, 16),   // BC5_UNORM
+        (95, image::ColorType::Rgb32F, 16), // BC6H_UF16
+        (98, image::ColorType::Rgba8, 16),  // BC7_UNORM
+    ];
+    for (dxgi_format, color_type, block_size) in cases {
+        let file = dds_file(dxgi_format, 4, 4, &vec![0x55; block_size]);
+        let image = ImageReader::new(Cursor::new(&file))
+            .with_guessed_format()
+            .unwrap()
+            .decode()
+            .unwrap();
+        assert_eq!(image.color(), color_type, "{dxgi_format}");
+        assert_eq!((image.width(), image.height()), (4, 4));
+    }
+}
+
+#[test]
+fn write_to() {
+    let image = RgbaImage::from_fn(9, 6, |x, _| {
+        image::Rgba([x as u8 * 25, 255 - x as u8 * 20, 128, 255])
+    });
+    let mut file = Cursor::new(Vec::new());
+    DynamicImage::ImageRgba8(image.clone())
+        .write_to(&mut file, ImageFormat::Dds)
+        .unwrap();
+
+    let file = file.into_inner();
+    assert_eq!(file[84..88], *b"DXT5");
+    let loaded = image::load_from_memory(&file).unwrap().into_rgba8();
+    assert_eq!(loaded.dimensions(), image.dimensions());
+    for (pixel, loaded) in image.pixels().zip(loaded.pixels()) {
+        assert!(pixel
+            .0
+            .iter()
+            .zip(loaded.0)
+            .all(|(a, b)| a.abs_diff(b) <= 16));
+    }
+}